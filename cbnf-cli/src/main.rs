@@ -0,0 +1,368 @@
+use std::{fs, path::Path, process::ExitCode};
+
+use cbnf::{
+    ambiguity::Checker,
+    compare::Comparison,
+    corpus,
+    coverage::Coverage,
+    diff::Diff,
+    enumerate::{Derivations, Enumerator},
+    generate::Generator,
+    grammar::{Grammar, RuleId},
+    interp::Interpreter,
+    normal::Bnf,
+    report::Renderer,
+    Cbnf,
+};
+
+const USAGE: &str = "\
+usage: cbnf-cli <command> [options]
+
+commands:
+    check <grammar>               report the errors of a grammar
+    test <grammar> <dir>          run the corpus tests in <dir>
+    coverage <grammar> <dir>      report what the corpus in <dir> never exercises
+    generate <grammar>            print random sentences of a grammar
+    enumerate <grammar>           print every short sentence of a grammar
+    ambiguity <grammar>           search a grammar for ambiguous sentences
+    normalize <grammar>           print a grammar in a normal form
+    compare <left> <right>        diff two grammars and search for a sentence
+                                  only one of them accepts
+    diff <left> <right>           diff two grammars, classifying every change
+                                  as widening, narrowing or incompatible
+
+options:
+    --color                       color the output
+    --update                      (test) write the trees of accepted samples
+    --json                        (coverage, diff) print the report as JSON
+    --start=<rule>                (generate, enumerate, ambiguity, normalize,
+                                  compare) the rule to start from
+    --seed=<n>                    (generate, compare) the random seed
+    --count=<n>                   (generate) how many sentences to print,
+                                  (compare) how many random sentences to try
+    --max=<n>                     (enumerate, ambiguity, compare) the most
+                                  terminals in a sentence
+    --derivations                 (enumerate) print the number of derivations
+    --form=<form>                 (normalize) `bnf`, `cnf` or `gnf`
+";
+
+/// Positional arguments and `--flags`, in the order they were given
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Self {
+        let (flags, positional) = args.partition(|a| a.starts_with("--"));
+        Self { positional, flags }
+    }
+
+    /// Takes a flag, returning whether it was given
+    fn flag(&mut self, name: &str) -> bool {
+        let len = self.flags.len();
+        self.flags.retain(|f| f.trim_start_matches('-') != name);
+        self.flags.len() != len
+    }
+
+    /// Takes a `--name=value` option
+    fn option(&mut self, name: &str) -> Option<String> {
+        let prefix = format!("--{name}=");
+        let i = self.flags.iter().position(|f| f.starts_with(&prefix))?;
+        Some(self.flags.remove(i)[prefix.len()..].to_owned())
+    }
+
+    /// Takes a numeric `--name=n` option
+    fn number(&mut self, name: &str) -> Result<Option<u64>, String> {
+        self.option(name)
+            .map(|n| {
+                n.parse()
+                    .map_err(|_| format!("`--{name}` expects a number"))
+            })
+            .transpose()
+    }
+
+    /// Takes the next positional argument
+    fn arg(&mut self, name: &str) -> Result<String, String> {
+        if self.positional.is_empty() {
+            return Err(format!("missing <{name}>"));
+        }
+        Ok(self.positional.remove(0))
+    }
+
+    /// Errors on any argument that was not taken
+    fn finish(self) -> Result<(), String> {
+        match (self.positional.first(), self.flags.first()) {
+            (Some(arg), _) => Err(format!("unexpected argument `{arg}`")),
+            (_, Some(flag)) => Err(format!("unknown option `{flag}`")),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse(std::env::args().skip(1));
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(msg) => {
+            eprintln!("error: {msg}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Runs a command, returning whether it succeeded
+fn run(mut args: Args) -> Result<bool, String> {
+    if args.flag("help") {
+        print!("{USAGE}");
+        return Ok(true);
+    }
+    let command = args.arg("command")?;
+    let color = args.flag("color");
+    let renderer = Renderer::new().color(color);
+    match command.as_str() {
+        "check" => check(args, renderer),
+        "test" => test(args, renderer),
+        "coverage" => coverage(args, renderer),
+        "generate" => generate(args, renderer),
+        "enumerate" => enumerate(args, renderer),
+        "ambiguity" => ambiguity(args, renderer),
+        "normalize" => normalize(args, renderer),
+        "compare" => compare(args, renderer),
+        "diff" => diff(args, renderer),
+        "help" => {
+            print!("{USAGE}");
+            Ok(true)
+        }
+        other => Err(format!("unknown command `{other}`")),
+    }
+}
+
+fn check(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    args.finish()?;
+    let (src, cbnf) = load(&path)?;
+    eprint!("{}", renderer.render_errors(&path, &src, cbnf.errors()));
+    Ok(!cbnf.has_errors())
+}
+
+fn test(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let dir = args.arg("dir")?;
+    let update = args.flag("update");
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let interp = Interpreter::new(&grammar);
+    let report = corpus::run(&interp, Path::new(&dir), update)
+        .map_err(|e| format!("could not run `{dir}`: {e}"))?;
+    print!("{report}");
+    Ok(report.is_ok())
+}
+
+fn coverage(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let dir = args.arg("dir")?;
+    let json = args.flag("json");
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let interp = Interpreter::new(&grammar);
+    let coverage = Coverage::corpus(&interp, Path::new(&dir))
+        .map_err(|e| format!("could not run `{dir}`: {e}"))?;
+    if json {
+        print!("{}", coverage.json(&grammar, &path, &src));
+    } else {
+        print!("{}", coverage.text(&grammar, &path, &src));
+    }
+    Ok(true)
+}
+
+fn generate(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let start = args.option("start");
+    let seed = args.number("seed")?.unwrap_or(0);
+    let count = args.number("count")?.unwrap_or(1);
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let start = start_rule(&grammar, start)?;
+    let mut generator = Generator::new(&grammar).seed(seed);
+    for _ in 0..count {
+        match generator.generate(start) {
+            Ok(sentence) => println!("{sentence}"),
+            Err(e) => {
+                eprintln!("error: {e}");
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn enumerate(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let start = args.option("start");
+    let max = args.number("max")?.unwrap_or(4);
+    let derivations = args.flag("derivations");
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let start = start_rule(&grammar, start)?;
+    let max = usize::try_from(max).map_err(|_| "`--max` is too large")?;
+    let sentences = Enumerator::new(&grammar, start, max).count_derivations(derivations);
+    for sentence in sentences {
+        match sentence.derivations {
+            Some(Derivations::Finite(n)) => print!("{n}\t"),
+            Some(Derivations::Infinite) => print!("inf\t"),
+            None => {}
+        }
+        println!("{}", sentence.text(&grammar, " "));
+    }
+    Ok(true)
+}
+
+fn ambiguity(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let start = args.option("start");
+    let max = args.number("max")?.unwrap_or(6);
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let max = usize::try_from(max).map_err(|_| "`--max` is too large")?;
+    let checker = Checker::new(&grammar).max_len(max);
+    let found = match start {
+        Some(_) => Vec::from_iter(checker.check(start_rule(&grammar, start)?)),
+        None => checker.check_all(),
+    };
+    for ambiguity in &found {
+        let diag = ambiguity.diagnostic(&grammar);
+        eprintln!("{}", renderer.render(&path, &src, &diag));
+    }
+    Ok(found.is_empty())
+}
+
+fn normalize(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let path = args.arg("grammar")?;
+    let start = args.option("start");
+    let form = args.option("form");
+    args.finish()?;
+    let Some((src, cbnf)) = load_valid(&path, renderer)? else {
+        return Ok(false);
+    };
+    let grammar = Grammar::new(&cbnf, &src);
+    let bnf = Bnf::new(&grammar, start_rule(&grammar, start)?);
+    let bnf = match form.as_deref() {
+        None | Some("bnf") => bnf,
+        Some("cnf") => bnf.cnf(),
+        Some("gnf") => bnf.gnf(),
+        Some(other) => return Err(format!("unknown form `{other}`")),
+    };
+    print!("{bnf}");
+    Ok(true)
+}
+
+fn compare(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let left_path = args.arg("left")?;
+    let right_path = args.arg("right")?;
+    let start = args.option("start");
+    let max = args.number("max")?.unwrap_or(4);
+    let count = args.number("count")?.unwrap_or(100);
+    let seed = args.number("seed")?.unwrap_or(0);
+    args.finish()?;
+    let (left, right) = (
+        load_valid(&left_path, renderer)?,
+        load_valid(&right_path, renderer)?,
+    );
+    let (Some((left_src, left)), Some((right_src, right))) = (left, right) else {
+        return Ok(false);
+    };
+    let left = Grammar::new(&left, &left_src);
+    let right = Grammar::new(&right, &right_src);
+    let max = usize::try_from(max).map_err(|_| "`--max` is too large")?;
+    let count = usize::try_from(count).map_err(|_| "`--count` is too large")?;
+    print!("{}", Diff::new(&left, &right).text(&left_src, &right_src));
+    let comparison = Comparison::new(
+        (&left, start_rule(&left, start.clone())?),
+        (&right, start_rule(&right, start)?),
+    )
+    .max_len(max)
+    .samples(count)
+    .seed(seed);
+    let found = comparison.distinguish();
+    match &found {
+        Some(distinction) => println!("{distinction}"),
+        None => println!("no sentence tells the grammars apart"),
+    }
+    Ok(found.is_none())
+}
+
+fn diff(mut args: Args, renderer: Renderer) -> Result<bool, String> {
+    let left_path = args.arg("left")?;
+    let right_path = args.arg("right")?;
+    let json = args.flag("json");
+    args.finish()?;
+    let (left, right) = (
+        load_valid(&left_path, renderer)?,
+        load_valid(&right_path, renderer)?,
+    );
+    let (Some((left_src, left)), Some((right_src, right))) = (left, right) else {
+        return Ok(false);
+    };
+    let diff = Diff::new(
+        &Grammar::new(&left, &left_src),
+        &Grammar::new(&right, &right_src),
+    );
+    if json {
+        print!(
+            "{}",
+            diff.json((&left_path, &left_src), (&right_path, &right_src))
+        );
+    } else {
+        print!("{}", diff.text(&left_src, &right_src));
+    }
+    Ok(diff.class().is_none_or(|c| !c.is_breaking()))
+}
+
+/// The rule named by `--start`, or the `#[start]` rule, or the first rule
+fn start_rule(grammar: &Grammar, name: Option<String>) -> Result<RuleId, String> {
+    name.map_or_else(
+        || {
+            grammar
+                .start()
+                .ok_or_else(|| "the grammar has no rules".into())
+        },
+        |name| {
+            grammar
+                .rule(&name)
+                .ok_or_else(|| format!("unknown rule `{name}`"))
+        },
+    )
+}
+
+/// Loads a grammar, rendering its errors and returning `None` if it has any
+fn load_valid(path: &str, renderer: Renderer) -> Result<Option<(String, Cbnf)>, String> {
+    let (src, cbnf) = load(path)?;
+    if cbnf.has_errors() {
+        eprint!("{}", renderer.render_errors(path, &src, cbnf.errors()));
+        return Ok(None);
+    }
+    Ok(Some((src, cbnf)))
+}
+
+fn load(path: &str) -> Result<(String, Cbnf), String> {
+    let src = fs::read_to_string(path).map_err(|e| format!("could not read `{path}`: {e}"))?;
+    let cbnf = Cbnf::parse(&src);
+    Ok((src, cbnf))
+}
@@ -0,0 +1,507 @@
+//! code actions
+
+use cbnf::parser::error::{ErrorKind, InvalidLiteral};
+use cbnf::util::valid_id;
+use cbnf::{span::BSpan, Rule, RuleKind, Term};
+use tower_lsp::lsp_types::*;
+
+use crate::Document;
+
+#[cfg(test)]
+mod test;
+
+/// The title and edits of a code action
+type Fix = (String, Vec<TextEdit>);
+
+const fn overlaps(a: BSpan, b: BSpan) -> bool {
+    a.from <= b.to && b.from <= a.to
+}
+
+impl Document {
+    /// Quick fixes for every problem touching `span`
+    pub(crate) fn quick_fixes(&self, uri: &Url, span: BSpan) -> Vec<CodeActionOrCommand> {
+        let mut actions = Vec::new();
+        for error in self.errors.iter().filter(|e| overlaps(e.span(), span)) {
+            let diag = self.error_diagnostic(uri, error);
+            let fix = match error.kind {
+                ErrorKind::UnclosedRule => Some(self.close_rule(error.span())),
+                ErrorKind::Unterminated => self.close_group(error.span()),
+                ErrorKind::InvalidLit(InvalidLiteral::Numeric) => {
+                    Some(self.quote_number(error.span()))
+                }
+                _ => None,
+            };
+            actions.extend(fix.map(|(title, edits)| quick_fix(uri, title, edits, diag, true)));
+        }
+        for unknown in self.unknown_terms().filter(|&s| overlaps(s, span)) {
+            let diag = self.unknown_diagnostic(unknown);
+            let name = unknown.slice(&self.source);
+            let edit = self.append(&format!("{name} {{\n    nil\n}}\n"));
+            let title = format!("Create rule `{name}`");
+            actions.push(quick_fix(uri, title, vec![edit], diag, false));
+        }
+        for extra in self.extras.iter().filter(|r| overlaps(r.span, span)) {
+            let name = extra.name.slice(&self.source);
+            let diag = self.duplicate_diagnostic(extra);
+            let remove = self.remove_rule(extra);
+            let title = format!("Remove duplicate rule `{name}`");
+            actions.push(quick_fix(
+                uri,
+                title,
+                vec![remove.clone()],
+                diag.clone(),
+                false,
+            ));
+            if let Some(merge) = self.merge_rule(extra) {
+                let title = format!("Merge into the first `{name}`");
+                actions.push(quick_fix(uri, title, vec![merge, remove], diag, false));
+            }
+        }
+        actions
+    }
+
+    pub(crate) fn insert(&self, pos: u32, text: impl Into<String>) -> TextEdit {
+        self.replace(BSpan::empty(pos), text)
+    }
+
+    pub(crate) fn replace(&self, span: BSpan, text: impl Into<String>) -> TextEdit {
+        TextEdit {
+            range: self.get_range(span),
+            new_text: text.into(),
+        }
+    }
+
+    /// Adds `text` to the end of the document, on its own paragraph
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn append(&self, text: &str) -> TextEdit {
+        let sep = match () {
+            () if self.source.is_empty() || self.source.ends_with("\n\n") => "",
+            () if self.source.ends_with('\n') => "\n",
+            () => "\n\n",
+        };
+        self.insert(self.source.len() as u32, format!("{sep}{text}"))
+    }
+
+    fn close_rule(&self, span: BSpan) -> Fix {
+        let text = if self.source[..span.to as usize].ends_with('\n') {
+            "}\n"
+        } else {
+            "\n}"
+        };
+        let edit = self.insert(span.to, text);
+        ("Insert missing `}`".into(), vec![edit])
+    }
+
+    fn close_group(&self, span: BSpan) -> Option<Fix> {
+        let (i, list) = self.terms.iter().enumerate().find_map(|(i, t)| match t {
+            Term::Group(list) if list.span() == span => Some((i, *list)),
+            _ => None,
+        })?;
+        // right after the last item of the group, leaving out unclosed groups
+        // within it as their span runs on to the end of the rule
+        let mut pos = span.from + 1;
+        let mut j = i + 1;
+        while j < list.terms().to as usize {
+            match self.terms[j] {
+                Term::Group(inner) if !inner.span().slice(&self.source).ends_with(')') => {
+                    j = inner.terms().to as usize;
+                }
+                term => {
+                    pos = pos.max(term.span().to);
+                    j += 1;
+                }
+            }
+        }
+        Some(("Close group".into(), vec![self.insert(pos, ")")]))
+    }
+
+    fn quote_number(&self, span: BSpan) -> Fix {
+        let text = span.slice(&self.source);
+        let edit = self.replace(span, format!("\"{text}\""));
+        (format!("Convert `{text}` into a string"), vec![edit])
+    }
+
+    /// Removes a rule along with the line break following it
+    fn remove_rule(&self, rule: &Rule) -> TextEdit {
+        let mut span = rule.span;
+        if self.source[span.to as usize..].starts_with('\n') {
+            span.to += 1;
+        }
+        self.replace(span, "")
+    }
+
+    /// Adds the body of `extra` as another alternative of the first definition
+    fn merge_rule(&self, extra: &Rule) -> Option<TextEdit> {
+        let first = self.rules.get(extra.name.slice(&self.source))?;
+        let inner = |rule: &Rule| {
+            let span = rule.expr?.span();
+            let text = span.slice(&self.source);
+            text.ends_with('}')
+                .then(|| BSpan::new(span.from + 1, span.to - 1))
+        };
+        let (into, from) = (inner(first)?, inner(extra)?);
+        let added = from.slice(&self.source).trim();
+        if added.is_empty() {
+            return None;
+        }
+        let body = into.slice(&self.source).trim_end();
+        #[allow(clippy::cast_possible_truncation)]
+        Some(if body.trim_start().is_empty() {
+            self.replace(into, format!(" {added} "))
+        } else {
+            self.insert(into.from + body.len() as u32, format!(" | {added}"))
+        })
+    }
+}
+
+impl Document {
+    /// Refactorings applicable at `span`
+    pub(crate) fn refactors(&self, uri: &Url, span: BSpan) -> Vec<CodeActionOrCommand> {
+        let actions = [
+            self.inline_rule(span.from),
+            self.extract_rule(span),
+            self.extract_literal(span.from),
+        ];
+        actions
+            .into_iter()
+            .flatten()
+            .map(|(title, edits)| refactor(uri, title, edits))
+            .collect()
+    }
+
+    /// The name of the rule declared or referenced at `pos`
+    fn rule_at(&self, pos: u32) -> Option<(&str, Rule)> {
+        let span = match self.get_rule(pos) {
+            Some(rule) => rule.name,
+            None => match self.get_token(pos)? {
+                Term::Ident(span) => span,
+                _ => return None,
+            },
+        };
+        let name = span.slice(&self.source);
+        Some((name, *self.rules.get(name)?))
+    }
+
+    /// The text within the braces of a rule, if it was closed
+    fn body(&self, rule: &Rule) -> Option<BSpan> {
+        let span = rule.expr?.span();
+        span.slice(&self.source)
+            .ends_with('}')
+            .then(|| BSpan::new(span.from + 1, span.to - 1))
+    }
+
+    /// The text of `span` with the comments within it left out, as a line
+    /// comment would swallow whatever follows it once moved
+    fn without_comments(&self, span: BSpan) -> String {
+        let mut comments: Vec<_> = self
+            .comments
+            .iter()
+            .map(cbnf::Comment::span)
+            .chain(self.docs.iter().map(cbnf::DocComment::span))
+            .filter(|c| span.from <= c.from && c.to <= span.to)
+            .collect();
+        comments.sort_unstable_by_key(|c| c.from);
+        let mut text = String::new();
+        let mut from = span.from;
+        for comment in comments {
+            text.push_str(BSpan::new(from, comment.from).slice(&self.source));
+            text.truncate(text.trim_end().len());
+            from = comment.to;
+        }
+        text.push_str(BSpan::new(from, span.to).slice(&self.source));
+        text
+    }
+
+    fn rule_terms(&self, rule: &Rule) -> &[Term] {
+        rule.expr
+            .map_or(&[], |list| &self.terms[list.terms().range()])
+    }
+
+    /// The terms directly within the body of a rule, not counting those
+    /// within other terms
+    fn top_level(&self, rule: &Rule) -> usize {
+        let Some(list) = rule.expr else {
+            return 0;
+        };
+        let (mut i, mut count) = (list.terms().from as usize, 0);
+        while i < list.terms().to as usize {
+            i = match self.terms[i] {
+                Term::Or(_) => i + 1,
+                term => term.terms().map_or(i + 1, |t| t.to as usize),
+            };
+            count += 1;
+        }
+        count
+    }
+
+    /// The innermost term holding the term at `index`
+    fn parent(&self, index: usize) -> Option<Term> {
+        self.terms[..index].iter().rev().copied().find(|t| {
+            !matches!(t, Term::Or(_)) && t.terms().is_some_and(|r| r.range().contains(&index))
+        })
+    }
+
+    /// Whether a body of several terms must be grouped to replace the
+    /// reference at `index`, which stands for a single term
+    fn needs_group(&self, index: usize) -> bool {
+        // the argument of a call, the term looked ahead at or a labeled one
+        let single = matches!(
+            self.parent(index),
+            Some(Term::Call(_) | Term::And(_) | Term::Not(_) | Term::Label(..))
+        );
+        // a term on either side of a `,`
+        let adjacent = |i: Option<usize>| {
+            i.and_then(|i| self.terms.get(i))
+                .is_some_and(|t| matches!(t, Term::Adjacent(_)))
+        };
+        single || adjacent(index.checked_sub(1)) || adjacent(Some(index + 1))
+    }
+
+    /// Whether `name` is used as a parameterized rule, as in `name<..>`
+    fn is_called(&self, name: &str) -> bool {
+        self.terms.iter().any(|t| match t {
+            // the terms of a call start with the call itself
+            Term::Call(list) => self
+                .terms
+                .get(list.terms().from as usize + 1)
+                .is_some_and(|callee| callee.span().slice(&self.source) == name),
+            _ => false,
+        })
+    }
+
+    /// Replaces every reference to a rule with its body, removing the rule
+    fn inline_rule(&self, pos: u32) -> Option<Fix> {
+        let (name, rule) = self.rule_at(pos)?;
+        let body = self.without_comments(self.body(&rule)?);
+        let body = body.trim();
+        let terms = self.rule_terms(&rule);
+        let recursive = terms
+            .iter()
+            .any(|t| matches!(t, Term::Ident(s) if s.slice(&self.source) == name));
+        let duplicated = self
+            .extras
+            .iter()
+            .any(|r| r.name.slice(&self.source) == name);
+        // inlining would change how lexical rules skip whitespace, drop the
+        // operator levels of `@prec` rules and the arguments of
+        // parameterized ones
+        let special = rule.kind != RuleKind::Syntactic;
+        let parameterized = !self.params[rule.params.range()].is_empty();
+        if body.is_empty()
+            || recursive
+            || duplicated
+            || special
+            || parameterized
+            || self.is_called(name)
+        {
+            return None;
+        }
+        let alternatives = terms.iter().any(|t| matches!(t, Term::Or(_)));
+        let several = self.top_level(&rule) > 1;
+        let mut edits: Vec<_> = self
+            .terms
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| matches!(t, Term::Ident(s) if s.slice(&self.source) == name))
+            .map(|(i, t)| {
+                let text = if alternatives || several && self.needs_group(i) {
+                    format!("({body})")
+                } else {
+                    body.to_owned()
+                };
+                self.replace(t.span(), text)
+            })
+            .collect();
+        if edits.is_empty() {
+            return None;
+        }
+        edits.push(self.remove_rule(&rule));
+        Some((format!("Inline rule `{name}`"), edits))
+    }
+
+    /// Moves the selected terms into a new rule
+    fn extract_rule(&self, span: BSpan) -> Option<Fix> {
+        if span.is_empty() {
+            return None;
+        }
+        let rule = self
+            .rules
+            .values()
+            .find(|r| r.span.from <= span.from && span.to <= r.span.to)?;
+        let body = self.body(rule)?;
+        let terms = self.rule_terms(rule);
+        let contained = |t: BSpan| span.from <= t.from && t.to <= span.to;
+        let selected = terms
+            .iter()
+            .map(Term::span)
+            .filter(|&t| contained(t))
+            .reduce(|a, b| a.from(a.from.min(b.from)).to(a.to.max(b.to)))?;
+        // the operators of a level are alternatives, not a sequence
+        if self.levels[rule.levels.range()]
+            .iter()
+            .any(|level| overlaps(level.span, selected))
+        {
+            return None;
+        }
+        let whole = selected == self.trimmed(body);
+        let within = |t: BSpan| selected.from <= t.from && t.to <= selected.to;
+        let groups: Vec<_> = terms
+            .iter()
+            .filter(|t| matches!(t, Term::Group(_)) && within(t.span()))
+            .map(Term::span)
+            .collect();
+        let valid = terms.iter().all(|t| {
+            let t = t.span();
+            let outside = t.to <= selected.from || selected.to <= t.from;
+            let around = t.from <= selected.from && selected.to <= t.to;
+            outside || around || within(t)
+        }) && terms.iter().all(|t| match t {
+            // an alternative cut from its siblings changes the meaning
+            Term::Or(_) if within(t.span()) => {
+                whole
+                    || groups
+                        .iter()
+                        .any(|g| g.from < t.span().from && t.span().to <= g.to)
+            }
+            _ => true,
+        });
+        if !valid {
+            return None;
+        }
+        let group = terms
+            .iter()
+            .find(|t| matches!(t, Term::Group(_)) && t.span() == selected);
+        let extracted = group.map_or(selected, |g| BSpan::new(g.span().from + 1, g.span().to - 1));
+        let extracted = extracted.slice(&self.source).trim();
+        let name = self.fresh_name("new_rule");
+        // the parameters used in the selection are passed on to the new rule
+        let params: Vec<_> = self.params[rule.params.range()]
+            .iter()
+            .map(|p| p.slice(&self.source))
+            .filter(|&p| {
+                terms.iter().any(
+                    |t| matches!(t, Term::Ident(s) if within(*s) && s.slice(&self.source) == p),
+                )
+            })
+            .collect();
+        let head = if params.is_empty() {
+            name.clone()
+        } else {
+            format!("{name}<{}>", params.join(", "))
+        };
+        // terms of a lexical rule may only refer to lexical rules
+        let kind = if rule.kind.is_lexical() {
+            "@lexical "
+        } else {
+            ""
+        };
+        let edits = vec![
+            self.replace(selected, head.clone()),
+            self.insert(
+                rule.span.to,
+                format!("\n{kind}{head} {{\n    {extracted}\n}}"),
+            ),
+        ];
+        Some((format!("Extract into rule `{name}`"), edits))
+    }
+
+    /// Replaces every occurrence of the literal at `pos` with a new rule
+    fn extract_literal(&self, pos: u32) -> Option<Fix> {
+        let Term::Literal(span, _) = self.get_token(pos)? else {
+            return None;
+        };
+        let literal = span.slice(&self.source);
+        let base = literal.trim_start_matches(['b', 'i']);
+        let base = base.trim_matches(|c| c == '"' || c == '\'');
+        let name = self.fresh_name(if valid_id(base) { base } else { "literal" });
+        let mut edits: Vec<_> = self
+            .literals(literal)
+            .map(|span| self.replace(span, name.clone()))
+            .collect();
+        let lexical = self.literals(literal).any(|span| {
+            self.rules
+                .values()
+                .chain(&self.extras)
+                .any(|r| r.kind.is_lexical() && r.span.from <= span.from && span.to <= r.span.to)
+        });
+        let kind = if lexical { "@lexical " } else { "" };
+        edits.push(self.append(&format!("{kind}{name} {{\n    {literal}\n}}\n")));
+        Some((format!("Replace all `{literal}` with rule `{name}`"), edits))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn trimmed(&self, span: BSpan) -> BSpan {
+        let text = span.slice(&self.source);
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len();
+        BSpan::new(span.from + start as u32, span.from + end as u32)
+    }
+
+    /// Every literal term written exactly as `literal`
+    pub(crate) fn literals<'a>(&'a self, literal: &'a str) -> impl Iterator<Item = BSpan> + 'a {
+        self.terms.iter().filter_map(move |t| match t {
+            Term::Literal(span, _) if span.slice(&self.source) == literal => Some(*span),
+            _ => None,
+        })
+    }
+
+    /// Replaces every occurrence of a literal
+    ///
+    /// `new` may be given either quoted or as the plain text to match.
+    pub(crate) fn rename_literal(&self, span: BSpan, new: &str) -> Vec<TextEdit> {
+        let quoted = ['"', '\'']
+            .iter()
+            .any(|&q| new.len() > 1 && new.starts_with(q) && new.ends_with(q));
+        let new = if quoted {
+            new.to_owned()
+        } else {
+            format!("\"{}\"", new.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        self.literals(span.slice(&self.source))
+            .map(|span| self.replace(span, new.clone()))
+            .collect()
+    }
+
+    /// `base`, suffixed with a number if a rule with that name already exists
+    fn fresh_name(&self, base: &str) -> String {
+        let mut name = base.to_owned();
+        let mut i = 0;
+        while self.rules.contains_key(&name) {
+            i += 1;
+            name = format!("{base}_{i}");
+        }
+        name
+    }
+}
+
+fn quick_fix(
+    uri: &Url,
+    title: String,
+    edits: Vec<TextEdit>,
+    diag: Diagnostic,
+    preferred: bool,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diag]),
+        edit: Some(WorkspaceEdit {
+            changes: Some([(uri.clone(), edits)].into()),
+            ..Default::default()
+        }),
+        is_preferred: Some(preferred),
+        ..Default::default()
+    })
+}
+
+fn refactor(uri: &Url, title: String, edits: Vec<TextEdit>) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::REFACTOR),
+        edit: Some(WorkspaceEdit {
+            changes: Some([(uri.clone(), edits)].into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,456 @@
+use std::fmt::Write;
+
+use cbnf::{span::BSpan, Cbnf};
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+use tower_lsp::lsp_types::{CodeActionOrCommand, TextEdit};
+
+use crate::{
+    test::{document, uri},
+    Document,
+};
+
+/// The source of `doc` with `edits` made to it
+fn apply(doc: &Document, edits: &[TextEdit]) -> String {
+    let mut edits: Vec<_> = edits
+        .iter()
+        .map(|e| {
+            let (from, to) = (doc.get_point(e.range.start), doc.get_point(e.range.end));
+            (from as usize, to as usize, e.new_text.as_str())
+        })
+        .collect();
+    // edits at the same position are made in order, so the later ones
+    // go first
+    edits.sort_by_key(|&(from, to, _)| (from, to));
+    let mut out = doc.source.clone();
+    for (from, to, text) in edits.into_iter().rev() {
+        out.replace_range(from..to, text);
+    }
+    out
+}
+
+/// The title of every code action over the text between `«` and `»` in
+/// `src`, with the source after its edits
+fn edited(src: &str) -> Vec<(String, String)> {
+    let from = src.find('«').expect("a selection");
+    let to = src.find('»').expect("a selection") - '«'.len_utf8();
+    let src = src.replace(['«', '»'], "");
+    let doc = document(&src);
+    #[allow(clippy::cast_possible_truncation)]
+    let span = BSpan::new(from as u32, to as u32);
+    let mut actions = doc.quick_fixes(&uri(), span);
+    actions.extend(doc.refactors(&uri(), span));
+    actions
+        .into_iter()
+        .map(|action| {
+            let CodeActionOrCommand::CodeAction(action) = action else {
+                panic!("a command instead of an action");
+            };
+            let mut changes = action.edit.unwrap().changes.unwrap();
+            let edits = changes.remove(&uri()).unwrap();
+            (action.title, apply(&doc, &edits))
+        })
+        .collect()
+}
+
+fn actions(src: &str) -> String {
+    let mut out = String::new();
+    for (title, text) in edited(src) {
+        let _ = write!(out, "# {title}\n{text}\n");
+    }
+    out
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, expect: Expect) {
+    expect.assert_eq(&actions(src));
+}
+
+/// The titles of the code actions over the selection of `src`
+fn titles(src: &str) -> Vec<String> {
+    edited(src).into_iter().map(|(title, _)| title).collect()
+}
+
+/// Asserts that every code action over the selection of `src` leaves a
+/// grammar without errors
+fn assert_valid(src: &str) {
+    for (title, text) in edited(src) {
+        let errors = Cbnf::parse(&text).errors;
+        assert_eq!(errors, [], "{title}:\n{text}");
+    }
+}
+
+/// Whether a code action starting with `title` is offered over the
+/// selection of `src`
+fn offered(src: &str, title: &str) -> bool {
+    titles(src).iter().any(|t| t.starts_with(title))
+}
+
+#[test]
+fn create_rule() {
+    check(
+        "a { «b» }\n",
+        expect![[r"
+            # Create rule `b`
+            a { b }
+
+            b {
+                nil
+            }
+
+            # Extract into rule `new_rule`
+            a { new_rule }
+            new_rule {
+                b
+            }
+
+        "]],
+    );
+}
+
+#[test]
+fn duplicate_rule() {
+    check(
+        "a { \"x\" }\n«a { \"y\" }»\nb { a }\n",
+        expect![[r#"
+            # Remove duplicate rule `a`
+            a { "x" }
+            b { a }
+
+            # Merge into the first `a`
+            a { "x" | "y" }
+            b { a }
+
+        "#]],
+    );
+    // an empty duplicate has nothing to merge
+    assert_eq!(
+        titles("a { \"x\" }\n«a { }»\n"),
+        ["Remove duplicate rule `a`"]
+    );
+}
+
+#[test]
+fn close_rule() {
+    check(
+        "a «»{ \"x\"",
+        expect![[r#"
+            # Insert missing `}`
+            a { "x"
+            }
+        "#]],
+    );
+    check(
+        "a «»{ \"x\"\n",
+        expect![[r#"
+            # Insert missing `}`
+            a { "x"
+            }
+
+        "#]],
+    );
+}
+
+#[test]
+fn close_group() {
+    check(
+        "a { «»(\"x\" \"y\" }\n",
+        expect![[r#"
+            # Close group
+            a { ("x" "y") }
+
+        "#]],
+    );
+    // the outer group closes before the one nested in it, which is left
+    // unclosed
+    check(
+        "a { «»(\"x\" (\"y\" }\n",
+        expect![[r#"
+            # Close group
+            a { ("x") ("y" }
+
+        "#]],
+    );
+}
+
+#[test]
+fn quote_number() {
+    check(
+        "a { «12» }\n",
+        expect![[r#"
+            # Convert `12` into a string
+            a { "12" }
+
+        "#]],
+    );
+}
+
+#[test]
+fn inline_rule() {
+    check(
+        "a { «»b \"y\" }\nb { \"x\" | \"z\" }\n",
+        expect![[r#"
+            # Inline rule `b`
+            a { ("x" | "z") "y" }
+
+        "#]],
+    );
+    check(
+        "a { b «»b }\nb { \"x\" }\nc { !b b:b }\n",
+        expect![[r#"
+            # Inline rule `b`
+            a { "x" "x" }
+            c { !"x" b:"x" }
+
+        "#]],
+    );
+    check(
+        "a { «»b }\nb { \"x\" \"y\" }\nc { !b b:b d<b> }\nd<t> { t }\n",
+        expect![[r#"
+            # Inline rule `b`
+            a { "x" "y" }
+            c { !("x" "y") b:("x" "y") d<("x" "y")> }
+            d<t> { t }
+
+        "#]],
+    );
+}
+
+#[test]
+fn inline_rule_comments() {
+    check(
+        "a { «»b \"y\" }\nb { \"x\" // note\n}\n",
+        expect![[r#"
+            # Inline rule `b`
+            a { "x" "y" }
+
+        "#]],
+    );
+    check(
+        "a { «»b }\nb {\n    // first\n    \"x\" /* or */ | \"y\" /// last\n}\n",
+        expect![[r#"
+            # Inline rule `b`
+            a { ("x" | "y") }
+
+        "#]],
+    );
+}
+
+#[test]
+fn inline_rule_refused() {
+    // recursive, duplicated, lexical, parameterized and called rules
+    for src in [
+        "a { «»b }\nb { \"x\" b | nil }\n",
+        "a { «»b }\nb { \"x\" }\nb { \"y\" }\n",
+        "a { «»b }\n@lexical b { \"x\" }\n",
+        "a { «»b<\"x\"> }\nb<t> { t }\n",
+        "a { «»b<\"x\"> }\nb { \"x\" }\n",
+        "a { «»b }\nb { }\n",
+    ] {
+        assert!(!offered(src, "Inline"), "{src}");
+    }
+}
+
+#[test]
+fn extract_rule() {
+    check(
+        "a { «\"x\" \"y\"» \"z\" }\n",
+        expect![[r#"
+            # Extract into rule `new_rule`
+            a { new_rule "z" }
+            new_rule {
+                "x" "y"
+            }
+
+            # Replace all `"x"` with rule `x`
+            a { x "y" "z" }
+
+            x {
+                "x"
+            }
+
+        "#]],
+    );
+    // a group is extracted without its parentheses
+    check(
+        "a { \"x\" «(\"y\" | \"z\")» }\nnew_rule { nil }\n",
+        expect![[r#"
+            # Extract into rule `new_rule_1`
+            a { "x" new_rule_1 }
+            new_rule_1 {
+                "y" | "z"
+            }
+            new_rule { nil }
+
+        "#]],
+    );
+    // alternatives can't be cut from their siblings, nor terms from their
+    // group
+    assert!(!offered("a { «\"x\" | \"y\"» | \"z\" }\n", "Extract into"));
+    assert!(offered("a { («\"x\"» \"y\") }\n", "Extract into"));
+    assert!(!offered("a { (\"x\" «\"y\") \"z\"» }\n", "Extract into"));
+}
+
+#[test]
+fn extract_rule_parameters() {
+    check(
+        "s<item, sep> { item «(sep item)» }\n",
+        expect![[r"
+            # Extract into rule `new_rule`
+            s<item, sep> { item new_rule<item, sep> }
+            new_rule<item, sep> {
+                sep item
+            }
+
+        "]],
+    );
+    check(
+        "s<item> { item «(\",\" item)» }\n",
+        expect![[r#"
+            # Extract into rule `new_rule`
+            s<item> { item new_rule<item> }
+            new_rule<item> {
+                "," item
+            }
+
+        "#]],
+    );
+    check(
+        "list<item> { «list<item> \",\"» item | nil }\n",
+        expect![[r#"
+            # Extract into rule `new_rule`
+            list<item> { new_rule<item> item | nil }
+            new_rule<item> {
+                list<item> ","
+            }
+
+        "#]],
+    );
+}
+
+#[test]
+fn extract_lexical() {
+    for src in [
+        "@lexical id { «\"a\" \"b\"» \"c\" }\n",
+        "@trivia comment { «\"#\"» text }\ntext {}\na { \"#\" }\n",
+        "x { «»\"a\" } @lexical id { \"a\" \"b\" }\n",
+    ] {
+        assert_valid(src);
+    }
+    check(
+        "@lexical id { «\"a\" \"b\"» \"c\" }\n",
+        expect![[r#"
+            # Extract into rule `new_rule`
+            @lexical id { new_rule "c" }
+            @lexical new_rule {
+                "a" "b"
+            }
+
+            # Replace all `"a"` with rule `a`
+            @lexical id { a "b" "c" }
+
+            @lexical a {
+                "a"
+            }
+
+        "#]],
+    );
+    check(
+        "@trivia comment { «\"#\"» text }\ntext {}\n",
+        expect![[r##"
+            # Extract into rule `new_rule`
+            @trivia comment { new_rule text }
+            @lexical new_rule {
+                "#"
+            }
+            text {}
+
+            # Replace all `"#"` with rule `literal`
+            @trivia comment { literal text }
+            text {}
+
+            @lexical literal {
+                "#"
+            }
+
+        "##]],
+    );
+    check(
+        "x { «»\"a\" } @lexical id { \"a\" \"b\" }\n",
+        expect![[r#"
+            # Replace all `"a"` with rule `a`
+            x { a } @lexical id { a "b" }
+
+            @lexical a {
+                "a"
+            }
+
+        "#]],
+    );
+}
+
+#[test]
+fn extract_prec() {
+    let src = "@prec e { atom | left «\"+\" \"-\"» | right \"^\" }\natom { \"1\" }\n";
+    assert!(!offered(src, "Extract into"));
+    let src = "@prec e { «atom | left \"+\"» }\natom { \"1\" }\n";
+    assert!(!offered(src, "Extract into"));
+    let src = "@prec e { «atom \"!\"» | left \"+\" }\natom { \"1\" }\n";
+    assert_valid(src);
+    check(
+        src,
+        expect![[r#"
+            # Inline rule `atom`
+            @prec e { "1" "!" | left "+" }
+
+            # Extract into rule `new_rule`
+            @prec e { new_rule | left "+" }
+            new_rule {
+                atom "!"
+            }
+            atom { "1" }
+
+        "#]],
+    );
+}
+
+#[test]
+fn extract_literal() {
+    check(
+        "a { «»\"x\" b } b { \"x\" \"y\" }\n",
+        expect![[r#"
+            # Replace all `"x"` with rule `x`
+            a { x b } b { x "y" }
+
+            x {
+                "x"
+            }
+
+        "#]],
+    );
+    check(
+        "a { «»i\"+\" }\n",
+        expect![[r#"
+            # Replace all `i"+"` with rule `literal`
+            a { literal }
+
+            literal {
+                i"+"
+            }
+
+        "#]],
+    );
+}
+
+#[test]
+fn rename_literal() {
+    let src = r#"a { "x" b "x" 'x' } b { "x" }"#;
+    let doc = document(src);
+    let rename = |new| apply(&doc, &doc.rename_literal(BSpan::new(4, 7), new));
+    assert_eq!(rename("y"), r#"a { "y" b "y" 'x' } b { "y" }"#);
+    assert_eq!(
+        rename(r#"a"b"#),
+        r#"a { "a\"b" b "a\"b" 'x' } b { "a\"b" }"#
+    );
+    assert_eq!(rename("'z'"), r"a { 'z' b 'z' 'x' } b { 'z' }");
+}
@@ -1,12 +1,22 @@
 #![allow(clippy::significant_drop_tightening)]
 use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
 
+use cbnf::ambiguity::{Ambiguity, Checker};
+use cbnf::coverage::{Coverage, Uncovered};
+use cbnf::grammar::Grammar;
 use cbnf::indexmap::IndexMap;
-use cbnf::util::valid_id;
-use cbnf::{span::BSpan, Cbnf, Rule, Term};
+use cbnf::interp::Interpreter;
+use cbnf::util::{is_id_continue, valid_id};
+use cbnf::{span::BSpan, Cbnf, Level, List, Rule, Term, Value};
 use dashmap::DashMap;
 use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer};
 
+mod actions;
+#[cfg(test)]
+mod test;
+
 // TODO: consider parsing newlines at a different point in the process
 // Add LSpan (Line Span) to cbnf
 
@@ -20,12 +30,49 @@ use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer};
 // of completely recomputing it each time
 
 // TODO: add qol things:
-// = Generate new rule, at top level & when unkown rules have been detected
+// = Generate new rule at top level
 
 #[derive(Debug)]
 pub struct Backend {
     pub client: Client,
     pub forms: DashMap<Url, Document>,
+    pub config: RwLock<Config>,
+}
+
+/// Settings sent by the client, either as initialization options or through
+/// `workspace/didChangeConfiguration`, optionally nested under `cbnf`
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Search every rule for ambiguous sentences, off by default as it can
+    /// be slow on large grammars
+    pub ambiguity: bool,
+    /// A corpus directory (see `cbnf::corpus`), relative to the grammar,
+    /// whose coverage is shown as code lenses
+    pub corpus: Option<String>,
+}
+
+impl Config {
+    fn update(&mut self, settings: &serde_json::Value) {
+        let settings = settings.get("cbnf").unwrap_or(settings);
+        if let Some(ambiguity) = settings
+            .get("ambiguity")
+            .and_then(serde_json::Value::as_bool)
+        {
+            self.ambiguity = ambiguity;
+        }
+        match settings.get("corpus") {
+            Some(serde_json::Value::String(dir)) => self.corpus = Some(dir.clone()),
+            Some(serde_json::Value::Null) => self.corpus = None,
+            _ => {}
+        }
+    }
+
+    /// The corpus directory of the grammar at `uri`
+    fn corpus_dir(&self, uri: &Url) -> Option<PathBuf> {
+        let corpus = self.corpus.as_ref()?;
+        let path = uri.to_file_path().ok()?;
+        Some(path.parent()?.join(corpus))
+    }
 }
 
 impl Backend {
@@ -45,6 +92,11 @@ pub struct Document {
     docs: Vec<cbnf::DocComment>,
     errors: Vec<cbnf::parser::error::Error>,
     terms: Vec<Term>,
+    params: Vec<BSpan>,
+    levels: Vec<Level>,
+    /// The corpus coverage, only measured on open, save and configuration
+    /// changes as it reads the corpus from disk
+    coverage: Option<Coverage>,
     cache: Cache,
 }
 
@@ -52,6 +104,7 @@ pub struct Document {
 pub struct Cache {
     diagnostics: Vec<Diagnostic>,
     completions: Vec<CompletionItem>,
+    code_lenses: Vec<CodeLens>,
 }
 
 fn find_lines(source: &str) -> Vec<u32> {
@@ -66,78 +119,94 @@ fn find_lines(source: &str) -> Vec<u32> {
     lines
 }
 
-/// returns `Some((true, ..))` if target is first line
+/// returns the line `target` lies on, a line break belongs to the line it ends
 const fn find_line(lbs: &[u32], target: u32) -> u32 {
     let mut i = 0;
     loop {
         if i as usize >= lbs.len() {
             break i;
         }
-        if lbs[i as usize] > target {
+        if lbs[i as usize] >= target {
             break i;
         }
         i += 1;
     }
 }
 
-const fn get_range(breaks: &[u32], span: BSpan) -> Range {
-    let line_from = find_line(breaks, span.from);
-    let line_to = find_line(breaks, span.to);
-    let from = match line_from {
-        0 => span.from,
-        _ => span.from - breaks[line_from as usize - 1],
-    };
-    let to = match line_to {
-        0 => span.to,
-        _ => span.to - breaks[line_to as usize - 1],
+const fn get_position(breaks: &[u32], pos: u32) -> Position {
+    let line = find_line(breaks, pos);
+    let character = match line {
+        0 => pos,
+        _ => pos - breaks[line as usize - 1] - 1,
     };
+    Position { line, character }
+}
+
+/// Whether `coverage` has the rules and alternatives of `grammar`, which an
+/// edit since it was measured may have changed
+fn fits(coverage: &Coverage, grammar: &Grammar) -> bool {
+    coverage.rules.len() == grammar.rules.len()
+        && coverage
+            .alts
+            .iter()
+            .map(Vec::len)
+            .eq(grammar.alts.iter().map(|a| a.alternatives.len()))
+}
+
+const fn get_range(breaks: &[u32], span: BSpan) -> Range {
     Range {
-        start: Position {
-            line: line_from,
-            character: from.saturating_sub(1),
-        },
-        end: Position {
-            line: line_to,
-            character: to.saturating_sub(1),
-        },
+        start: get_position(breaks, span.from),
+        end: get_position(breaks, span.to),
     }
 }
 
 impl Document {
+    /// A document showing `coverage` as long as it still fits the grammar
     #[must_use]
-    fn new(source: String) -> Self {
+    fn new(uri: &Url, source: String, config: &Config, coverage: Option<Coverage>) -> Self {
         let tokens = Cbnf::parse(&source);
-        let line_breaks = find_lines(&source);
-        let loose_terms = tokens.terms.iter().filter_map(|t| match t {
-            &Term::Ident(span)
-                if !tokens.rules().contains_key(span.slice(&source))
-                    && !is_keyword(span.slice(&source)) =>
-            {
-                Some(Diagnostic {
-                    range: get_range(&line_breaks, span),
-                    message: "Unknown term".into(),
-                    ..Default::default()
+        let grammar = Grammar::new(&tokens, &source);
+        let ambiguities = if config.ambiguity {
+            let checker = Checker::new(&grammar).max_len(4).max_sentences(1000);
+            checker
+                .check_all()
+                .into_iter()
+                .map(|a| {
+                    let witness = a.text(&grammar);
+                    (a, witness)
                 })
-            }
-            _ => None,
-        });
-        let extras = tokens.extras.iter().map(|r| Diagnostic {
-            range: get_range(&line_breaks, r.span),
-            message: "Duplicate rule found".into(),
-            ..Default::default()
-        });
-        let diagnostics = tokens
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let line_breaks = find_lines(&source);
+        let mut doc = Self {
+            source,
+            line_breaks,
+            rules: tokens.rules,
+            extras: tokens.extras,
+            comments: tokens.comments,
+            docs: tokens.docs,
+            terms: tokens.terms,
+            params: tokens.params,
+            levels: tokens.levels,
+            errors: tokens.errors,
+            coverage,
+            cache: Cache::default(),
+        };
+        let diagnostics = doc
             .errors
             .iter()
-            .map(|e| Diagnostic {
-                range: get_range(&line_breaks, e.span()),
-                message: e.message(),
-                ..Default::default()
-            })
-            .chain(loose_terms)
-            .chain(extras)
+            .map(|e| doc.error_diagnostic(uri, e))
+            .chain(doc.unknown_terms().map(|span| doc.unknown_diagnostic(span)))
+            .chain(doc.extras.iter().map(|r| doc.duplicate_diagnostic(r)))
+            .chain(
+                ambiguities
+                    .iter()
+                    .map(|(a, witness)| doc.ambiguity_diagnostic(uri, a, witness)),
+            )
             .collect();
-        let completions = tokens
+        let completions = doc
             .rules
             .iter()
             .map(|(n, _)| CompletionItem {
@@ -146,19 +215,173 @@ impl Document {
                 ..Default::default()
             })
             .collect();
-        Self {
-            source,
-            line_breaks,
-            rules: tokens.rules,
-            extras: tokens.extras,
-            comments: tokens.comments,
-            docs: tokens.docs,
-            terms: tokens.terms,
-            errors: tokens.errors,
-            cache: Cache {
-                diagnostics,
-                completions,
+        let code_lenses = doc
+            .coverage
+            .as_ref()
+            .filter(|c| fits(c, &grammar))
+            .map(|c| doc.coverage_lenses(&grammar, c))
+            .unwrap_or_default();
+        doc.cache = Cache {
+            diagnostics,
+            completions,
+            code_lenses,
+        };
+        doc
+    }
+
+    /// A document with the coverage of the corpus of `config` measured again
+    #[must_use]
+    fn measured(uri: &Url, source: String, config: &Config) -> Self {
+        let coverage = config.corpus_dir(uri).and_then(|dir| {
+            let tokens = Cbnf::parse(&source);
+            if tokens.has_errors() {
+                return None;
+            }
+            let grammar = Grammar::new(&tokens, &source);
+            let coverage = Coverage::corpus(&Interpreter::new(&grammar), &dir);
+            coverage.ok()
+        });
+        Self::new(uri, source, config, coverage)
+    }
+
+    /// The hits of every rule, and a lens on every alternative never taken
+    fn coverage_lenses(&self, grammar: &Grammar, coverage: &Coverage) -> Vec<CodeLens> {
+        let lens = |span: BSpan, title: String| CodeLens {
+            range: self.get_range(span),
+            command: Some(Command {
+                title,
+                command: String::new(),
+                arguments: None,
+            }),
+            data: None,
+        };
+        // parameterized rules are hit through their instances
+        let hits = |id: usize| -> usize {
+            let instances = grammar.rules.iter().enumerate();
+            let instances = instances.filter(|(_, r)| r.template == Some(id));
+            coverage.rules[id] + instances.map(|(i, _)| coverage.rules[i]).sum::<usize>()
+        };
+        let rules = grammar
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.body.is_some() && rule.template.is_none())
+            .map(|(id, rule)| match hits(id) {
+                0 => lens(rule.span, "never matched by the corpus".into()),
+                1 => lens(rule.span, "1 hit".into()),
+                n => lens(rule.span, format!("{n} hits")),
+            });
+        let alternatives = coverage
+            .uncovered(grammar)
+            .into_iter()
+            .filter_map(|u| match u {
+                Uncovered::Alternative { span, .. } => {
+                    Some(lens(span, "never taken by the corpus".into()))
+                }
+                Uncovered::Rule { .. } => None,
+            });
+        rules.chain(alternatives).collect()
+    }
+
+    /// Idents that don't refer to any rule
+    fn unknown_terms(&self) -> impl Iterator<Item = BSpan> + '_ {
+        self.terms.iter().enumerate().filter_map(|(i, t)| match t {
+            &Term::Ident(span)
+                if !self.rules.contains_key(span.slice(&self.source))
+                    && !is_keyword(span.slice(&self.source))
+                    && !self.is_param(i, span)
+                    && !self.is_assoc(span) =>
+            {
+                Some(span)
+            }
+            _ => None,
+        })
+    }
+
+    /// Whether the ident at `index` of the terms names a parameter of the
+    /// rule it is in
+    fn is_param(&self, index: usize, span: BSpan) -> bool {
+        let name = span.slice(&self.source);
+        self.rules
+            .values()
+            .chain(&self.extras)
+            .find(|r| r.expr.is_some_and(|l| l.terms().range().contains(&index)))
+            .is_some_and(|r| {
+                self.params[r.params.range()]
+                    .iter()
+                    .any(|p| p.slice(&self.source) == name)
+            })
+    }
+
+    /// Whether `span` is the associativity of a level of a `@prec` rule,
+    /// such as `left`
+    fn is_assoc(&self, span: BSpan) -> bool {
+        self.levels.iter().any(|l| l.span.from == span.from)
+    }
+
+    fn error_diagnostic(&self, uri: &Url, e: &cbnf::parser::error::Error) -> Diagnostic {
+        let related = e
+            .related()
+            .into_iter()
+            .map(|r| DiagnosticRelatedInformation {
+                location: Location {
+                    uri: uri.clone(),
+                    range: self.get_range(r.span),
+                },
+                message: r.message.into(),
+            })
+            .collect::<Vec<_>>();
+        Diagnostic {
+            range: self.get_range(e.span()),
+            severity: Some(if e.is_warning() {
+                DiagnosticSeverity::WARNING
+            } else {
+                DiagnosticSeverity::ERROR
+            }),
+            code: Some(NumberOrString::String(e.code().into())),
+            message: e.message(),
+            related_information: (!related.is_empty()).then_some(related),
+            ..Default::default()
+        }
+    }
+
+    fn unknown_diagnostic(&self, span: BSpan) -> Diagnostic {
+        Diagnostic {
+            range: self.get_range(span),
+            message: "Unknown term".into(),
+            ..Default::default()
+        }
+    }
+
+    fn duplicate_diagnostic(&self, rule: &Rule) -> Diagnostic {
+        Diagnostic {
+            range: self.get_range(rule.span),
+            message: "Duplicate rule found".into(),
+            ..Default::default()
+        }
+    }
+
+    fn ambiguity_diagnostic(&self, uri: &Url, a: &Ambiguity, witness: &str) -> Diagnostic {
+        let (span, related) = a.conflict.map_or_else(
+            || (self.rules[a.start].name, None),
+            |conflict| {
+                let related = DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: uri.clone(),
+                        range: self.get_range(conflict.spans[1]),
+                    },
+                    message: "the second derivation takes this alternative".into(),
+                };
+                (conflict.spans[0], Some(vec![related]))
             },
+        );
+        Diagnostic {
+            range: self.get_range(span),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String("ambiguity".into())),
+            message: format!("Ambiguous alternatives, `{witness}` has two derivations"),
+            related_information: related,
+            ..Default::default()
         }
     }
 
@@ -189,7 +412,7 @@ impl Document {
             .terms
             .binary_search_by(|t| match (pos >= t.span().from, pos < t.span().to) {
                 // if the position lies within a group, go to the item within the group
-                (true, true) if matches!(t, Term::Or(_) | Term::Group(_)) => Less,
+                (true, true) if t.terms().is_some() => Less,
                 (true, true) => Equal,
                 (true, false) => Less,
                 (false, true) => Greater,
@@ -199,6 +422,65 @@ impl Document {
         Some(self.terms[pos])
     }
 
+    /// The innermost label at `pos`, either on its name or on the term it
+    /// names, along with the name of the rule it is in
+    fn get_label(&self, pos: u32) -> Option<(BSpan, List, &str)> {
+        let (index, name, list) =
+            self.terms
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(i, t)| match *t {
+                    Term::Label(name, list) if list.span().from <= pos && pos < list.span().to => {
+                        Some((i, name, list))
+                    }
+                    _ => None,
+                })?;
+        let (rule, _) = self
+            .rules
+            .iter()
+            .find(|(_, r)| r.expr.is_some_and(|l| l.terms().range().contains(&index)))?;
+        Some((name, list, rule))
+    }
+
+    /// What the literal or label at `pos` matches
+    fn hover(&self, pos: u32) -> Option<Hover> {
+        let hover = |value, span| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(self.get_range(span)),
+        };
+        if let Some(term @ Term::Literal(span, _)) = self.get_token(pos) {
+            let value = match term.value(&self.source)? {
+                Value::Char(c) => format!("char `{c:?}`, `U+{:04X}`", u32::from(c)),
+                Value::Text(text) => format!("matches `{text:?}`"),
+                Value::Bytes(bytes) => format!("matches the bytes `{bytes:02x?}`"),
+            };
+            return Some(hover(value, span));
+        }
+        let (name, list, rule) = self.get_label(pos)?;
+        let value = format!(
+            "```cbnf\n{}\n```\nlabel `{}` of `{rule}`, a field of its syntax tree",
+            list.span().slice(&self.source),
+            name.slice(&self.source),
+        );
+        Some(hover(value, list.span()))
+    }
+
+    /// Whether `pos` is where the name of an attribute goes, like `#[|`
+    fn in_attribute(&self, pos: u32) -> bool {
+        let Some(before) = self.source.get(..pos as usize) else {
+            return false;
+        };
+        before
+            .trim_end_matches(is_id_continue)
+            .trim_end()
+            .strip_suffix('[')
+            .is_some_and(|b| b.trim_end().ends_with('#'))
+    }
+
     fn get_point(&self, pos: Position) -> u32 {
         if pos.line == 0 {
             pos.character
@@ -220,7 +502,40 @@ impl Backend {
     #[must_use]
     pub fn new(client: Client) -> Self {
         let forms = DashMap::new();
-        Self { client, forms }
+        let config = RwLock::default();
+        Self {
+            client,
+            forms,
+            config,
+        }
+    }
+    fn config(&self) -> Config {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+    /// Rebuilds the document at `uri` with its coverage measured again, on
+    /// a blocking thread and without holding its entry, so requests on it
+    /// aren't held up by reading the corpus or searching for ambiguities
+    async fn measure(&self, uri: Url, config: Config) {
+        let Some(source) = self.forms.get(&uri).map(|doc| doc.source.clone()) else {
+            return;
+        };
+        let measured = {
+            let uri = uri.clone();
+            tokio::task::spawn_blocking(move || Document::measured(&uri, source, &config)).await
+        };
+        let Ok(measured) = measured else {
+            return;
+        };
+        // an edit made in the meantime is newer, and is measured on the
+        // next save
+        if let Some(mut doc) = self.forms.get_mut(&uri) {
+            if doc.source == measured.source {
+                *doc = measured;
+            }
+        }
     }
     async fn info(&self, m: impl Display + Send) {
         tracing::info!("{m}");
@@ -237,8 +552,13 @@ fn capabilities() -> ServerCapabilities {
         references_provider: Some(OneOf::Left(true)),
         position_encoding: Some(PositionEncodingKind::UTF8),
         rename_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
         completion_provider: Some(CompletionOptions {
-            trigger_characters: Some(vec!["$".into()]),
+            trigger_characters: Some(vec!["$".into(), "[".into()]),
             ..Default::default()
         }),
         diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
@@ -259,7 +579,11 @@ fn unknown_uri<T>() -> Result<T> {
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     // TODO: consider making 'syntax.cbnf' be the root of a file.
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(options) = &params.initialization_options {
+            let mut config = self.config.write().unwrap_or_else(PoisonError::into_inner);
+            config.update(options);
+        }
         Ok(InitializeResult {
             capabilities: capabilities(),
             server_info: Some(ServerInfo {
@@ -289,23 +613,68 @@ impl LanguageServer for Backend {
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let doc = self.get_doc(&position.text_document.uri)?;
+        if doc.in_attribute(doc.get_point(position.position)) {
+            let attributes = cbnf::ATTRIBUTES
+                .iter()
+                .map(|(name, detail)| CompletionItem {
+                    label: (*name).into(),
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    detail: Some((*detail).into()),
+                    ..Default::default()
+                });
+            return Ok(Some(CompletionResponse::Array(attributes.collect())));
+        }
         Ok(Some(CompletionResponse::Array(
-            self.get_doc(&params.text_document_position.text_document.uri)?
-                .cache
-                .completions
-                .clone(),
+            doc.cache.completions.clone(),
         )))
     }
 
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let doc = self.get_doc(&position.text_document.uri)?;
+        Ok(doc.hover(doc.get_point(position.position)))
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let lenses = self
+            .get_doc(&params.text_document.uri)?
+            .cache
+            .code_lenses
+            .clone();
+        Ok((!lenses.is_empty()).then_some(lenses))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let doc = self.get_doc(&uri)?;
+        let span = BSpan::new(
+            doc.get_point(params.range.start),
+            doc.get_point(params.range.end),
+        );
+        let mut actions = doc.quick_fixes(&uri, span);
+        actions.extend(doc.refactors(&uri, span));
+        Ok((!actions.is_empty()).then_some(actions))
+    }
+
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let doc = self.get_doc(&uri)?;
+        let pos = doc.get_point(params.text_document_position.position);
+        if let Some(Term::Literal(span, _)) = doc.get_token(pos) {
+            let edits = doc.rename_literal(span, &params.new_name);
+            return Ok(Some(WorkspaceEdit {
+                changes: Some([(uri, edits)].into()),
+                document_changes: None,
+                change_annotations: None,
+            }));
+        }
         if !valid_id(&params.new_name) {
             return Err(tower_lsp::jsonrpc::Error::invalid_params(
                 "Invalid Name Inputted",
             ));
         }
-        let uri = params.text_document_position.text_document.uri;
-        let doc = self.get_doc(&uri)?;
-        let pos = doc.get_point(params.text_document_position.position);
         let (span, rule) = match doc.get_rule(pos) {
             Some(r) => (r.name, true),
             None => match doc.get_token(pos) {
@@ -330,7 +699,7 @@ impl LanguageServer for Backend {
                     te.range.end.character = te.range.start.character + (span.to - span.from);
                 }
             }
-        };
+        }
         Ok(Some(WorkspaceEdit {
             changes: Some([(uri, edits)].into()),
             document_changes: None,
@@ -432,24 +801,44 @@ impl LanguageServer for Backend {
             return;
         };
         let src = params.content_changes.swap_remove(i).text;
-        let doc = Document::new(src);
-        *self.forms.entry(params.text_document.uri).or_default() = doc;
+        let uri = params.text_document.uri;
+        let coverage = self.forms.get_mut(&uri).and_then(|mut d| d.coverage.take());
+        let doc = Document::new(&uri, src, &self.config(), coverage);
+        self.forms.insert(uri, doc);
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        let _ = params;
+        let config = self.config();
+        if config.corpus.is_none() {
+            return;
+        }
+        // the corpus may have changed since, measure it again
+        self.measure(params.text_document.uri, config).await;
+        let _ = self.client.code_lens_refresh().await;
     }
 
-    // TODO: do something with this
     async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
-        let _ = params;
+        self.config
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .update(&params.settings);
+        let config = self.config();
+        let uris: Vec<_> = self.forms.iter().map(|doc| doc.key().clone()).collect();
+        for uri in uris {
+            self.measure(uri, config.clone()).await;
+        }
+        // diagnostics and lenses are pulled, ask the client to pull them again
+        let _ = self.client.workspace_diagnostic_refresh().await;
+        let _ = self.client.code_lens_refresh().await;
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        self.forms.insert(
-            params.text_document.uri,
-            Document::new(params.text_document.text),
+        let doc = Document::measured(
+            &params.text_document.uri,
+            params.text_document.text,
+            &self.config(),
         );
+        self.forms.insert(params.text_document.uri, doc);
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
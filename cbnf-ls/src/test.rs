@@ -0,0 +1,119 @@
+use std::fmt::Write;
+
+use cbnf::{coverage::Coverage, grammar::Grammar, interp::Interpreter, Cbnf};
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+use tower_lsp::lsp_types::{HoverContents, Range, Url};
+
+use crate::{Config, Document};
+
+pub fn uri() -> Url {
+    Url::parse("file:///grammar.cbnf").unwrap()
+}
+
+/// A document of `src` without corpus coverage, for the tests of every
+/// module
+pub fn document(src: &str) -> Document {
+    Document::new(&uri(), src.into(), &Config::default(), None)
+}
+
+fn range(range: Range) -> String {
+    let (start, end) = (range.start, range.end);
+    format!(
+        "{}:{}-{}:{}",
+        start.line, start.character, end.line, end.character
+    )
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check_hover(src: &str, at: &str, expect: Expect) {
+    let doc = document(src);
+    #[allow(clippy::cast_possible_truncation)]
+    let pos = src.find(at).unwrap() as u32;
+    let out = doc.hover(pos).map_or_else(
+        || "no hover".into(),
+        |hover| {
+            let HoverContents::Markup(content) = hover.contents else {
+                panic!("hover without markup");
+            };
+            format!("{} {}", range(hover.range.unwrap()), content.value)
+        },
+    );
+    expect.assert_eq(&out);
+}
+
+#[test]
+fn hover_literals() {
+    let src = r#"a { "x\t" 'y' b"\xff" i"Z" "\q" }"#;
+    check_hover(src, r#""x"#, expect![[r#"0:4-0:9 matches `"x\t"`"#]]);
+    check_hover(src, "'y'", expect![["0:10-0:13 char `'y'`, `U+0079`"]]);
+    check_hover(src, "b\"", expect!["0:14-0:21 matches the bytes `[ff]`"]);
+    check_hover(src, "i\"", expect![[r#"0:22-0:26 matches `"Z"`"#]]);
+    check_hover(src, r#""\q"#, expect!["no hover"]);
+}
+
+#[test]
+fn hover_labels() {
+    let src = r#"sum { lhs:term "+" rhs:(term | "0") } term { "1" }"#;
+    check_hover(
+        src,
+        "lhs",
+        expect![[r"
+            0:6-0:14 ```cbnf
+            lhs:term
+            ```
+            label `lhs` of `sum`, a field of its syntax tree"]],
+    );
+    check_hover(src, r#""0""#, expect![[r#"0:31-0:34 matches `"0"`"#]]);
+    check_hover(
+        src,
+        "(term",
+        expect![[r#"
+            0:19-0:35 ```cbnf
+            rhs:(term | "0")
+            ```
+            label `rhs` of `sum`, a field of its syntax tree"#]],
+    );
+    check_hover(src, "term {", expect!["no hover"]);
+}
+
+#[test]
+fn code_lenses() {
+    let src = "list { item list | nil }\nitem { \"x\" | \"y\" }\nunused { \"z\" }\n";
+    let cbnf = Cbnf::parse(src);
+    let grammar = Grammar::new(&cbnf, src);
+    let interp = Interpreter::new(&grammar);
+    let mut coverage = Coverage::new(&grammar);
+    coverage.record(&interp.parse(0, "x x").unwrap());
+    let doc = Document::new(&uri(), src.into(), &Config::default(), Some(coverage));
+    let mut out = String::new();
+    for lens in &doc.cache.code_lenses {
+        let title = &lens.command.as_ref().unwrap().title;
+        let _ = writeln!(out, "{} {title}", range(lens.range));
+    }
+    expect![[r"
+        0:0-0:4 3 hits
+        1:0-1:4 2 hits
+        2:0-2:6 never matched by the corpus
+        1:11-1:16 never taken by the corpus
+    "]]
+    .assert_eq(&out);
+
+    // coverage measured before an edit that changed the alternatives is
+    // left out until it is measured again
+    let edited = "list { item list | nil }\nitem { \"x\" }\nunused { \"z\" }\n";
+    let coverage = doc.coverage.clone();
+    let doc = Document::new(&uri(), edited.into(), &Config::default(), coverage);
+    assert_eq!(doc.cache.code_lenses, []);
+}
+
+#[test]
+fn attribute_completion() {
+    let src = "#[sta] #[ x] a { }";
+    let doc = document(src);
+    assert!(doc.in_attribute(5));
+    assert!(doc.in_attribute(9));
+    assert!(doc.in_attribute(11));
+    assert!(!doc.in_attribute(12));
+    assert!(!doc.in_attribute(100));
+}
@@ -0,0 +1,349 @@
+//! bounded search for ambiguous sentences
+//!
+//! A grammar is ambiguous when some sentence has two different derivation
+//! trees. Deciding this is impossible in general, so [`Checker`] enumerates
+//! every sentence up to a length (see [`crate::enumerate`]) and reports the
+//! shortest one with more than one derivation, along with two of its trees
+//! and the alternation where they part ways.
+//!
+//! Rules with an empty body are opaque terminals here, so `ident | "x"` is
+//! not ambiguous even though `x` is an identifier. Case-insensitive literals
+//! are terminals of their own too, so neither is `i"x" | "x"`. Lookahead
+//! predicates are taken to always hold, and terms joined by `,` are tokens
+//! like any others.
+
+use std::fmt::Write;
+
+use crate::{
+    enumerate::{Derivations, Enumerator, Sentence, Terminal},
+    grammar::{AltId, Grammar, Node, NodeKind, RuleId},
+    report::{Diagnostic, Label},
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// A derivation tree over the terminals of a [`Sentence`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Terminal(Terminal),
+    Rule {
+        rule: RuleId,
+        /// The alternative taken at each alternation of the body, in the
+        /// order they were entered
+        choices: Vec<(AltId, usize)>,
+        children: Vec<Self>,
+    },
+}
+
+impl Tree {
+    /// Renders the tree as an s-expression, one node per line
+    #[must_use]
+    pub fn render(&self, grammar: &Grammar) -> String {
+        let mut out = String::new();
+        self.write(&mut out, grammar, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, grammar: &Grammar, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        match self {
+            Self::Terminal(Terminal::Literal(lit)) => _ = write!(out, "{lit:?}"),
+            Self::Terminal(Terminal::Insensitive(lit)) => _ = write!(out, "i{lit:?}"),
+            Self::Terminal(Terminal::Builtin(id)) => _ = write!(out, "<{}>", grammar.name(*id)),
+            Self::Rule { rule, children, .. } => {
+                out.push('(');
+                out.push_str(grammar.name(*rule));
+                for child in children {
+                    out.push('\n');
+                    child.write(out, grammar, depth + 1);
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// The alternation at which two derivations first differ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    /// The rule containing the alternation
+    pub rule: RuleId,
+    pub alt: AltId,
+    /// The alternative taken by each derivation
+    pub alternatives: [usize; 2],
+    /// The spans of those alternatives
+    pub spans: [BSpan; 2],
+}
+
+/// A sentence with two derivations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    /// The rule the sentence derives from
+    pub start: RuleId,
+    pub witness: Sentence,
+    pub trees: [Tree; 2],
+    /// `None` when the trees only differ in how terminals are split between
+    /// the terms of a sequence
+    pub conflict: Option<Conflict>,
+}
+
+impl Ambiguity {
+    /// The witness as text, terminals separated by spaces
+    #[must_use]
+    pub fn text(&self, grammar: &Grammar) -> String {
+        self.witness.text(grammar, " ")
+    }
+
+    /// A warning pointing at the conflicting alternatives
+    #[must_use]
+    pub fn diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let message = format!(
+            "`{}` is ambiguous, `{}` has two derivations",
+            grammar.name(self.start),
+            self.text(grammar)
+        );
+        let mut diag = Diagnostic::warning(message).with_code("ambiguity");
+        if let Some(Conflict { spans: [a, b], .. }) = self.conflict {
+            diag = diag
+                .with_label(Label::primary(
+                    a,
+                    "the first derivation takes this alternative",
+                ))
+                .with_label(Label::secondary(b, "the second takes this one"));
+        } else {
+            let span = grammar.rules[self.start].span;
+            diag = diag.with_label(Label::primary(span, "in this rule"));
+        }
+        for (i, tree) in self.trees.iter().enumerate() {
+            diag = diag.with_note(format!("derivation {}:\n{}", i + 1, tree.render(grammar)));
+        }
+        diag
+    }
+}
+
+/// Searches a [`Grammar`] for ambiguities within bounds
+#[derive(Debug, Clone, Copy)]
+pub struct Checker<'g> {
+    grammar: &'g Grammar,
+    max_len: usize,
+    max_sentences: usize,
+}
+
+impl<'g> Checker<'g> {
+    /// A checker looking at up to 10000 sentences of at most 6 terminals
+    #[must_use]
+    pub const fn new(grammar: &'g Grammar) -> Self {
+        Self {
+            grammar,
+            max_len: 6,
+            max_sentences: 10_000,
+        }
+    }
+
+    /// The most terminals in a sentence
+    #[must_use]
+    pub const fn max_len(mut self, len: usize) -> Self {
+        self.max_len = len;
+        self
+    }
+
+    /// The most sentences looked at per start rule
+    #[must_use]
+    pub const fn max_sentences(mut self, count: usize) -> Self {
+        self.max_sentences = count;
+        self
+    }
+
+    /// The shortest ambiguous sentence of `start`, if any within bounds
+    #[must_use]
+    pub fn check(&self, start: RuleId) -> Option<Ambiguity> {
+        let witness = Enumerator::new(self.grammar, start, self.max_len)
+            .count_derivations(true)
+            .take(self.max_sentences)
+            .find(|s| s.derivations.is_some_and(Derivations::is_ambiguous))?;
+        let mut derive = Derive {
+            grammar: self.grammar,
+            terminals: &witness.terminals,
+            stack: Vec::new(),
+        };
+        let mut trees = derive.rule(start, 0, witness.terminals.len()).into_iter();
+        let trees = [trees.next()?, trees.next()?];
+        let conflict = conflict(&trees[0], &trees[1]).map(|(rule, alt, a, b)| {
+            let spans = &self.grammar.alts[alt].alternatives;
+            Conflict {
+                rule,
+                alt,
+                alternatives: [a, b],
+                spans: [spans[a], spans[b]],
+            }
+        });
+        Some(Ambiguity {
+            start,
+            witness,
+            trees,
+            conflict,
+        })
+    }
+
+    /// Checks every rule, reporting each conflicting alternation once
+    #[must_use]
+    pub fn check_all(&self) -> Vec<Ambiguity> {
+        let mut found: Vec<Ambiguity> = Vec::new();
+        for (id, rule) in self.grammar.rules.iter().enumerate() {
+            if rule.body.is_none() {
+                continue;
+            }
+            let Some(ambiguity) = self.check(id) else {
+                continue;
+            };
+            let key = |a: &Ambiguity| a.conflict.map_or(Err(a.start), |c| Ok(c.alt));
+            if !found.iter().any(|a| key(a) == key(&ambiguity)) {
+                found.push(ambiguity);
+            }
+        }
+        found
+    }
+}
+
+/// Where two trees first take different alternatives
+fn conflict(a: &Tree, b: &Tree) -> Option<(RuleId, AltId, usize, usize)> {
+    let (
+        Tree::Rule {
+            rule,
+            choices: a_choices,
+            children: a_children,
+        },
+        Tree::Rule {
+            choices: b_choices,
+            children: b_children,
+            ..
+        },
+    ) = (a, b)
+    else {
+        return None;
+    };
+    let differ = a_choices.iter().zip(b_choices).find(|(a, b)| a != b);
+    if let Some((&(alt, a), &(_, b))) = differ {
+        return Some((*rule, alt, a, b));
+    }
+    a_children
+        .iter()
+        .zip(b_children)
+        .find_map(|(a, b)| conflict(a, b))
+}
+
+/// At most this many derivations are built for any part of the witness
+const LIMIT: usize = 2;
+
+type Partial = (Vec<Tree>, Vec<(AltId, usize)>);
+
+/// Builds derivation trees of a short sentence
+struct Derive<'a> {
+    grammar: &'a Grammar,
+    terminals: &'a [Terminal],
+    /// Rules being derived, with the terminals they cover
+    stack: Vec<(RuleId, usize, usize)>,
+}
+
+impl Derive<'_> {
+    fn rule(&mut self, id: RuleId, from: usize, to: usize) -> Vec<Tree> {
+        let Some(body) = &self.grammar.rules[id].body else {
+            let builtin = Terminal::Builtin(id);
+            if to == from + 1 && self.terminals[from] == builtin {
+                return vec![Tree::Terminal(builtin)];
+            }
+            return Vec::new();
+        };
+        // going around a cycle once is enough to tell derivations apart
+        let key = (id, from, to);
+        if self.stack.iter().filter(|k| **k == key).count() >= 2 {
+            return Vec::new();
+        }
+        self.stack.push(key);
+        let partials = self.node(body, from, to);
+        self.stack.pop();
+        partials
+            .into_iter()
+            .map(|(children, choices)| Tree::Rule {
+                rule: id,
+                choices,
+                children,
+            })
+            .collect()
+    }
+
+    fn node(&mut self, node: &Node, from: usize, to: usize) -> Vec<Partial> {
+        match &node.kind {
+            NodeKind::Nil | NodeKind::And(_) | NodeKind::Not(_) | NodeKind::Adjacent => {
+                empty(from, to)
+            }
+            NodeKind::Literal(lit) | NodeKind::Insensitive(lit) if lit.is_empty() => {
+                empty(from, to)
+            }
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                let terminal = Terminal::of(&node.kind).expect("a literal");
+                if to == from + 1 && self.terminals[from] == terminal {
+                    vec![(vec![Tree::Terminal(terminal)], Vec::new())]
+                } else {
+                    Vec::new()
+                }
+            }
+            NodeKind::Rule(id) => self
+                .rule(*id, from, to)
+                .into_iter()
+                .map(|tree| (vec![tree], Vec::new()))
+                .collect(),
+            NodeKind::Unknown(_) => Vec::new(),
+            NodeKind::Seq(nodes) => self.seq(nodes, from, to),
+            NodeKind::Alt(alt, nodes) => {
+                let mut partials = Vec::new();
+                for (i, node) in nodes.iter().enumerate() {
+                    for (children, mut choices) in self.node(node, from, to) {
+                        choices.insert(0, (*alt, i));
+                        partials.push((children, choices));
+                        if partials.len() == LIMIT {
+                            return partials;
+                        }
+                    }
+                }
+                partials
+            }
+        }
+    }
+
+    fn seq(&mut self, nodes: &[Node], from: usize, to: usize) -> Vec<Partial> {
+        let Some((first, rest)) = nodes.split_first() else {
+            return empty(from, to);
+        };
+        let mut partials = Vec::new();
+        for mid in from..=to {
+            let heads = self.node(first, from, mid);
+            if heads.is_empty() {
+                continue;
+            }
+            let tails = self.seq(rest, mid, to);
+            for (head, head_choices) in &heads {
+                for (tail, tail_choices) in &tails {
+                    let children = [head.as_slice(), tail].concat();
+                    let choices = [head_choices.as_slice(), tail_choices].concat();
+                    partials.push((children, choices));
+                    if partials.len() == LIMIT {
+                        return partials;
+                    }
+                }
+            }
+        }
+        partials
+    }
+}
+
+/// The single derivation of nothing
+fn empty(from: usize, to: usize) -> Vec<Partial> {
+    if from == to {
+        vec![(Vec::new(), Vec::new())]
+    } else {
+        Vec::new()
+    }
+}
@@ -0,0 +1,130 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    ambiguity::{Checker, Conflict},
+    grammar::test::grammar,
+    report::Renderer,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let actual = Checker::new(&grammar).check(0).map_or_else(
+        || "unambiguous".into(),
+        |a| Renderer::new().render("test.cbnf", src, &a.diagnostic(&grammar)),
+    );
+    expect.assert_eq(&actual);
+}
+
+#[test]
+fn unambiguous() {
+    check(
+        r#"e { e "+" t | t } t { "1" | "(" e ")" }"#,
+        expect!["unambiguous"],
+    );
+}
+
+#[test]
+fn binary_operator() {
+    check(
+        r#"e { e "+" e | "1" }"#,
+        expect![[r#"
+            warning[ambiguity]: `e` is ambiguous, `1 + 1 + 1` has two derivations
+             --> test.cbnf:1:13
+              |
+            1 | e { e "+" e | "1" }
+              |             ^^^^^ the first derivation takes this alternative
+              |     ------- the second takes this one
+              |
+              = note: derivation 1:
+                      (e
+                        (e
+                          "1")
+                        "+"
+                        (e
+                          (e
+                            "1")
+                          "+"
+                          (e
+                            "1")))
+              = note: derivation 2:
+                      (e
+                        (e
+                          (e
+                            "1")
+                          "+"
+                          (e
+                            "1"))
+                        "+"
+                        (e
+                          "1"))
+        "#]],
+    );
+}
+
+#[test]
+fn overlapping_alternatives() {
+    let src = r#"a { b | c } b { "x" "y" } c { "x" ("y" | "z") }"#;
+    check(
+        src,
+        expect![[r#"
+            warning[ambiguity]: `a` is ambiguous, `x y` has two derivations
+             --> test.cbnf:1:5
+              |
+            1 | a { b | c } b { "x" "y" } c { "x" ("y" | "z") }
+              |     ^ the first derivation takes this alternative
+              |       --- the second takes this one
+              |
+              = note: derivation 1:
+                      (a
+                        (b
+                          "x"
+                          "y"))
+              = note: derivation 2:
+                      (a
+                        (c
+                          "x"
+                          "y"))
+        "#]],
+    );
+    let grammar = grammar(src);
+    let conflict = Checker::new(&grammar).check(0).unwrap().conflict.unwrap();
+    assert_eq!(
+        conflict,
+        Conflict {
+            rule: 0,
+            alt: 0,
+            alternatives: [0, 1],
+            spans: [
+                grammar.alts[0].alternatives[0],
+                grammar.alts[0].alternatives[1]
+            ],
+        }
+    );
+}
+
+#[test]
+fn bounds() {
+    let grammar = grammar(r#"a { "x" "x" "x" b | "x" "x" "x" c } b { "y" } c { "y" }"#);
+    assert!(Checker::new(&grammar).max_len(3).check(0).is_none());
+    assert!(Checker::new(&grammar).max_len(4).check(0).is_some());
+    assert!(Checker::new(&grammar).max_sentences(0).check(0).is_none());
+}
+
+#[test]
+fn cyclic() {
+    let grammar = grammar(r#"a { a | "x" }"#);
+    let ambiguity = Checker::new(&grammar).check(0).unwrap();
+    assert_eq!(ambiguity.text(&grammar), "x");
+    assert_eq!(ambiguity.conflict.unwrap().alternatives, [0, 1]);
+}
+
+#[test]
+fn check_all() {
+    let grammar = grammar(r#"top { list } list { item | list list } item { "x" }"#);
+    let found = Checker::new(&grammar).check_all();
+    // `top` and `list` share the same conflict
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].start, 0);
+}
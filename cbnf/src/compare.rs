@@ -0,0 +1,243 @@
+//! evidence that two grammars do or don't accept the same language
+//!
+//! Whether two context-free grammars are equivalent can't be decided in
+//! general, so [`Comparison`] looks for a sentence that tells them apart:
+//! every sentence of either grammar with at most a few terminals (see
+//! [`crate::enumerate`]) and a number of random ones (see
+//! [`crate::generate`]) are parsed with the other grammar. Finding none is
+//! evidence, not proof, that the languages are the same.
+//!
+//! Sentences are written with a space between terminals, and rules with an
+//! empty body are filled in by [`DefaultSource`]. Only sentences the grammar
+//! they come from accepts itself are tried, which leaves out those breaking
+//! lookahead predicates or `,`.
+//!
+//! [`diff`] is the structural side, for code review: the rules added,
+//! removed or changed between the grammars, matched up by name. It is a
+//! summary of [`crate::diff::Diff`], which also has what changed within
+//! each rule and how that affects the language.
+//!
+//! ```
+//! use cbnf::{compare::{Comparison, Side}, grammar::Grammar, Cbnf};
+//!
+//! let old = r#"list { "x" list | nil }"#;
+//! let new = r#"list { list "x" | nil }"#;
+//! let (old, new) = (
+//!     Grammar::new(&Cbnf::parse(old), old),
+//!     Grammar::new(&Cbnf::parse(new), new),
+//! );
+//! assert_eq!(Comparison::new((&old, 0), (&new, 0)).distinguish(), None);
+//!
+//! let new_src = r#"list { "x" list | "y" }"#;
+//! let new = Grammar::new(&Cbnf::parse(new_src), new_src);
+//! let found = Comparison::new((&old, 0), (&new, 0)).distinguish().unwrap();
+//! assert_eq!((found.sentence.as_str(), found.accepted_by), ("", Side::Left));
+//! ```
+
+use std::fmt::{self, Write};
+
+pub use crate::diff::{RuleChange, RuleDiff};
+use crate::{
+    diff::Diff,
+    earley::Earley,
+    enumerate::{Enumerator, Terminal},
+    generate::{DefaultSource, Generator, Rng, Source},
+    grammar::{Grammar, RuleId},
+    report::LineIndex,
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// One of the two grammars of a [`Comparison`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    #[must_use]
+    pub const fn other(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Left => "left",
+            Self::Right => "right",
+        })
+    }
+}
+
+/// A sentence only one of the grammars accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distinction {
+    pub sentence: String,
+    pub accepted_by: Side,
+}
+
+impl fmt::Display for Distinction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} is accepted by the {} grammar but not the {}",
+            self.sentence,
+            self.accepted_by,
+            self.accepted_by.other()
+        )
+    }
+}
+
+/// Looks for a sentence telling two grammars apart, see the
+/// [module docs](self)
+pub struct Comparison<'g> {
+    left: (&'g Grammar, RuleId),
+    right: (&'g Grammar, RuleId),
+    max_len: usize,
+    samples: usize,
+    seed: u64,
+}
+
+impl<'g> Comparison<'g> {
+    /// Compares the languages of two grammars, each with its start rule,
+    /// enumerating sentences of up to 4 terminals and sampling 100 more of
+    /// each with a seed of 0
+    #[must_use]
+    pub const fn new(left: (&'g Grammar, RuleId), right: (&'g Grammar, RuleId)) -> Self {
+        Self {
+            left,
+            right,
+            max_len: 4,
+            samples: 100,
+            seed: 0,
+        }
+    }
+
+    /// The most terminals in an enumerated sentence
+    #[must_use]
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// How many random sentences to try from each grammar
+    #[must_use]
+    pub const fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    #[must_use]
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// A sentence accepted by only one of the grammars, the shortest
+    /// enumerated one if there is any
+    #[must_use]
+    pub fn distinguish(&self) -> Option<Distinction> {
+        let parsers = (Earley::new(self.left.0), Earley::new(self.right.0));
+        let check = |side: Side, sentence: String| {
+            let (this, other) = match side {
+                Side::Left => (&parsers.0, &parsers.1),
+                Side::Right => (&parsers.1, &parsers.0),
+            };
+            let accepted = this.recognize(self.start(side), &sentence)
+                && !other.recognize(self.start(side.other()), &sentence);
+            accepted.then_some(Distinction {
+                sentence,
+                accepted_by: side,
+            })
+        };
+
+        let enumerated = [Side::Left, Side::Right].into_iter().filter_map(|side| {
+            let grammar = self.grammar(side);
+            let mut rng = Rng::new(self.seed);
+            Enumerator::new(grammar, self.start(side), self.max_len).find_map(|s| {
+                let text = sentence(grammar, &s.terminals, &mut rng)?;
+                Some((s.terminals.len(), check(side, text)?))
+            })
+        });
+        if let Some((_, distinction)) = enumerated.min_by_key(|(len, _)| *len) {
+            return Some(distinction);
+        }
+
+        [Side::Left, Side::Right].into_iter().find_map(|side| {
+            let mut generator = Generator::new(self.grammar(side)).seed(self.seed);
+            (0..self.samples).find_map(|_| check(side, generator.generate(self.start(side)).ok()?))
+        })
+    }
+
+    const fn grammar(&self, side: Side) -> &'g Grammar {
+        match side {
+            Side::Left => self.left.0,
+            Side::Right => self.right.0,
+        }
+    }
+
+    const fn start(&self, side: Side) -> RuleId {
+        match side {
+            Side::Left => self.left.1,
+            Side::Right => self.right.1,
+        }
+    }
+}
+
+/// The rules added, removed or changed from `left` to `right`
+///
+/// Rules are matched up by name and compared ignoring their spans, so moving
+/// or reformatting a rule is no change. Instances of parameterized rules are
+/// left out, their templates stand for them. Removed and changed rules come
+/// in the order of `left`, followed by the added rules in the order of
+/// `right`. These are the rules of [`Diff::new`].
+#[must_use]
+pub fn diff(left: &Grammar, right: &Grammar) -> Vec<RuleDiff> {
+    Diff::new(left, right).rules
+}
+
+/// The diff of [`diff`] with one line per rule, and where it is in the
+/// sources of the grammars
+#[must_use]
+pub fn diff_text(diffs: &[RuleDiff], left: &str, right: &str) -> String {
+    let (left_lines, right_lines) = (LineIndex::new(left), LineIndex::new(right));
+    let at = |lines: &LineIndex, src, span: Option<BSpan>| {
+        span.map(|span| {
+            let (line, col) = lines.line_col(span.from, src);
+            format!("{line}:{col}")
+        })
+    };
+    let mut out = String::new();
+    for diff in diffs {
+        let left = at(&left_lines, left, diff.left);
+        let right = at(&right_lines, right, diff.right);
+        let _ = match (diff.change, left, right) {
+            (RuleChange::Changed, Some(left), Some(right)) => {
+                writeln!(out, "~ {} (left {left}, right {right})", diff.name)
+            }
+            (RuleChange::Removed, Some(left), _) => writeln!(out, "- {} (left {left})", diff.name),
+            (_, _, Some(right)) => writeln!(out, "+ {} (right {right})", diff.name),
+            _ => Ok(()),
+        };
+    }
+    out
+}
+
+/// The text of an enumerated sentence, `None` when a builtin has no text
+fn sentence(grammar: &Grammar, terminals: &[Terminal], rng: &mut Rng) -> Option<String> {
+    let words = terminals
+        .iter()
+        .map(|t| match t {
+            Terminal::Literal(lit) | Terminal::Insensitive(lit) => Some(lit.clone()),
+            Terminal::Builtin(id) => DefaultSource.generate(grammar.name(*id), rng),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(words.join(" "))
+}
@@ -0,0 +1,131 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    compare::{diff, diff_text, Comparison, Distinction, RuleChange, Side},
+    grammar::test::grammar,
+};
+
+fn distinguish(left: &str, right: &str) -> Option<Distinction> {
+    let (left, right) = (grammar(left), grammar(right));
+    Comparison::new((&left, 0), (&right, 0)).distinguish()
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check_diff(left: &str, right: &str, expect: Expect) {
+    let diffs = diff(&grammar(left), &grammar(right));
+    expect.assert_eq(&diff_text(&diffs, left, right));
+}
+
+#[test]
+fn equivalent() {
+    assert_eq!(
+        distinguish(r#"list { "x" list | nil }"#, r#"list { list "x" | nil }"#),
+        None
+    );
+    assert_eq!(
+        distinguish(
+            r#"e { e "+" t | t } t { "1" | "(" e ")" }"#,
+            r#"e { t ("+" e | nil) } t { "(" e ")" | "1" }"#,
+        ),
+        None
+    );
+}
+
+#[test]
+fn shortest() {
+    let found = distinguish(r#"e { e "+" e | "1" }"#, r#"e { e "+" e | e "-" e | "1" }"#);
+    assert_eq!(
+        found.map(|f| f.to_string()).as_deref(),
+        Some(r#""1 - 1" is accepted by the right grammar but not the left"#)
+    );
+}
+
+#[test]
+fn sampled() {
+    let (left, right) = (
+        grammar(r#"a { "x" a | nil }"#),
+        grammar(r#"a { "x" "x" a | nil }"#),
+    );
+    let comparison = Comparison::new((&left, 0), (&right, 0)).max_len(0);
+    let found = comparison.distinguish().unwrap();
+    assert_eq!(found.accepted_by, Side::Left);
+    assert_eq!(found.sentence.split(' ').count() % 2, 1);
+    assert_eq!(comparison.samples(0).distinguish(), None);
+}
+
+#[test]
+fn builtins() {
+    let found = distinguish(
+        r#"call { ident "(" ")" } ident {}"#,
+        r#"call { ident "(" (ident | nil) ")" } ident {}"#,
+    )
+    .unwrap();
+    assert_eq!(found.accepted_by, Side::Right);
+    assert_eq!(found.sentence.split(' ').count(), 4);
+}
+
+#[test]
+fn starts() {
+    let src = r#"a { "x" } b { "x" } c { "y" }"#;
+    let grammar = grammar(src);
+    let same = Comparison::new((&grammar, 0), (&grammar, 1));
+    assert_eq!(same.distinguish(), None);
+    let other = Comparison::new((&grammar, 0), (&grammar, 2));
+    assert_eq!(
+        other.distinguish(),
+        Some(Distinction {
+            sentence: "x".into(),
+            accepted_by: Side::Left,
+        })
+    );
+}
+
+#[test]
+fn structural() {
+    let left = r#"
+a { b "x" }
+b { "y" | c }
+c { "z" }
+list<t> { t list<t> | nil }
+d { list<a> }
+"#;
+    let right = r#"
+b {
+    "y"
+    | c
+}
+a { b "x" "w" }
+list<t> { t list<t> | nil }
+d { list<a> }
+e { "v" }
+"#;
+    check_diff(
+        left,
+        right,
+        expect![[r"
+            ~ a (left 2:1, right 6:1)
+            - c (left 4:1)
+            + e (right 9:1)
+        "]],
+    );
+    let diffs = diff(&grammar(left), &grammar(right));
+    let changes: Vec<_> = diffs.iter().map(|d| d.change).collect();
+    assert_eq!(
+        changes,
+        [RuleChange::Changed, RuleChange::Removed, RuleChange::Added]
+    );
+}
+
+#[test]
+fn kinds() {
+    check_diff(
+        r#"a { "x" } @lexical b { "y" } ident {}"#,
+        r#"a { x:"x" } b { "y" } ident { "z" }"#,
+        expect![[r"
+            ~ a (left 1:1, right 1:1)
+            ~ b (left 1:20, right 1:13)
+            ~ ident (left 1:30, right 1:23)
+        "]],
+    );
+}
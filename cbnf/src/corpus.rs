@@ -0,0 +1,347 @@
+//! corpus tests, sample inputs checked in next to a grammar
+//!
+//! Every file in a corpus directory is a [`Sample`]. A sample starts with a
+//! header of `key: value` lines, followed by the input and optionally the
+//! expected tree, each section ending at a `---` line:
+//!
+//! ```text
+//! start: rule
+//! expect: accept
+//! ---
+//! yeah { "x" }
+//! ---
+//! (rule
+//!   (ident "yeah")
+//!   "{"
+//!   ...
+//! ```
+//!
+//! A file without any `---` line is taken as an input that the start rule of
+//! the grammar should accept, see [`Grammar::start`].
+
+use std::{
+    fmt::{self, Write},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    grammar::{Grammar, RuleId},
+    interp::{Interpreter, ParseError},
+};
+
+#[cfg(test)]
+mod test;
+
+const SEPARATOR: &str = "---";
+
+/// Whether a sample should parse
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Expectation {
+    #[default]
+    Accept,
+    Reject,
+}
+
+/// A single corpus test
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sample {
+    /// The rule to parse the input as, the first rule if `None`
+    pub start: Option<String>,
+    pub expect: Expectation,
+    pub input: String,
+    /// The expected [`crate::interp::Cst::snapshot`]
+    pub snapshot: Option<String>,
+    /// The header as written, kept when the sample is rewritten
+    header: String,
+}
+
+impl Sample {
+    /// Parses the contents of a sample file
+    ///
+    /// # Errors
+    /// When the header contains an unknown key or value.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut sections = split(text);
+        let (Some(header), Some(input)) = (sections.next(), sections.next()) else {
+            return Ok(Self {
+                input: strip_newline(text).to_owned(),
+                ..Self::default()
+            });
+        };
+        let mut sample = Self {
+            input: strip_newline(input).to_owned(),
+            snapshot: sections
+                .next()
+                .map(str::trim_end)
+                .filter(|s| !s.is_empty())
+                .map(String::from),
+            header: header.to_owned(),
+            ..Self::default()
+        };
+        for line in header.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(format!("expected `key: value`, found `{line}`"));
+            };
+            match (key.trim(), value.trim()) {
+                ("start", rule) => sample.start = Some(rule.to_owned()),
+                ("expect", "accept") => sample.expect = Expectation::Accept,
+                ("expect", "reject") => sample.expect = Expectation::Reject,
+                ("expect", other) => {
+                    return Err(format!("expected `accept` or `reject`, found `{other}`"));
+                }
+                (other, _) => return Err(format!("unknown key `{other}`")),
+            }
+        }
+        Ok(sample)
+    }
+
+    /// The sample file with its snapshot replaced
+    #[must_use]
+    pub fn with_snapshot(&self, snapshot: &str) -> String {
+        format!(
+            "{}{SEPARATOR}\n{}\n{SEPARATOR}\n{snapshot}\n",
+            self.header, self.input
+        )
+    }
+
+    /// The rule the input is parsed as
+    ///
+    /// # Errors
+    /// When the rule doesn't exist.
+    pub fn start_rule(&self, grammar: &Grammar) -> Result<RuleId, Failure> {
+        let start = self
+            .start
+            .as_ref()
+            .map_or_else(|| grammar.start(), |name| grammar.rule(name));
+        start.ok_or_else(|| Failure::UnknownStart(self.start.clone().unwrap_or_default()))
+    }
+
+    /// Parses the input and compares the result against the expectations
+    ///
+    /// On success returns the snapshot of the parsed tree, if any.
+    ///
+    /// # Errors
+    /// When the sample did not behave as expected.
+    pub fn check(&self, interp: &Interpreter<'_>) -> Result<Option<String>, Failure> {
+        let grammar = interp.grammar();
+        let start = self.start_rule(grammar)?;
+        match (interp.parse(start, &self.input), self.expect) {
+            (Ok(_), Expectation::Reject) => Err(Failure::Accepted),
+            (Err(error), Expectation::Accept) => Err(Failure::Rejected(error)),
+            (Err(_), Expectation::Reject) => Ok(None),
+            (Ok(cst), Expectation::Accept) => {
+                let actual = cst.snapshot(grammar, &self.input);
+                match &self.snapshot {
+                    Some(expected) if *expected != actual => Err(Failure::CstMismatch {
+                        expected: expected.clone(),
+                        actual,
+                    }),
+                    _ => Ok(Some(actual)),
+                }
+            }
+        }
+    }
+}
+
+/// Splits a file at lines consisting of only `---`
+fn split(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(text);
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let mut start = 0;
+        for line in text.split_inclusive('\n') {
+            if line.trim_end() == SEPARATOR {
+                rest = Some(&text[start + line.len()..]);
+                return Some(&text[..start]);
+            }
+            start += line.len();
+        }
+        rest = None;
+        Some(text)
+    })
+}
+
+fn strip_newline(text: &str) -> &str {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    text.strip_suffix('\r').unwrap_or(text)
+}
+
+/// Why a [`Sample`] failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// A sample expected to be rejected parsed
+    Accepted,
+    /// A sample expected to be accepted did not parse
+    Rejected(ParseError),
+    /// The parsed tree differs from the snapshot
+    CstMismatch { expected: String, actual: String },
+    /// The start rule does not exist
+    UnknownStart(String),
+    /// The sample file could not be read
+    Invalid(String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accepted => write!(f, "expected the input to be rejected, but it parsed"),
+            Self::Rejected(error) => write!(f, "expected the input to parse: {error}"),
+            Self::CstMismatch { expected, actual } => {
+                writeln!(f, "tree differs from snapshot (- expected, + actual)")?;
+                write!(f, "{}", diff(expected, actual))
+            }
+            Self::UnknownStart(name) if name.is_empty() => write!(f, "the grammar has no rules"),
+            Self::UnknownStart(name) => write!(f, "unknown start rule `{name}`"),
+            Self::Invalid(reason) => write!(f, "invalid sample: {reason}"),
+        }
+    }
+}
+
+/// A line based diff of two texts, lines are prefixed with `-`, `+` or ` `
+#[must_use]
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<_> = expected.lines().collect();
+    let new: Vec<_> = actual.lines().collect();
+    // lcs[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            let _ = writeln!(out, " {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            let _ = writeln!(out, "+{}", new[j]);
+            j += 1;
+        } else {
+            let _ = writeln!(out, "-{}", old[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// The outcome of running every sample of a directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub results: Vec<(PathBuf, Result<(), Failure>)>,
+    /// Samples whose snapshot was written
+    pub updated: Vec<PathBuf>,
+}
+
+impl Report {
+    #[must_use]
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|(_, r)| r.is_ok()).count()
+    }
+
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, result) in &self.results {
+            let status = if result.is_ok() { "ok" } else { "FAILED" };
+            writeln!(f, "{} ... {status}", path.display())?;
+        }
+        for (path, result) in &self.results {
+            if let Err(failure) = result {
+                writeln!(f, "\n---- {} ----\n{failure}", path.display())?;
+            }
+        }
+        for path in &self.updated {
+            writeln!(f, "updated {}", path.display())?;
+        }
+        let status = if self.is_ok() { "ok" } else { "FAILED" };
+        writeln!(
+            f,
+            "\ntest result: {status}. {} passed; {} failed",
+            self.passed(),
+            self.failed()
+        )
+    }
+}
+
+/// Runs every sample in `dir` and its subdirectories, in order of their path
+///
+/// With `update`, accepted samples get their snapshot (re)written instead of
+/// failing on a mismatch.
+///
+/// # Errors
+/// When the directory or a sample can't be read or written.
+pub fn run(interp: &Interpreter<'_>, dir: &Path, update: bool) -> io::Result<Report> {
+    let mut report = Report::default();
+    for (path, sample) in load(dir)? {
+        let sample = match sample {
+            Ok(sample) => sample,
+            Err(reason) => {
+                report.results.push((path, Err(Failure::Invalid(reason))));
+                continue;
+            }
+        };
+        let result = match sample.check(interp) {
+            Err(Failure::CstMismatch { actual, .. }) if update => Ok(Some(actual)),
+            result => result,
+        };
+        let result = match result {
+            Ok(Some(actual)) if update && sample.snapshot.as_ref() != Some(&actual) => {
+                fs::write(&path, sample.with_snapshot(&actual))?;
+                report.updated.push(path.clone());
+                Ok(())
+            }
+            result => result.map(drop),
+        };
+        report.results.push((path, result));
+    }
+    Ok(report)
+}
+
+/// Reads every sample in `dir` and its subdirectories, in order of their path
+///
+/// # Errors
+/// When the directory or a sample can't be read.
+pub fn load(dir: &Path) -> io::Result<Vec<(PathBuf, Result<Sample, String>)>> {
+    files(dir)?
+        .into_iter()
+        .map(|path| {
+            let sample = Sample::parse(&fs::read_to_string(&path)?);
+            Ok((path, sample))
+        })
+        .collect()
+}
+
+fn files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(self::files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
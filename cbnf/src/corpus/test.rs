@@ -0,0 +1,113 @@
+use std::fs;
+
+use expect_test::expect;
+use pretty_assertions::assert_eq;
+
+use crate::{
+    corpus::{diff, run, Expectation, Failure, Sample},
+    grammar::Grammar,
+    interp::Interpreter,
+    Cbnf,
+};
+
+const GRAMMAR: &str = r#"
+list { "[" (items | nil) "]" }
+items { ident | ident "," items }
+ident {}
+"#;
+
+fn grammar() -> Grammar {
+    Grammar::new(&Cbnf::parse(GRAMMAR), GRAMMAR)
+}
+
+#[test]
+fn parse_sample() {
+    let sample = Sample::parse("start: items\nexpect: reject\n---\na,\n---\n").unwrap();
+    assert_eq!(sample.start.as_deref(), Some("items"));
+    assert_eq!(sample.expect, Expectation::Reject);
+    assert_eq!(sample.input, "a,");
+    assert_eq!(sample.snapshot, None);
+
+    let sample = Sample::parse("[a]\n").unwrap();
+    assert_eq!(sample.start, None);
+    assert_eq!(sample.expect, Expectation::Accept);
+    assert_eq!(sample.input, "[a]");
+
+    let sample = Sample::parse("---\n[]\n---\n(list\n  \"[\"\n  \"]\")\n").unwrap();
+    assert_eq!(sample.snapshot.as_deref(), Some("(list\n  \"[\"\n  \"]\")"));
+
+    assert_eq!(
+        Sample::parse("expect: maybe\n---\n"),
+        Err("expected `accept` or `reject`, found `maybe`".into())
+    );
+    assert_eq!(
+        Sample::parse("rule: list\n---\n"),
+        Err("unknown key `rule`".into())
+    );
+}
+
+#[test]
+fn check_sample() {
+    let grammar = grammar();
+    let interp = Interpreter::new(&grammar);
+    let check = |text: &str| Sample::parse(text).unwrap().check(&interp);
+
+    assert!(check("[a, b]").is_ok());
+    assert!(check("start: items\n---\na, b\n").is_ok());
+    assert!(check("expect: reject\n---\n[a,]\n").is_ok());
+    assert_eq!(check("expect: reject\n---\n[a]\n"), Err(Failure::Accepted));
+    assert!(matches!(check("[a"), Err(Failure::Rejected(_))));
+    assert_eq!(
+        check("start: nope\n---\n"),
+        Err(Failure::UnknownStart("nope".into()))
+    );
+
+    let Err(failure) = check("---\n[a]\n---\n(list\n  \"[\"\n  \"]\")") else {
+        panic!("snapshot should differ");
+    };
+    expect![[r#"
+        tree differs from snapshot (- expected, + actual)
+         (list
+           "["
+        +  (items
+        +    (ident "a"))
+           "]")
+    "#]]
+    .assert_eq(&failure.to_string());
+}
+
+#[test]
+fn line_diff() {
+    assert_eq!(diff("a\nb\nc", "a\nc\nd"), " a\n-b\n c\n+d\n");
+    assert_eq!(diff("", "a"), "+a\n");
+    assert_eq!(diff("a", "a"), " a\n");
+}
+
+#[test]
+fn run_dir() {
+    let dir = std::env::temp_dir().join(format!("cbnf-corpus-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.txt"), "[a]\n").unwrap();
+    fs::write(dir.join("b.txt"), "expect: reject\n---\n[a\n").unwrap();
+    fs::write(dir.join("nested/c.txt"), "[a,]\n").unwrap();
+
+    let grammar = grammar();
+    let interp = Interpreter::new(&grammar);
+    let report = run(&interp, &dir, false).unwrap();
+    assert_eq!(report.passed(), 2);
+    assert_eq!(report.failed(), 1);
+    assert!(report.to_string().contains("nested/c.txt ... FAILED"));
+
+    let report = run(&interp, &dir, true).unwrap();
+    assert_eq!(report.updated, vec![dir.join("a.txt")]);
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "---\n[a]\n---\n(list\n  \"[\"\n  (items\n    (ident \"a\"))\n  \"]\")\n"
+    );
+    let report = run(&interp, &dir, false).unwrap();
+    assert_eq!(report.passed(), 2);
+    assert!(report.updated.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
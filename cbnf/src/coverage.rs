@@ -0,0 +1,297 @@
+//! which rules and alternatives a set of inputs exercises
+//!
+//! [`Coverage`] counts how often every rule is matched and every alternative
+//! of every alternation is taken in the trees of an [`Interpreter`]. What was
+//! never hit is listed by [`Coverage::uncovered`], which makes it easy to see
+//! which parts of a grammar a corpus doesn't test yet.
+//!
+//! Alternatives of a rule that was never matched aren't listed on their own,
+//! the rule already is. Parameterized rules are only ever matched through
+//! their instances, so only those are listed.
+
+use std::{fmt::Write, io, path::Path};
+
+use crate::{
+    corpus::{self, Expectation},
+    grammar::{AltId, Grammar, RuleId},
+    interp::{Cst, CstKind, Interpreter},
+    report::LineIndex,
+    span::BSpan,
+    util::json_string,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Something a [`Coverage`] never hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uncovered {
+    Rule {
+        rule: RuleId,
+        /// The span of the rule's name
+        span: BSpan,
+    },
+    Alternative {
+        rule: RuleId,
+        alt: AltId,
+        /// The position of the alternative within the alternation
+        index: usize,
+        span: BSpan,
+    },
+}
+
+impl Uncovered {
+    #[must_use]
+    pub const fn rule(&self) -> RuleId {
+        match self {
+            Self::Rule { rule, .. } | Self::Alternative { rule, .. } => *rule,
+        }
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> BSpan {
+        match self {
+            Self::Rule { span, .. } | Self::Alternative { span, .. } => *span,
+        }
+    }
+
+    /// A short description, such as ``alternative 2 of `a` is never taken``
+    #[must_use]
+    pub fn message(&self, grammar: &Grammar) -> String {
+        match self {
+            Self::Rule { rule, .. } => format!("rule `{}` is never matched", grammar.name(*rule)),
+            Self::Alternative { rule, index, .. } => format!(
+                "alternative {} of `{}` is never taken",
+                index + 1,
+                grammar.name(*rule)
+            ),
+        }
+    }
+}
+
+/// Hit counts of the rules and alternatives of a [`Grammar`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Matches of every rule, by [`RuleId`]
+    pub rules: Vec<usize>,
+    /// Times every alternative was taken, by [`AltId`] then index
+    pub alts: Vec<Vec<usize>>,
+}
+
+impl Coverage {
+    /// Nothing hit yet
+    #[must_use]
+    pub fn new(grammar: &Grammar) -> Self {
+        Self {
+            rules: vec![0; grammar.rules.len()],
+            alts: grammar
+                .alts
+                .iter()
+                .map(|alt| vec![0; alt.alternatives.len()])
+                .collect(),
+        }
+    }
+
+    /// Parses every accepted sample in a corpus directory, see
+    /// [`crate::corpus`]
+    ///
+    /// Samples that can't be read or don't parse are left out.
+    ///
+    /// # Errors
+    /// When the directory can't be read.
+    pub fn corpus(interp: &Interpreter<'_>, dir: &Path) -> io::Result<Self> {
+        let grammar = interp.grammar();
+        let mut coverage = Self::new(grammar);
+        for (_, sample) in corpus::load(dir)? {
+            let Ok(sample) = sample else { continue };
+            if sample.expect == Expectation::Reject {
+                continue;
+            }
+            let Ok(start) = sample.start_rule(grammar) else {
+                continue;
+            };
+            if let Ok(cst) = interp.parse(start, &sample.input) {
+                coverage.record(&cst);
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// Counts the rules and alternatives of a tree
+    pub fn record(&mut self, cst: &Cst) {
+        match cst.kind {
+            CstKind::Rule(id) | CstKind::Builtin(id) => self.rules[id] += 1,
+            CstKind::Literal => {}
+        }
+        for &(alt, index) in &cst.choices {
+            self.alts[alt][index] += 1;
+        }
+        for child in &cst.children {
+            self.record(child);
+        }
+    }
+
+    /// Adds the hits of another coverage of the same grammar
+    pub fn merge(&mut self, other: &Self) {
+        for (a, b) in self.rules.iter_mut().zip(&other.rules) {
+            *a += b;
+        }
+        for (a, b) in self.alts.iter_mut().zip(&other.alts) {
+            for (a, b) in a.iter_mut().zip(b) {
+                *a += b;
+            }
+        }
+    }
+
+    /// Covered and total rules
+    #[must_use]
+    pub fn rule_totals(&self) -> (usize, usize) {
+        let covered = self.rules.iter().filter(|&&hits| hits > 0).count();
+        (covered, self.rules.len())
+    }
+
+    /// Covered and total alternatives
+    #[must_use]
+    pub fn alternative_totals(&self) -> (usize, usize) {
+        let all = self.alts.iter().flatten();
+        let covered = all.clone().filter(|&&hits| hits > 0).count();
+        (covered, all.count())
+    }
+
+    /// Everything never hit, in source order
+    #[must_use]
+    pub fn uncovered(&self, grammar: &Grammar) -> Vec<Uncovered> {
+        let mut uncovered = Vec::new();
+        for (rule, def) in grammar.rules.iter().enumerate() {
+            if self.rules[rule] == 0 && def.params.is_empty() {
+                uncovered.push(Uncovered::Rule {
+                    rule,
+                    span: def.span,
+                });
+            }
+        }
+        for (alt, def) in grammar.alts.iter().enumerate() {
+            if self.rules[def.rule] == 0 {
+                continue;
+            }
+            for (index, &span) in def.alternatives.iter().enumerate() {
+                if self.alts[alt][index] == 0 {
+                    uncovered.push(Uncovered::Alternative {
+                        rule: def.rule,
+                        alt,
+                        index,
+                        span,
+                    });
+                }
+            }
+        }
+        uncovered.sort_by_key(|u| u.span().from);
+        uncovered
+    }
+
+    /// A summary followed by a `file:line:col: message` line for everything
+    /// never hit
+    ///
+    /// ```text
+    /// rules: 3/4 covered (75%)
+    /// alternatives: 3/4 covered (75%)
+    /// test.cbnf:1:13: alternative 2 of `a` is never taken
+    /// test.cbnf:1:21: rule `c` is never matched
+    /// ```
+    #[must_use]
+    pub fn text(&self, grammar: &Grammar, file: &str, src: &str) -> String {
+        let mut out = String::new();
+        for (what, (covered, total)) in [
+            ("rules", self.rule_totals()),
+            ("alternatives", self.alternative_totals()),
+        ] {
+            _ = writeln!(
+                out,
+                "{what}: {covered}/{total} covered ({}%)",
+                percent(covered, total)
+            );
+        }
+        let index = LineIndex::new(src);
+        for uncovered in self.uncovered(grammar) {
+            let (line, col) = index.line_col(uncovered.span().from, src);
+            _ = writeln!(out, "{file}:{line}:{col}: {}", uncovered.message(grammar));
+        }
+        out
+    }
+
+    /// The hits of every rule, the totals and everything never hit as JSON
+    ///
+    /// ```text
+    /// {
+    ///   "file": "test.cbnf",
+    ///   "rules": {"covered": 3, "total": 4},
+    ///   "alternatives": {"covered": 3, "total": 4},
+    ///   "hits": {"a": 2, ...},
+    ///   "uncovered": [
+    ///     {"kind": "alternative", "rule": "a", "index": 1, "from": 12,
+    ///      "to": 17, "line": 1, "column": 13},
+    ///     ...
+    ///   ]
+    /// }
+    /// ```
+    #[must_use]
+    pub fn json(&self, grammar: &Grammar, file: &str, src: &str) -> String {
+        let mut out = String::new();
+        _ = writeln!(out, "{{\n  \"file\": {},", json_string(file));
+        for (what, (covered, total)) in [
+            ("rules", self.rule_totals()),
+            ("alternatives", self.alternative_totals()),
+        ] {
+            _ = writeln!(
+                out,
+                "  \"{what}\": {{\"covered\": {covered}, \"total\": {total}}},"
+            );
+        }
+        let hits: Vec<_> = grammar
+            .rules
+            .iter()
+            .zip(&self.rules)
+            .map(|(rule, hits)| format!("{}: {hits}", json_string(&rule.name)))
+            .collect();
+        _ = writeln!(out, "  \"hits\": {{{}}},", hits.join(", "));
+        let index = LineIndex::new(src);
+        let uncovered: Vec<_> = self
+            .uncovered(grammar)
+            .iter()
+            .map(|uncovered| {
+                let span = uncovered.span();
+                let (line, col) = index.line_col(span.from, src);
+                let name = json_string(grammar.name(uncovered.rule()));
+                let what = match uncovered {
+                    Uncovered::Rule { .. } => format!("\"kind\": \"rule\", \"rule\": {name}"),
+                    Uncovered::Alternative { index, .. } => {
+                        format!("\"kind\": \"alternative\", \"rule\": {name}, \"index\": {index}")
+                    }
+                };
+                format!(
+                    "    {{{what}, \"from\": {}, \"to\": {}, \"line\": {line}, \"column\": {col}}}",
+                    span.from, span.to
+                )
+            })
+            .collect();
+        if uncovered.is_empty() {
+            out.push_str("  \"uncovered\": []\n}\n");
+        } else {
+            _ = write!(
+                out,
+                "  \"uncovered\": [\n{}\n  ]\n}}\n",
+                uncovered.join(",\n")
+            );
+        }
+        out
+    }
+}
+
+/// `part` of `total` as a whole percentage, rounded down, all of nothing is
+/// covered
+const fn percent(part: usize, total: usize) -> usize {
+    match (part * 100).checked_div(total) {
+        Some(percent) => percent,
+        None => 100,
+    }
+}
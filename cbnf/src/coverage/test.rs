@@ -0,0 +1,136 @@
+use std::fs;
+
+use expect_test::expect;
+use pretty_assertions::assert_eq;
+
+use crate::{
+    coverage::{Coverage, Uncovered},
+    grammar::Grammar,
+    interp::Interpreter,
+    Cbnf,
+};
+
+const GRAMMAR: &str = r#"a { b | "x" b | c } b { "y" ("z" | nil) } c { "w" }"#;
+
+fn grammar() -> Grammar {
+    Grammar::new(&Cbnf::parse(GRAMMAR), GRAMMAR)
+}
+
+fn coverage(grammar: &Grammar, inputs: &[&str]) -> Coverage {
+    let interp = Interpreter::new(grammar);
+    let mut coverage = Coverage::new(grammar);
+    for input in inputs {
+        coverage.record(&interp.parse(0, input).unwrap());
+    }
+    coverage
+}
+
+#[test]
+fn hits() {
+    let grammar = grammar();
+    let coverage = coverage(&grammar, &["y", "x y z"]);
+    assert_eq!(coverage.rules, [2, 2, 0]);
+    assert_eq!(coverage.alts, [vec![1, 1, 0], vec![1, 1]]);
+    assert_eq!(coverage.rule_totals(), (2, 3));
+    assert_eq!(coverage.alternative_totals(), (4, 5));
+}
+
+#[test]
+fn uncovered() {
+    let grammar = grammar();
+    let coverage = coverage(&grammar, &["y"]);
+    assert_eq!(
+        coverage.uncovered(&grammar),
+        [
+            Uncovered::Alternative {
+                rule: 0,
+                alt: 0,
+                index: 1,
+                span: grammar.alts[0].alternatives[1],
+            },
+            Uncovered::Alternative {
+                rule: 0,
+                alt: 0,
+                index: 2,
+                span: grammar.alts[0].alternatives[2],
+            },
+            Uncovered::Alternative {
+                rule: 1,
+                alt: 1,
+                index: 0,
+                span: grammar.alts[1].alternatives[0],
+            },
+            Uncovered::Rule {
+                rule: 2,
+                span: grammar.rules[2].span,
+            },
+        ]
+    );
+    // the alternatives of a rule never matched aren't listed on their own
+    let nothing = Coverage::new(&grammar);
+    assert_eq!(nothing.uncovered(&grammar).len(), 3);
+}
+
+#[test]
+fn merge() {
+    let grammar = grammar();
+    let mut a = coverage(&grammar, &["y"]);
+    a.merge(&coverage(&grammar, &["w"]));
+    assert_eq!(a, coverage(&grammar, &["y", "w"]));
+}
+
+#[test]
+fn text() {
+    let grammar = grammar();
+    let coverage = coverage(&grammar, &["y z", "w"]);
+    expect![[r"
+        rules: 3/3 covered (100%)
+        alternatives: 3/5 covered (60%)
+        test.cbnf:1:7: alternative 2 of `a` is never taken
+        test.cbnf:1:34: alternative 2 of `b` is never taken
+    "]]
+    .assert_eq(&coverage.text(&grammar, "test.cbnf", GRAMMAR));
+}
+
+#[test]
+fn json() {
+    let grammar = grammar();
+    let coverage = coverage(&grammar, &["x y"]);
+    expect![[r#"
+        {
+          "file": "dir/\"test\".cbnf",
+          "rules": {"covered": 2, "total": 3},
+          "alternatives": {"covered": 2, "total": 5},
+          "hits": {"a": 1, "b": 1, "c": 0},
+          "uncovered": [
+            {"kind": "alternative", "rule": "a", "index": 0, "from": 4, "to": 5, "line": 1, "column": 5},
+            {"kind": "alternative", "rule": "a", "index": 2, "from": 14, "to": 17, "line": 1, "column": 15},
+            {"kind": "alternative", "rule": "b", "index": 0, "from": 29, "to": 32, "line": 1, "column": 30},
+            {"kind": "rule", "rule": "c", "from": 42, "to": 43, "line": 1, "column": 43}
+          ]
+        }
+    "#]]
+    .assert_eq(&coverage.json(&grammar, "dir/\"test\".cbnf", GRAMMAR));
+    let empty = Grammar::new(&Cbnf::parse(""), "");
+    let json = Coverage::new(&empty).json(&empty, "", "");
+    assert!(json.contains("\"uncovered\": []"));
+}
+
+#[test]
+fn corpus() {
+    let dir = std::env::temp_dir().join(format!("cbnf-coverage-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "y z\n").unwrap();
+    fs::write(dir.join("b.txt"), "expect: reject\n---\nw w\n").unwrap();
+    fs::write(dir.join("c.txt"), "start: c\n---\nw\n").unwrap();
+    fs::write(dir.join("d.txt"), "start: nope\n---\nw\n").unwrap();
+
+    let grammar = grammar();
+    let interp = Interpreter::new(&grammar);
+    let coverage = Coverage::corpus(&interp, &dir).unwrap();
+    assert_eq!(coverage.rules, [1, 1, 1]);
+    assert_eq!(coverage.alts, [vec![1, 0, 0], vec![1, 0]]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,253 @@
+//! a CYK recognizer over the Chomsky normal form of a grammar
+//!
+//! [`Cyk`] is the textbook O(n³) algorithm: for every range of the input,
+//! shortest first, it works out which rules of [`Bnf::cnf`] match exactly
+//! that range. It is slow but simple enough to be obviously right, which
+//! makes it a reference to check the faster engines against in tests.
+//!
+//! Ranges start at positions a terminal could start at, and whitespace and
+//! the `@trivia` rule are skipped before every terminal. Like the normal
+//! forms, it takes lookahead predicates to always hold and ignores `,` and
+//! `@lexical`, so it only agrees with [`crate::earley`] on grammars without
+//! them.
+//!
+//! [`Cyk::chart`] returns every range and the rules matching it.
+//!
+//! ```
+//! use cbnf::{cyk::Cyk, grammar::Grammar, Cbnf};
+//!
+//! let src = r#"e { e "+" e | "1" }"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! let cyk = Cyk::new(&grammar, 0);
+//! assert!(cyk.recognize("1 + 1 + 1"));
+//! assert!(!cyk.recognize("1 +"));
+//! ```
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    enumerate::Terminal,
+    grammar::{insensitive_prefix, Grammar, RuleId},
+    interp::{Builtins, DefaultBuiltins, Scan, Trivia},
+    normal::{Bnf, Symbol},
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Recognizes input with the CYK algorithm, see the [module docs](self)
+pub struct Cyk<'g> {
+    cnf: Bnf<'g>,
+    builtins: Box<dyn Builtins + 'g>,
+    skip_whitespace: bool,
+    /// `(rule, left, right)` for every production of two rules
+    pairs: Vec<(usize, usize, usize)>,
+}
+
+impl<'g> Cyk<'g> {
+    /// A recognizer of `start` using [`DefaultBuiltins`] that skips
+    /// whitespace
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    #[must_use]
+    pub fn new(grammar: &'g Grammar, start: RuleId) -> Self {
+        let cnf = Bnf::new(grammar, start).cnf();
+        let pairs = cnf
+            .rules
+            .iter()
+            .enumerate()
+            .flat_map(|(r, rule)| {
+                rule.productions
+                    .iter()
+                    .filter_map(move |p| match p.symbols.as_slice() {
+                        &[Symbol::Rule(left), Symbol::Rule(right)] => Some((r, left, right)),
+                        _ => None,
+                    })
+            })
+            .collect();
+        Self {
+            cnf,
+            builtins: Box::new(DefaultBuiltins),
+            skip_whitespace: true,
+            pairs,
+        }
+    }
+
+    /// Matches rules with an empty body with `builtins`
+    #[must_use]
+    pub fn builtins(mut self, builtins: impl Builtins + 'g) -> Self {
+        self.builtins = Box::new(builtins);
+        self
+    }
+
+    /// Whether whitespace is skipped before terminals and at the end of
+    /// input
+    #[must_use]
+    pub const fn skip_whitespace(mut self, skip: bool) -> Self {
+        self.skip_whitespace = skip;
+        self
+    }
+
+    /// The grammar in Chomsky normal form, which the rules of a [`Chart`]
+    /// index into
+    #[must_use]
+    pub const fn cnf(&self) -> &Bnf<'g> {
+        &self.cnf
+    }
+
+    /// Whether all of `input` matches the start rule
+    #[must_use]
+    pub fn recognize(&self, input: &str) -> bool {
+        self.chart(input).accepted
+    }
+
+    /// Every range of `input` with the rules matching it
+    #[must_use]
+    pub fn chart(&self, input: &str) -> Chart {
+        let trivia = Trivia::new(
+            self.cnf.grammar,
+            &*self.builtins,
+            self.skip_whitespace,
+            input,
+        );
+        let skip = |pos: usize| trivia.skip(pos);
+        // the terminal rules matching from every position a terminal could
+        // start at, found from the start of the input onwards
+        let mut starts = vec![0];
+        let mut terminals = BTreeMap::<usize, Vec<(usize, usize)>>::new();
+        let mut i = 0;
+        while i < starts.len() {
+            let pos = starts[i];
+            i += 1;
+            let at = Scan {
+                input,
+                pos,
+                skipped: skip(pos),
+            };
+            let mut matches = Vec::new();
+            for (r, rule) in self.cnf.rules.iter().enumerate() {
+                for production in &rule.productions {
+                    let ends = match production.symbols.as_slice() {
+                        [Symbol::Terminal(terminal)] => self.scan(terminal, at),
+                        _ => continue,
+                    };
+                    matches.extend(ends.into_iter().map(|end| (r, end)));
+                }
+            }
+            for &(_, end) in &matches {
+                if !starts.contains(&end) {
+                    starts.push(end);
+                }
+            }
+            terminals.insert(pos, matches);
+        }
+        starts.sort_unstable();
+
+        // cells[a][b][r] is whether rule r matches from starts[a] to starts[b]
+        let rules = self.cnf.rules.len();
+        let len = starts.len();
+        let mut cells = vec![vec![vec![false; rules]; len]; len];
+        for width in 0..len {
+            for a in 0..len - width {
+                let b = a + width;
+                for &(r, end) in &terminals[&starts[a]] {
+                    if end == starts[b] {
+                        cells[a][b][r] = true;
+                    }
+                }
+                // a split may leave either side empty, so a cell can depend
+                // on itself
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for &(r, left, right) in &self.pairs {
+                        if !cells[a][b][r]
+                            && (a..=b).any(|k| cells[a][k][left] && cells[k][b][right])
+                        {
+                            cells[a][b][r] = true;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        let start = self.cnf.start;
+        let empty = self.cnf.rules[start]
+            .productions
+            .iter()
+            .any(|p| p.symbols.is_empty());
+        let accepted = (empty && skip(0) == input.len())
+            || (0..len).any(|b| cells[0][b][start] && skip(starts[b]) == input.len());
+        let mut spans = BTreeMap::<BSpan, Vec<usize>>::new();
+        for a in 0..len {
+            for b in a..len {
+                let matched: Vec<_> = (0..rules).filter(|&r| cells[a][b][r]).collect();
+                if matched.is_empty() {
+                    continue;
+                }
+                let to = starts[b];
+                let from = skip(starts[a]).min(to);
+                #[allow(clippy::cast_possible_truncation)]
+                let span = BSpan {
+                    from: from as u32,
+                    to: to as u32,
+                };
+                let entry = spans.entry(span).or_default();
+                entry.extend(matched);
+                entry.sort_unstable();
+                entry.dedup();
+            }
+        }
+        Chart { accepted, spans }
+    }
+
+    /// The ends of `terminal` matched at `at`
+    fn scan(&self, terminal: &Terminal, at: Scan) -> Vec<usize> {
+        let len = match terminal {
+            Terminal::Literal(lit) => at.rest().starts_with(lit.as_str()).then_some(lit.len()),
+            Terminal::Insensitive(lit) => insensitive_prefix(at.rest(), lit),
+            Terminal::Builtin(id) => return self.builtins.scan(self.cnf.grammar.name(*id), at),
+        };
+        len.map(|len| at.skipped + len).into_iter().collect()
+    }
+}
+
+/// The ranges of an input matched by the rules of [`Cyk::cnf`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chart {
+    /// Whether all of the input matches the start rule
+    pub accepted: bool,
+    /// Every range, without leading whitespace, with the rules matching it
+    /// in order
+    pub spans: BTreeMap<BSpan, Vec<usize>>,
+}
+
+impl Chart {
+    /// The rules matching exactly `span`
+    #[must_use]
+    pub fn rules(&self, span: BSpan) -> &[usize] {
+        self.spans.get(&span).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every range of `input` on its own line, with the names of the rules
+    /// of `cnf` matching it
+    #[must_use]
+    pub fn text(&self, cnf: &Bnf, input: &str) -> String {
+        let mut out = String::new();
+        for (span, rules) in &self.spans {
+            let names: Vec<_> = rules.iter().map(|&r| cnf.rules[r].name.as_str()).collect();
+            let _ = writeln!(
+                out,
+                "{}..{} {:?}: {}",
+                span.from,
+                span.to,
+                span.slice(input),
+                names.join(", ")
+            );
+        }
+        out
+    }
+}
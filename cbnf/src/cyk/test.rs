@@ -0,0 +1,157 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{cyk::Cyk, earley::Earley, grammar::test::grammar, span::BSpan};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, input: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let cyk = Cyk::new(&grammar, 0);
+    let chart = cyk.chart(input);
+    let actual = format!(
+        "accepted: {}\n{}",
+        chart.accepted,
+        chart.text(cyk.cnf(), input)
+    );
+    expect.assert_eq(&actual);
+}
+
+/// Every string of at most `max` of `words`, joined by `separator`
+fn strings(words: &[&str], max: usize, separator: &str) -> Vec<String> {
+    let mut all = vec![Vec::new()];
+    let mut last = vec![Vec::new()];
+    for _ in 0..max {
+        last = last
+            .iter()
+            .flat_map(|s: &Vec<&str>| words.iter().map(move |w| [s.as_slice(), &[*w]].concat()))
+            .collect();
+        all.extend(last.iter().cloned());
+    }
+    all.iter().map(|s| s.join(separator)).collect()
+}
+
+/// CYK and Earley accept the same strings of `words`
+fn agrees(src: &str, words: &[&str], max: usize) {
+    let grammar = grammar(src);
+    let cyk = Cyk::new(&grammar, 0);
+    let earley = Earley::new(&grammar);
+    let mut accepted = 0;
+    for separator in [" ", ""] {
+        for input in strings(words, max, separator) {
+            let expected = earley.recognize(0, &input);
+            assert_eq!(cyk.recognize(&input), expected, "recognizing {input:?}");
+            accepted += usize::from(expected);
+        }
+    }
+    assert!(accepted > 0);
+}
+
+#[test]
+fn expressions() {
+    agrees(
+        r#"e { e "+" t | t } t { t "*" f | f } f { "x" | "(" e ")" }"#,
+        &["x", "+", "*", "(", ")"],
+        5,
+    );
+}
+
+#[test]
+fn ambiguous() {
+    agrees(r#"e { e e | e "+" e | "a" | nil }"#, &["a", "+"], 6);
+}
+
+#[test]
+fn nullable() {
+    agrees(
+        r#"list { item list | nil } item { "a" | "b" opt } opt { "?" | nil }"#,
+        &["a", "b", "?"],
+        5,
+    );
+}
+
+#[test]
+fn builtins() {
+    agrees(
+        r#"call { ident "(" (args | nil) ")" } args { ident | args "," ident } ident {}"#,
+        &["f", "(", ")", ",", "x1"],
+        5,
+    );
+}
+
+#[test]
+fn trivia() {
+    let src = r##"list { "x" list | nil } @trivia comment { "#" ident } ident {}"##;
+    agrees(src, &["x", "#", "c"], 4);
+    let grammar = grammar(src);
+    assert!(Cyk::new(&grammar, 0).recognize("x #c x #c"));
+}
+
+#[test]
+fn insensitive() {
+    let src = r#"s { i"select" ident | i"sel" } ident {}"#;
+    agrees(src, &["select", "SELECT", "Sel", "se", "x"], 2);
+    let grammar = grammar(src);
+    let cyk = Cyk::new(&grammar, 0);
+    assert!(cyk.recognize("SELECT x"));
+    assert!(cyk.recognize("sEl"));
+    assert!(!cyk.recognize("selec x"));
+}
+
+#[test]
+fn whitespace() {
+    let grammar = grammar(r#"pair { "a" "b" }"#);
+    let cyk = Cyk::new(&grammar, 0);
+    assert!(cyk.recognize(" a b "));
+    let cyk = cyk.skip_whitespace(false);
+    assert!(cyk.recognize("ab"));
+    assert!(!cyk.recognize("a b"));
+}
+
+#[test]
+fn chart() {
+    check(
+        r#"e { e "+" e | "1" }"#,
+        "1 + 1 + 1",
+        expect![[r#"
+            accepted: true
+            0..1 "1": e, e#1
+            0..5 "1 + 1": e, e#1
+            0..9 "1 + 1 + 1": e, e#1
+            2..3 "+": T"+"
+            2..5 "+ 1": e#2
+            2..9 "+ 1 + 1": e#2
+            4..5 "1": e, e#1
+            4..9 "1 + 1": e, e#1
+            6..7 "+": T"+"
+            6..9 "+ 1": e#2
+            8..9 "1": e, e#1
+        "#]],
+    );
+    check(
+        r#"e { e "+" e | "1" }"#,
+        "1 +",
+        expect![[r#"
+            accepted: false
+            0..1 "1": e, e#1
+            2..3 "+": T"+"
+        "#]],
+    );
+}
+
+#[test]
+fn rules() {
+    let grammar = grammar(r#"pair { "(" "x" ")" }"#);
+    let cyk = Cyk::new(&grammar, 0);
+    let chart = cyk.chart("(x)");
+    let names = |from, to| {
+        let rules = chart.rules(BSpan { from, to });
+        rules
+            .iter()
+            .map(|&r| cyk.cnf().rules[r].name.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert!(chart.accepted);
+    assert_eq!(names(0, 3), ["pair"]);
+    assert_eq!(names(1, 2), [r#"T"x""#]);
+    assert_eq!(names(0, 2), Vec::<&str>::new());
+}
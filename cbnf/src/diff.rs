@@ -0,0 +1,866 @@
+//! structural diffs between two versions of a grammar
+//!
+//! [`Diff::new`] matches up the rules of two grammars by name and compares
+//! their bodies term by term, ignoring spans, so moving or reformatting a
+//! rule is no change, and neither is reordering alternatives. References
+//! are compared by name, so a rule that only refers to a removed rule is
+//! unchanged. Instances of parameterized rules are left out, their
+//! templates stand for them, and so are the rules of `@prec` towers: the
+//! levels of a `@prec` rule are compared one by one, a new level being an
+//! added alternative.
+//!
+//! Literals are compared by what they match, so `"x"` and `'x'` are the
+//! same, and alternatives are also compared multiplied out, so
+//! `"x" ("y" | "z")` and `"x" "y" | "x" "z"` are the same too.
+//!
+//! Every change is given a [`Class`], as if the grammars were context-free:
+//! a new rule, alternative or optional term such as `("," | nil)` is
+//! [`Class::Widening`], as every sentence accepted before still is, and so
+//! is a literal made case-insensitive or a removed `,`. A removed rule,
+//! alternative or optional term, or a new `,`, is [`Class::Narrowing`], and
+//! anything else, such as a replaced term or a changed label, is
+//! [`Class::Incompatible`]. Attributes other than `start`, such as
+//! `deprecated`, don't change what is accepted, so adding or removing them
+//! is [`Class::Equivalent`]. A rule or grammar with both widening and
+//! narrowing changes is incompatible. In semver terms only widening and
+//! equivalent changes are additive, the others are breaking. The classes
+//! don't account for ordered choice or lookahead, changes within a
+//! predicate are always incompatible.
+//!
+//! ```
+//! use cbnf::{diff::{Class, Diff}, grammar::Grammar, Cbnf};
+//!
+//! let old = r#"value { "true" | "false" }"#;
+//! let new = r#"value { "true" | "false" | "null" }"#;
+//! let (old, new) = (
+//!     Grammar::new(&Cbnf::parse(old), old),
+//!     Grammar::new(&Cbnf::parse(new), new),
+//! );
+//! assert_eq!(Diff::new(&old, &new).class(), Some(Class::Widening));
+//! assert_eq!(Diff::new(&new, &old).class(), Some(Class::Narrowing));
+//! ```
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{
+    grammar::{insensitive_prefix, Grammar, Node, NodeKind, RuleId},
+    report::LineIndex,
+    span::BSpan,
+    util::json_string,
+    Assoc, RuleKind,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The most alternatives a node is multiplied out into, beyond which it
+/// is only compared as written
+const MAX_EXPANDED: usize = 64;
+
+/// How a change affects the sentences a grammar accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Class {
+    /// The same sentences are accepted
+    Equivalent,
+    /// Everything accepted before still is
+    Widening,
+    /// Nothing new is accepted
+    Narrowing,
+    /// Some sentences may be gained and others lost
+    Incompatible,
+}
+
+impl Class {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Equivalent => "equivalent",
+            Self::Widening => "widening",
+            Self::Narrowing => "narrowing",
+            Self::Incompatible => "incompatible",
+        }
+    }
+
+    /// The class of two changes made together
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        if self == other || other == Self::Equivalent {
+            self
+        } else if self == Self::Equivalent {
+            other
+        } else {
+            Self::Incompatible
+        }
+    }
+
+    /// Whether a change of this class breaks existing sentences or trees
+    #[must_use]
+    pub const fn is_breaking(self) -> bool {
+        !matches!(self, Self::Equivalent | Self::Widening)
+    }
+}
+
+/// How a rule differs between two grammars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleChange {
+    /// Only in the right grammar
+    Added,
+    /// Only in the left grammar
+    Removed,
+    /// In both, with a different body, kind, attributes or parameters
+    Changed,
+}
+
+/// A change within a rule that is in both grammars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// An alternative only in the right grammar
+    AddedAlternative,
+    /// An alternative only in the left grammar
+    RemovedAlternative,
+    /// A term replaced by another
+    Replaced,
+    /// A term replaced by one that matches more, such as `i"x"` for `"x"`,
+    /// or two terms no longer joined by a `,`
+    Widened,
+    /// A term replaced by one that matches less, or two terms joined by a
+    /// new `,`
+    Narrowed,
+    /// A term with a different label
+    Relabeled,
+    /// A rule with a different kind or parameters, with or without `start`,
+    /// or that was or became a rule with an empty body
+    Redefined,
+    /// A rule with different attributes that don't change what it accepts,
+    /// such as `deprecated`
+    Annotated,
+}
+
+impl ChangeKind {
+    #[must_use]
+    pub const fn class(self) -> Class {
+        match self {
+            Self::AddedAlternative | Self::Widened => Class::Widening,
+            Self::RemovedAlternative | Self::Narrowed => Class::Narrowing,
+            Self::Replaced | Self::Relabeled | Self::Redefined => Class::Incompatible,
+            Self::Annotated => Class::Equivalent,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AddedAlternative => "added_alternative",
+            Self::RemovedAlternative => "removed_alternative",
+            Self::Replaced => "replaced",
+            Self::Widened => "widened",
+            Self::Narrowed => "narrowed",
+            Self::Relabeled => "relabeled",
+            Self::Redefined => "redefined",
+            Self::Annotated => "annotated",
+        }
+    }
+}
+
+/// A single change, with the span of what changed in either grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub left: Option<BSpan>,
+    pub right: Option<BSpan>,
+}
+
+/// A rule that differs between two grammars
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleDiff {
+    pub name: String,
+    pub change: RuleChange,
+    /// The span of the rule's name in the left grammar
+    pub left: Option<BSpan>,
+    /// The span of the rule's name in the right grammar
+    pub right: Option<BSpan>,
+    /// What changed within a [`RuleChange::Changed`] rule, in the order of
+    /// its body
+    pub changes: Vec<Change>,
+}
+
+impl RuleDiff {
+    #[must_use]
+    pub fn class(&self) -> Class {
+        match self.change {
+            RuleChange::Added => Class::Widening,
+            RuleChange::Removed => Class::Narrowing,
+            RuleChange::Changed => self
+                .changes
+                .iter()
+                .map(|c| c.kind.class())
+                .reduce(Class::and)
+                .unwrap_or(Class::Incompatible),
+        }
+    }
+}
+
+/// The rules that differ between two grammars, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    /// Removed and changed rules in the order of the left grammar, followed
+    /// by the added rules in the order of the right grammar
+    pub rules: Vec<RuleDiff>,
+}
+
+impl Diff {
+    /// The changes from `left` to `right`
+    #[must_use]
+    pub fn new(left: &Grammar, right: &Grammar) -> Self {
+        let mut rules = Vec::new();
+        for (id, name) in written(left) {
+            let diff = match right.rule(name) {
+                None => RuleDiff {
+                    name: name.to_owned(),
+                    change: RuleChange::Removed,
+                    left: Some(left.rules[id].span),
+                    right: None,
+                    changes: Vec::new(),
+                },
+                Some(other) => {
+                    let mut differ = Differ {
+                        left,
+                        right,
+                        changes: Vec::new(),
+                    };
+                    differ.rule(id, other);
+                    if differ.changes.is_empty() {
+                        continue;
+                    }
+                    RuleDiff {
+                        name: name.to_owned(),
+                        change: RuleChange::Changed,
+                        left: Some(left.rules[id].span),
+                        right: Some(right.rules[other].span),
+                        changes: differ.changes,
+                    }
+                }
+            };
+            rules.push(diff);
+        }
+        for (id, name) in written(right) {
+            if left.rule(name).is_none() {
+                rules.push(RuleDiff {
+                    name: name.to_owned(),
+                    change: RuleChange::Added,
+                    left: None,
+                    right: Some(right.rules[id].span),
+                    changes: Vec::new(),
+                });
+            }
+        }
+        Self { rules }
+    }
+
+    /// The class of all changes together, `None` when nothing changed
+    #[must_use]
+    pub fn class(&self) -> Option<Class> {
+        self.rules.iter().map(RuleDiff::class).reduce(Class::and)
+    }
+
+    /// A line for every rule and every change within it, followed by the
+    /// class of the whole diff
+    ///
+    /// ```text
+    /// ~ value (left 1:1, right 1:1): widening
+    ///     + alternative `"null"` (right 1:28)
+    /// + number (right 2:1): widening
+    /// widening, every change is additive
+    /// ```
+    #[must_use]
+    pub fn text(&self, left: &str, right: &str) -> String {
+        let at = Positions::new(left, right);
+        let mut out = String::new();
+        for rule in &self.rules {
+            let (sign, spans) = match rule.change {
+                RuleChange::Added => ('+', format!("right {}", at.right(rule.right))),
+                RuleChange::Removed => ('-', format!("left {}", at.left(rule.left))),
+                RuleChange::Changed => (
+                    '~',
+                    format!(
+                        "left {}, right {}",
+                        at.left(rule.left),
+                        at.right(rule.right)
+                    ),
+                ),
+            };
+            _ = writeln!(
+                out,
+                "{sign} {} ({spans}): {}",
+                rule.name,
+                rule.class().name()
+            );
+            for change in &rule.changes {
+                let term = |src: &str, span: Option<BSpan>| {
+                    let text = span.map_or("", |s| s.slice(src));
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                };
+                let (left_term, right_term) = (term(left, change.left), term(right, change.right));
+                let (left_at, right_at) = (at.left(change.left), at.right(change.right));
+                _ = match change.kind {
+                    ChangeKind::AddedAlternative => writeln!(
+                        out,
+                        "    + alternative `{right_term}` (right {right_at})"
+                    ),
+                    ChangeKind::RemovedAlternative => writeln!(
+                        out,
+                        "    - alternative `{left_term}` (left {left_at})"
+                    ),
+                    ChangeKind::Replaced => writeln!(
+                        out,
+                        "    ~ `{left_term}` became `{right_term}` (left {left_at}, right {right_at})"
+                    ),
+                    ChangeKind::Widened => writeln!(
+                        out,
+                        "    ~ `{left_term}` widened to `{right_term}` (left {left_at}, right {right_at})"
+                    ),
+                    ChangeKind::Narrowed => writeln!(
+                        out,
+                        "    ~ `{left_term}` narrowed to `{right_term}` (left {left_at}, right {right_at})"
+                    ),
+                    ChangeKind::Relabeled => writeln!(
+                        out,
+                        "    ~ label of `{left_term}` became `{right_term}` (left {left_at}, right {right_at})"
+                    ),
+                    ChangeKind::Redefined => writeln!(
+                        out,
+                        "    ~ kind, start, parameters or builtin (left {left_at}, right {right_at})"
+                    ),
+                    ChangeKind::Annotated => writeln!(
+                        out,
+                        "    ~ attributes (left {left_at}, right {right_at})"
+                    ),
+                };
+            }
+        }
+        _ = writeln!(
+            out,
+            "{}",
+            match self.class() {
+                None => "no changes",
+                Some(Class::Equivalent) => "equivalent, no change is breaking",
+                Some(Class::Widening) => "widening, every change is additive",
+                Some(Class::Narrowing) => "narrowing, some changes are breaking",
+                Some(Class::Incompatible) => "incompatible, some changes are breaking",
+            }
+        );
+        out
+    }
+
+    /// The files compared, the class of the diff and every rule with its
+    /// changes as JSON
+    ///
+    /// ```text
+    /// {
+    ///   "left": "old.cbnf",
+    ///   "right": "new.cbnf",
+    ///   "class": "widening",
+    ///   "rules": [
+    ///     {"name": "value", "change": "changed", "class": "widening",
+    ///      "left": {"from": 0, "to": 5, "line": 1, "column": 1},
+    ///      "right": {...}, "changes": [
+    ///       {"kind": "added_alternative", "class": "widening",
+    ///        "left": null, "right": {...}},
+    ///       ...
+    ///     ]},
+    ///     ...
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// `class` is `null` when nothing changed.
+    #[must_use]
+    pub fn json(&self, left: (&str, &str), right: (&str, &str)) -> String {
+        let at = Positions::new(left.1, right.1);
+        let mut out = String::new();
+        _ = writeln!(
+            out,
+            "{{\n  \"left\": {},\n  \"right\": {},",
+            json_string(left.0),
+            json_string(right.0)
+        );
+        let class = self
+            .class()
+            .map_or_else(|| "null".into(), |c| json_string(c.name()));
+        _ = writeln!(out, "  \"class\": {class},");
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let change = match rule.change {
+                    RuleChange::Added => "added",
+                    RuleChange::Removed => "removed",
+                    RuleChange::Changed => "changed",
+                };
+                let changes: Vec<_> = rule
+                    .changes
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "      {{\"kind\": \"{}\", \"class\": \"{}\", \"left\": {}, \"right\": {}}}",
+                            c.kind.name(),
+                            c.kind.class().name(),
+                            at.left_json(c.left),
+                            at.right_json(c.right)
+                        )
+                    })
+                    .collect();
+                let changes = if changes.is_empty() {
+                    "[]".into()
+                } else {
+                    format!("[\n{}\n    ]", changes.join(",\n"))
+                };
+                format!(
+                    "    {{\"name\": {}, \"change\": \"{change}\", \"class\": \"{}\", \"left\": {}, \"right\": {}, \"changes\": {changes}}}",
+                    json_string(&rule.name),
+                    rule.class().name(),
+                    at.left_json(rule.left),
+                    at.right_json(rule.right)
+                )
+            })
+            .collect();
+        if rules.is_empty() {
+            out.push_str("  \"rules\": []\n}\n");
+        } else {
+            _ = write!(out, "  \"rules\": [\n{}\n  ]\n}}\n", rules.join(",\n"));
+        }
+        out
+    }
+}
+
+/// Every rule written in `grammar`, leaving out instances and the levels
+/// of `@prec` rules
+fn written(grammar: &Grammar) -> impl Iterator<Item = (RuleId, &str)> {
+    grammar
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.template.is_none() && r.tower.is_none())
+        .map(|(id, r)| (id, r.name.as_str()))
+}
+
+/// The levels of the `@prec` rule `id` from the loosest, as the
+/// associativity and the sequence of operands and operators of each,
+/// followed by the operand of the tightest level
+fn tower(grammar: &Grammar, id: RuleId) -> (Vec<(Assoc, &Node)>, Option<&Node>) {
+    let mut levels = Vec::new();
+    let mut level = id;
+    let mut body = grammar.rules[id].body.as_ref();
+    while let Some(node) = body {
+        let NodeKind::Alt(_, alternatives) = &node.kind else {
+            break;
+        };
+        let [binary, next] = &alternatives[..] else {
+            break;
+        };
+        let NodeKind::Seq(terms) = &binary.kind else {
+            break;
+        };
+        let [lhs, _, rhs] = &terms[..] else {
+            break;
+        };
+        let assoc = if lhs.kind == NodeKind::Rule(level) {
+            Assoc::Left
+        } else if rhs.kind == NodeKind::Rule(level) {
+            Assoc::Right
+        } else {
+            Assoc::NonAssoc
+        };
+        levels.push((assoc, binary));
+        body = Some(next);
+        match next.kind {
+            NodeKind::Rule(next) if grammar.rules[next].tower == Some(id) => {
+                level = next;
+                body = grammar.rules[next].body.as_ref();
+            }
+            _ => break,
+        }
+    }
+    (levels, body)
+}
+
+/// The alternatives of `node` as sequences of terms, with the groups in
+/// them multiplied out, `None` when there are more than [`MAX_EXPANDED`]
+///
+/// Labeled groups are kept whole, as are predicates.
+fn expand(node: &Node) -> Option<Vec<Vec<&Node>>> {
+    fn term(node: &Node) -> Option<Vec<Vec<&Node>>> {
+        match node.label {
+            Some(_) => Some(vec![vec![node]]),
+            None => expand(node),
+        }
+    }
+    let out = match &node.kind {
+        NodeKind::Nil => vec![Vec::new()],
+        NodeKind::Alt(_, nodes) => {
+            let mut out = Vec::new();
+            for node in nodes {
+                out.extend(term(node)?);
+            }
+            out
+        }
+        NodeKind::Seq(nodes) => nodes.iter().try_fold(vec![Vec::new()], |heads, node| {
+            let tails = term(node)?;
+            (heads.len() * tails.len() <= MAX_EXPANDED).then(|| {
+                heads
+                    .iter()
+                    .flat_map(|head| tails.iter().map(move |tail| [&head[..], tail].concat()))
+                    .collect()
+            })
+        })?,
+        _ => vec![vec![node]],
+    };
+    (out.len() <= MAX_EXPANDED).then_some(out)
+}
+
+/// Whether every text the literal `a` matches is also matched by the
+/// literal `b`
+fn within(a: &NodeKind, b: &NodeKind) -> bool {
+    let value = |kind: &NodeKind| match kind {
+        NodeKind::Literal(text) | NodeKind::Insensitive(text) => Some(text.clone()),
+        NodeKind::Char(c) => Some(c.to_string()),
+        _ => None,
+    };
+    let (Some(x), Some(y)) = (value(a), value(b)) else {
+        return false;
+    };
+    match (a, b) {
+        (_, NodeKind::Insensitive(_)) => insensitive_prefix(&x, &y) == Some(x.len()),
+        // only text without case matches the same in any case
+        (NodeKind::Insensitive(_), _) => x == y && x.to_lowercase() == x.to_uppercase(),
+        _ => x == y,
+    }
+}
+
+/// The attributes that don't change what a rule accepts, every one once
+fn annotations(attributes: &[String]) -> BTreeSet<&str> {
+    attributes
+        .iter()
+        .map(String::as_str)
+        .filter(|a| *a != "start")
+        .collect()
+}
+
+/// Lines and columns within the sources of both grammars
+struct Positions<'a> {
+    left: (&'a str, LineIndex),
+    right: (&'a str, LineIndex),
+}
+
+impl<'a> Positions<'a> {
+    fn new(left: &'a str, right: &'a str) -> Self {
+        Self {
+            left: (left, LineIndex::new(left)),
+            right: (right, LineIndex::new(right)),
+        }
+    }
+
+    fn show((src, index): &(&str, LineIndex), span: Option<BSpan>) -> String {
+        span.map_or_else(String::new, |span| {
+            let (line, col) = index.line_col(span.from, src);
+            format!("{line}:{col}")
+        })
+    }
+
+    fn json((src, index): &(&str, LineIndex), span: Option<BSpan>) -> String {
+        span.map_or_else(
+            || "null".into(),
+            |span| {
+                let (line, col) = index.line_col(span.from, src);
+                format!(
+                    "{{\"from\": {}, \"to\": {}, \"line\": {line}, \"column\": {col}}}",
+                    span.from, span.to
+                )
+            },
+        )
+    }
+
+    fn left(&self, span: Option<BSpan>) -> String {
+        Self::show(&self.left, span)
+    }
+
+    fn right(&self, span: Option<BSpan>) -> String {
+        Self::show(&self.right, span)
+    }
+
+    fn left_json(&self, span: Option<BSpan>) -> String {
+        Self::json(&self.left, span)
+    }
+
+    fn right_json(&self, span: Option<BSpan>) -> String {
+        Self::json(&self.right, span)
+    }
+}
+
+/// Collects the changes between two rules of the same name
+struct Differ<'a> {
+    left: &'a Grammar,
+    right: &'a Grammar,
+    changes: Vec<Change>,
+}
+
+impl Differ<'_> {
+    fn push(&mut self, kind: ChangeKind, left: Option<BSpan>, right: Option<BSpan>) {
+        self.changes.push(Change { kind, left, right });
+    }
+
+    fn rule(&mut self, left: RuleId, right: RuleId) {
+        let (a, b) = (&self.left.rules[left], &self.right.rules[right]);
+        if a.kind != b.kind
+            || a.has_attribute("start") != b.has_attribute("start")
+            || a.params != b.params
+            || a.body.is_some() != b.body.is_some()
+        {
+            self.push(ChangeKind::Redefined, Some(a.span), Some(b.span));
+        }
+        if annotations(&a.attributes) != annotations(&b.attributes) {
+            self.push(ChangeKind::Annotated, Some(a.span), Some(b.span));
+        }
+        if a.kind == RuleKind::Precedence && b.kind == RuleKind::Precedence {
+            return self.levels(left, right);
+        }
+        if let (Some(a), Some(b)) = (&a.body, &b.body) {
+            self.node(a, b);
+        }
+    }
+
+    /// Compares the operands of two `@prec` rules, and their levels in
+    /// order, a level with the same associativity and operators being the
+    /// same
+    fn levels(&mut self, left: RuleId, right: RuleId) {
+        let (xs, a) = tower(self.left, left);
+        let (ys, b) = tower(self.right, right);
+        if let (Some(a), Some(b)) = (a, b) {
+            self.node(a, b);
+        }
+        let operators = |binary: &Node| match &binary.kind {
+            NodeKind::Seq(terms) => terms[1].clone(),
+            _ => binary.clone(),
+        };
+        let mut matched = Vec::new();
+        let mut from = 0;
+        for (i, (assoc, x)) in xs.iter().enumerate() {
+            let same = |(other, y): &(Assoc, &Node)| {
+                assoc == other && self.same(&operators(x), &operators(y))
+            };
+            if let Some(j) = ys[from..].iter().position(same) {
+                matched.push((i, from + j));
+                from += j + 1;
+            }
+        }
+        let removed: Vec<_> = (0..xs.len())
+            .filter(|i| !matched.iter().any(|m| m.0 == *i))
+            .map(|i| xs[i])
+            .collect();
+        let added: Vec<_> = (0..ys.len())
+            .filter(|j| !matched.iter().any(|m| m.1 == *j))
+            .map(|j| ys[j])
+            .collect();
+        let paired = removed.len().min(added.len());
+        for ((assoc, x), (other, y)) in removed.iter().zip(&added) {
+            let (x_ops, y_ops) = (operators(x), operators(y));
+            // a level moved to another place is the same as replaced
+            if assoc == other && !self.same(&x_ops, &y_ops) {
+                self.node(&x_ops, &y_ops);
+            } else {
+                self.push(ChangeKind::Replaced, Some(x.span), Some(y.span));
+            }
+        }
+        for (_, x) in &removed[paired..] {
+            self.push(ChangeKind::RemovedAlternative, Some(x.span), None);
+        }
+        for (_, y) in &added[paired..] {
+            self.push(ChangeKind::AddedAlternative, None, Some(y.span));
+        }
+    }
+
+    fn node(&mut self, a: &Node, b: &Node) {
+        if a.label != b.label {
+            self.push(ChangeKind::Relabeled, Some(a.span), Some(b.span));
+        }
+        if self.same_kind(&a.kind, &b.kind) {
+            return;
+        }
+        let from = self.changes.len();
+        self.structure(a, b);
+        let (Some(xs), Some(ys)) = (expand(a), expand(b)) else {
+            return;
+        };
+        // multiplied out, alternatives only on one side are added or
+        // removed even when the terms are written in other groups
+        let span = |terms: &Vec<&Node>| match &terms[..] {
+            [first, .., last] => Some(first.span.to(last.span.to)),
+            [only] => Some(only.span),
+            [] => None,
+        };
+        let removed: Vec<_> = xs
+            .iter()
+            .filter(|x| !ys.iter().any(|y| self.same_terms(x, y)))
+            .map(span)
+            .collect();
+        let added: Vec<_> = ys
+            .iter()
+            .filter(|y| !xs.iter().any(|x| self.same_terms(x, y)))
+            .map(span)
+            .collect();
+        let class = match (removed.is_empty(), added.is_empty()) {
+            (true, true) => Class::Equivalent,
+            (true, false) => Class::Widening,
+            (false, true) => Class::Narrowing,
+            (false, false) => return,
+        };
+        let changes = self.changes[from..].iter().map(|c| c.kind.class());
+        if changes.fold(Class::Equivalent, Class::and) == class {
+            return;
+        }
+        self.changes.truncate(from);
+        for x in removed {
+            self.push(ChangeKind::RemovedAlternative, x.or(Some(a.span)), None);
+        }
+        for y in added {
+            self.push(ChangeKind::AddedAlternative, None, y.or(Some(b.span)));
+        }
+    }
+
+    /// Compares two nodes that aren't the same as they are written
+    fn structure(&mut self, a: &Node, b: &Node) {
+        let alternatives = |n: &Node| -> Vec<Node> {
+            match &n.kind {
+                NodeKind::Alt(_, nodes) => nodes.clone(),
+                _ => vec![n.clone()],
+            }
+        };
+        let (xs, ys) = (alternatives(a), alternatives(b));
+        if xs.len() > 1 || ys.len() > 1 {
+            return self.alternatives(&xs, &ys);
+        }
+        match (&a.kind, &b.kind) {
+            (NodeKind::Seq(xs), NodeKind::Seq(ys)) if xs.len() == ys.len() => {
+                for (x, y) in xs.iter().zip(ys) {
+                    self.node(x, y);
+                }
+            }
+            (a_kind, b_kind) if within(a_kind, b_kind) => {
+                self.push(ChangeKind::Widened, Some(a.span), Some(b.span));
+            }
+            (a_kind, b_kind) if within(b_kind, a_kind) => {
+                self.push(ChangeKind::Narrowed, Some(a.span), Some(b.span));
+            }
+            _ => {
+                if !self.optional_terms(a, b) {
+                    self.push(ChangeKind::Replaced, Some(a.span), Some(b.span));
+                }
+            }
+        }
+    }
+
+    /// Pushes a change for every optional term or `,` added to or removed
+    /// from a sequence that is otherwise the same, returns whether it was
+    fn optional_terms(&mut self, left: &Node, right: &Node) -> bool {
+        let terms = |n: &Node| match &n.kind {
+            NodeKind::Seq(nodes) => nodes.clone(),
+            _ => vec![n.clone()],
+        };
+        let optional = |n: &Node| match &n.kind {
+            NodeKind::Alt(_, nodes) => nodes.iter().any(|n| n.kind == NodeKind::Nil),
+            _ => false,
+        };
+        let (xs, ys) = (terms(left), terms(right));
+        // a `,` is shown with the terms around it, and they without it
+        let around = |terms: &[Node], at: usize, to: usize| match (terms.get(at), terms.get(to)) {
+            (Some(from), Some(to)) => Some(from.span.to(to.span.to)),
+            _ => None,
+        };
+        let (mut x, mut y) = (0, 0);
+        let mut changes = Vec::new();
+        while x < xs.len() || y < ys.len() {
+            match (xs.get(x), ys.get(y)) {
+                (Some(a), Some(b)) if self.same(a, b) => (x, y) = (x + 1, y + 1),
+                (_, Some(b)) if b.kind == NodeKind::Adjacent && x > 0 => {
+                    let (a, b) = (around(&xs, x - 1, x), around(&ys, y - 1, y + 1));
+                    changes.push((ChangeKind::Narrowed, a, b));
+                    y += 1;
+                }
+                (Some(a), _) if a.kind == NodeKind::Adjacent && y > 0 => {
+                    let (a, b) = (around(&xs, x - 1, x + 1), around(&ys, y - 1, y));
+                    changes.push((ChangeKind::Widened, a, b));
+                    x += 1;
+                }
+                (_, Some(b)) if optional(b) => {
+                    changes.push((ChangeKind::AddedAlternative, None, Some(b.span)));
+                    y += 1;
+                }
+                (Some(a), _) if optional(a) => {
+                    changes.push((ChangeKind::RemovedAlternative, Some(a.span), None));
+                    x += 1;
+                }
+                _ => return false,
+            }
+        }
+        for (kind, left, right) in changes {
+            self.push(kind, left, right);
+        }
+        true
+    }
+
+    /// Matches up the alternatives that are the same, and compares the rest
+    /// in order
+    fn alternatives(&mut self, xs: &[Node], ys: &[Node]) {
+        let mut added: Vec<_> = ys.iter().collect();
+        let mut removed = Vec::new();
+        for x in xs {
+            match added.iter().position(|y| self.same(x, y)) {
+                Some(i) => _ = added.remove(i),
+                None => removed.push(x),
+            }
+        }
+        let paired = removed.len().min(added.len());
+        for (x, y) in removed.iter().zip(&added) {
+            self.node(x, y);
+        }
+        for x in &removed[paired..] {
+            self.push(ChangeKind::RemovedAlternative, Some(x.span), None);
+        }
+        for y in &added[paired..] {
+            self.push(ChangeKind::AddedAlternative, None, Some(y.span));
+        }
+    }
+
+    /// Whether two nodes are written the same, apart from their spans
+    fn same(&self, a: &Node, b: &Node) -> bool {
+        a.label == b.label && self.same_kind(&a.kind, &b.kind)
+    }
+
+    fn same_terms(&self, a: &[&Node], b: &[&Node]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.same(a, b))
+    }
+
+    fn same_kind(&self, a: &NodeKind, b: &NodeKind) -> bool {
+        let name = |grammar: &Grammar, kind: &NodeKind| match kind {
+            NodeKind::Rule(id) => Some(grammar.name(*id).to_owned()),
+            NodeKind::Unknown(name) => Some(name.clone()),
+            _ => None,
+        };
+        let all = |a: &[Node], b: &[Node]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.same(a, b))
+        };
+        match (a, b) {
+            (
+                NodeKind::Rule(_) | NodeKind::Unknown(_),
+                NodeKind::Rule(_) | NodeKind::Unknown(_),
+            ) => name(self.left, a) == name(self.right, b),
+            (NodeKind::Seq(a), NodeKind::Seq(b)) | (NodeKind::Alt(_, a), NodeKind::Alt(_, b)) => {
+                all(a, b)
+            }
+            (NodeKind::And(a), NodeKind::And(b)) | (NodeKind::Not(a), NodeKind::Not(b)) => {
+                self.same(a, b)
+            }
+            (a, b) => a == b || (within(a, b) && within(b, a)),
+        }
+    }
+}
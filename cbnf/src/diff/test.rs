@@ -0,0 +1,310 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    diff::{Class, Diff, RuleChange},
+    grammar::test::grammar,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(left: &str, right: &str, expect: Expect) {
+    let diff = Diff::new(&grammar(left), &grammar(right));
+    expect.assert_eq(&diff.text(left, right));
+}
+
+fn class(left: &str, right: &str) -> Option<Class> {
+    Diff::new(&grammar(left), &grammar(right)).class()
+}
+
+#[test]
+fn rules() {
+    let left = r#"
+a { b "x" }
+b { "y" | c }
+c { "z" }
+list<t> { t list<t> | nil }
+d { list<a> }
+"#;
+    let right = r#"
+b {
+    c
+    | "y"
+}
+a { b "x" "w" }
+list<t> { t list<t> | nil }
+d { list<a> }
+e { "v" }
+"#;
+    check(
+        left,
+        right,
+        expect![[r#"
+            ~ a (left 2:1, right 6:1): incompatible
+                ~ `{ b "x" }` became `{ b "x" "w" }` (left 2:3, right 6:3)
+            - c (left 4:1): narrowing
+            + e (right 9:1): widening
+            incompatible, some changes are breaking
+        "#]],
+    );
+    let diff = Diff::new(&grammar(left), &grammar(right));
+    let changes: Vec<_> = diff.rules.iter().map(|d| d.change).collect();
+    assert_eq!(
+        changes,
+        [RuleChange::Changed, RuleChange::Removed, RuleChange::Added]
+    );
+}
+
+#[test]
+fn alternatives() {
+    check(
+        r#"value { "true" | "false" } list { value ("," list | nil) }"#,
+        r#"value { "true" | "false" | "null" } list { value ("," list | ";" list | nil) }"#,
+        expect![[r#"
+            ~ value (left 1:1, right 1:1): widening
+                + alternative `"null"` (right 1:28)
+            ~ list (left 1:28, right 1:37): widening
+                + alternative `";" list` (right 1:62)
+            widening, every change is additive
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { "x" | "y" | "z" }"#, r#"a { "z" | "x" }"#),
+        Some(Class::Narrowing)
+    );
+    assert_eq!(
+        class(r#"a { "x" | "y" }"#, r#"a { "x" | "w" }"#),
+        Some(Class::Incompatible)
+    );
+    assert_eq!(class(r#"a { "x" | "y" }"#, r#"a { "y" | "x" }"#), None);
+    // a single term that becomes one of several
+    assert_eq!(
+        class(r#"a { "x" "y" }"#, r#"a { "x" ("y" | "z") }"#),
+        Some(Class::Widening)
+    );
+}
+
+#[test]
+fn optional_terms() {
+    check(
+        r#"call { ident "(" ")" } ident {}"#,
+        r#"call { ident ("::" ident | nil) "(" ")" ("?" | nil) } ident {}"#,
+        expect![[r#"
+            ~ call (left 1:1, right 1:1): widening
+                + alternative `("::" ident | nil)` (right 1:14)
+                + alternative `("?" | nil)` (right 1:41)
+            widening, every change is additive
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { "x" ("y" | nil) }"#, r#"a { "x" }"#),
+        Some(Class::Narrowing)
+    );
+}
+
+#[test]
+fn literals() {
+    // literals are compared by what they match
+    assert_eq!(class(r#"a { "x" }"#, r"a { 'x' }"), None);
+    assert_eq!(class(r#"a { "+" }"#, r#"a { i"+" }"#), None);
+    check(
+        r#"a { "x" | "y" }"#,
+        r#"a { i"x" | "y" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:1): widening
+                ~ `"x"` widened to `i"x"` (left 1:5, right 1:5)
+            widening, every change is additive
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { i"x" }"#, r#"a { "X" }"#),
+        Some(Class::Narrowing)
+    );
+    assert_eq!(
+        class(r#"a { "x" }"#, r#"a { i"y" }"#),
+        Some(Class::Incompatible)
+    );
+}
+
+#[test]
+fn adjacency() {
+    check(
+        r#"a { "x" "y" "z" }"#,
+        r#"a { "x" , "y" "z" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:1): narrowing
+                ~ `"x" "y"` narrowed to `"x" , "y"` (left 1:5, right 1:5)
+            narrowing, some changes are breaking
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { "x", "y" }"#, r#"a { "x" "y" }"#),
+        Some(Class::Widening)
+    );
+}
+
+#[test]
+fn multiplied_out() {
+    assert_eq!(
+        class(r#"a { "x" ("y" | "z") }"#, r#"a { "x" "y" | "x" "z" }"#),
+        None
+    );
+    assert_eq!(
+        class(r#"a { "x" ("y" | nil) }"#, r#"a { "x" | "x" "y" }"#),
+        None
+    );
+    check(
+        r#"a { "x" ("y" | "z") }"#,
+        r#"a { "x" "y" | "w" | "x" "z" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:1): widening
+                + alternative `"w"` (right 1:15)
+            widening, every change is additive
+        "#]],
+    );
+    // `"x" "y"` was there before, `"x"` is the alternative added
+    check(
+        r#"a { "x" "y" }"#,
+        r#"a { "x" | "x" "y" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:1): widening
+                + alternative `"x"` (right 1:5)
+            widening, every change is additive
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { "x" ("y" | "z") }"#, r#"a { "x" "y" }"#),
+        Some(Class::Narrowing)
+    );
+}
+
+#[test]
+fn precedence() {
+    // the levels are compared rather than the tower of rules made of them
+    check(
+        r#"@prec e { n | left "+" } n { "1" }"#,
+        r#"@prec e { n | left "+" | left "*" } n { "1" }"#,
+        expect![[r#"
+            ~ e (left 1:7, right 1:7): widening
+                + alternative `left "*"` (right 1:26)
+            widening, every change is additive
+        "#]],
+    );
+    check(
+        r#"@prec e { n | left "+" | left "*" } n { "1" }"#,
+        r#"@prec e { m | left "+" "-" | right "*" } n { "1" }"#,
+        expect![[r#"
+            ~ e (left 1:7, right 1:7): incompatible
+                ~ `n` became `m` (left 1:11, right 1:11)
+                + alternative `"-"` (right 1:24)
+                ~ `left "*"` became `right "*"` (left 1:26, right 1:30)
+            incompatible, some changes are breaking
+        "#]],
+    );
+    let src = r#"@prec e { n | left "+" | left "*" } n { "1" }"#;
+    assert_eq!(
+        class(
+            src,
+            r#"@prec e { n | left "+" | nonassoc "=" | left "*" } n { "1" }"#
+        ),
+        Some(Class::Widening)
+    );
+    assert_eq!(
+        class(src, r#"@prec e { n | left "+" } n { "1" }"#),
+        Some(Class::Narrowing)
+    );
+    // swapping two levels changes how their operators group
+    assert_eq!(
+        class(src, r#"@prec e { n | left "*" | left "+" } n { "1" }"#),
+        Some(Class::Incompatible)
+    );
+    assert_eq!(class(src, src), None);
+}
+
+#[test]
+fn incompatible() {
+    check(
+        r#"a { x:"x" } @lexical b { "y" } c { "z" | !"w" "v" } ident {}"#,
+        r#"a { y:"x" } b { "y" } c { "z" | !"u" "v" } ident { "i" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:1): incompatible
+                ~ label of `x:"x"` became `y:"x"` (left 1:5, right 1:5)
+            ~ b (left 1:22, right 1:13): incompatible
+                ~ kind, start, parameters or builtin (left 1:22, right 1:13)
+            ~ c (left 1:32, right 1:23): incompatible
+                ~ `!"w"` became `!"u"` (left 1:42, right 1:33)
+            ~ ident (left 1:53, right 1:44): incompatible
+                ~ kind, start, parameters or builtin (left 1:53, right 1:44)
+            incompatible, some changes are breaking
+        "#]],
+    );
+}
+
+#[test]
+fn attributes() {
+    check(
+        r#"a { b } b { "x" | "y" }"#,
+        r#"#[start] a { b } #[deprecated] b { "x" }"#,
+        expect![[r#"
+            ~ a (left 1:1, right 1:10): incompatible
+                ~ kind, start, parameters or builtin (left 1:1, right 1:10)
+            ~ b (left 1:9, right 1:32): narrowing
+                ~ attributes (left 1:9, right 1:32)
+                - alternative `"y"` (left 1:19)
+            incompatible, some changes are breaking
+        "#]],
+    );
+    assert_eq!(
+        class(r#"a { "x" }"#, r#"#[deprecated] #[inline] a { "x" }"#),
+        Some(Class::Equivalent)
+    );
+    assert_eq!(
+        class(
+            r#"a { "x" | "y" }"#,
+            r#"#[deprecated] a { "x" | "y" | "z" }"#
+        ),
+        Some(Class::Widening)
+    );
+}
+
+#[test]
+fn unchanged() {
+    let src = r#"a { b | "x" } b { "y" }"#;
+    check(
+        src,
+        "a { b\n  | \"x\" }\n\nb { \"y\" }",
+        expect![[r"
+            no changes
+        "]],
+    );
+}
+
+#[test]
+fn json() {
+    let (left, right) = (r#"a { "x" } b { "y" }"#, r#"a { "x" | "z" } c { "w" }"#);
+    let diff = Diff::new(&grammar(left), &grammar(right));
+    expect![[r#"
+        {
+          "left": "old.cbnf",
+          "right": "new.cbnf",
+          "class": "incompatible",
+          "rules": [
+            {"name": "a", "change": "changed", "class": "widening", "left": {"from": 0, "to": 1, "line": 1, "column": 1}, "right": {"from": 0, "to": 1, "line": 1, "column": 1}, "changes": [
+              {"kind": "added_alternative", "class": "widening", "left": null, "right": {"from": 10, "to": 13, "line": 1, "column": 11}}
+            ]},
+            {"name": "b", "change": "removed", "class": "narrowing", "left": {"from": 10, "to": 11, "line": 1, "column": 11}, "right": null, "changes": []},
+            {"name": "c", "change": "added", "class": "widening", "left": null, "right": {"from": 16, "to": 17, "line": 1, "column": 17}, "changes": []}
+          ]
+        }
+    "#]]
+    .assert_eq(&diff.json(("old.cbnf", left), ("new.cbnf", right)));
+    let none = Diff::new(&grammar(left), &grammar(left));
+    expect![[r#"
+        {
+          "left": "a.cbnf",
+          "right": "b.cbnf",
+          "class": null,
+          "rules": []
+        }
+    "#]]
+    .assert_eq(&none.json(("a.cbnf", left), ("b.cbnf", left)));
+}
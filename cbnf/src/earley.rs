@@ -0,0 +1,1061 @@
+//! a general parser for any context-free grammar
+//!
+//! [`Earley`] accepts every grammar [`crate::interp`] does, left recursive,
+//! cyclic and ambiguous ones included, but instead of a single tree it
+//! returns a [`Forest`] holding every derivation of the input. The forest
+//! is a shared packed parse forest: a node exists once for every part of
+//! the input it matches, and each of its derivations is a [`Family`] of
+//! child nodes. Long alternatives are split up into [`ForestKind::Partial`]
+//! nodes, which keeps the forest cubic in the size of the input.
+//!
+//! Trees are taken out of the forest with [`Forest::tree`], or
+//! [`Forest::tree_with`] to pick a family wherever there is more than one,
+//! and [`Forest::trees`] enumerates them.
+//!
+//! ```
+//! use cbnf::{earley::Earley, enumerate::Derivations, grammar::Grammar, Cbnf};
+//!
+//! let src = r#"e { e "+" e | "1" }"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! let forest = Earley::new(&grammar).parse(0, "1 + 1 + 1").unwrap();
+//! assert_eq!(forest.count(), Derivations::Finite(2));
+//! assert_eq!(forest.trees(10).len(), 2);
+//! ```
+
+use std::{cell::RefCell, collections::HashMap};
+
+use indexmap::IndexMap;
+
+use crate::{
+    enumerate::Derivations,
+    grammar::{insensitive_prefix, AltId, Grammar, Node, NodeKind, RuleId},
+    interp::{label, Builtins, Cst, CstKind, DefaultBuiltins, ParseError, Scan, Trivia},
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// An index into the nodes of a [`Forest`]
+pub type NodeId = usize;
+
+/// What a [`ForestNode`] matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForestKind {
+    Rule(RuleId),
+    /// A group such as `("a" | "b")` within a rule
+    Group(AltId),
+    Literal,
+    /// A rule with an empty body, matched by [`Builtins`]
+    Builtin(RuleId),
+    /// The first few terms of an alternative
+    Partial,
+    /// A lookahead predicate that held or a `,` with no whitespace after
+    /// it, it matches nothing
+    Predicate,
+    /// Whitespace and trivia skipped before a lexical rule
+    Skipped,
+}
+
+/// One way of deriving a [`ForestNode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Family {
+    /// The alternative taken, for rules and groups with alternatives
+    pub choice: Option<(AltId, usize)>,
+    /// At most two nodes, a [`ForestKind::Partial`] node matching all but
+    /// the last term of the alternative, and the last term
+    pub children: Vec<NodeId>,
+    /// The label of the last term, such as `lhs` in `lhs:expr`
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForestNode {
+    pub kind: ForestKind,
+    /// The matched text, literals and builtins don't include leading
+    /// whitespace
+    pub span: BSpan,
+    /// Every derivation of the node, earlier alternatives and longer
+    /// prefixes first, empty for literals, builtins and predicates
+    pub families: Vec<Family>,
+}
+
+/// Every derivation of an input, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forest {
+    nodes: Vec<ForestNode>,
+    root: NodeId,
+}
+
+type Part = (Vec<Cst>, Vec<(AltId, usize)>);
+
+impl Forest {
+    /// The node of the start rule
+    #[must_use]
+    pub const fn root(&self) -> NodeId {
+        self.root
+    }
+
+    #[must_use]
+    pub fn node(&self, id: NodeId) -> &ForestNode {
+        &self.nodes[id]
+    }
+
+    #[must_use]
+    pub fn nodes(&self) -> &[ForestNode] {
+        &self.nodes
+    }
+
+    /// The number of trees in the forest
+    ///
+    /// Cyclic grammars such as `a { a | "x" }` derive some inputs in
+    /// infinitely many ways.
+    #[must_use]
+    pub fn count(&self) -> Derivations {
+        fn count(forest: &Forest, id: NodeId, memo: &mut [Option<Option<u64>>]) -> Option<u64> {
+            if let Some(count) = memo[id] {
+                return count;
+            }
+            // anything reached again before it's counted lies on a cycle
+            memo[id] = Some(None);
+            let node = &forest.nodes[id];
+            let total = if node.families.is_empty() {
+                Some(1)
+            } else {
+                node.families.iter().try_fold(0u64, |total, family| {
+                    let trees = family.children.iter().try_fold(1u64, |trees, &child| {
+                        trees.checked_mul(count(forest, child, memo)?)
+                    })?;
+                    total.checked_add(trees)
+                })
+            };
+            memo[id] = Some(total);
+            total
+        }
+        let mut memo = vec![None; self.nodes.len()];
+        count(self, self.root, &mut memo).map_or(Derivations::Infinite, Derivations::Finite)
+    }
+
+    /// Whether the input has more than one derivation
+    #[must_use]
+    pub fn is_ambiguous(&self) -> bool {
+        self.count().is_ambiguous()
+    }
+
+    /// The nodes reachable from the root with more than one family
+    #[must_use]
+    pub fn ambiguities(&self) -> Vec<NodeId> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        let mut found = Vec::new();
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            let families = &self.nodes[id].families;
+            if families.len() > 1 {
+                found.push(id);
+            }
+            stack.extend(families.iter().flat_map(|f| &f.children));
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// The tree taking the first family of every node, which prefers
+    /// earlier alternatives and then longer matches of earlier terms
+    #[must_use]
+    pub fn tree(&self) -> Cst {
+        self.tree_with(|_, _| 0)
+    }
+
+    /// A single tree, `choose` picks the index of the family to take at
+    /// every node with more than one
+    ///
+    /// Where the chosen family would go around a cycle, the family with the
+    /// smallest derivation is taken instead, from there on down.
+    #[must_use]
+    pub fn tree_with(&self, choose: impl FnMut(&Self, NodeId) -> usize) -> Cst {
+        let mut extract = Extract {
+            forest: self,
+            heights: self.heights(),
+            choose,
+            path: Vec::new(),
+        };
+        let mut root = Cst {
+            kind: CstKind::Literal,
+            span: BSpan::default(),
+            children: Vec::new(),
+            choices: Vec::new(),
+            label: None,
+        };
+        // the root adds exactly one tree, a rule or a builtin
+        extract.node(self.root, false, &mut root);
+        root.children.pop().unwrap_or(root)
+    }
+
+    /// Up to `limit` trees of the forest, leaving out those that go around
+    /// a cycle
+    #[must_use]
+    pub fn trees(&self, limit: usize) -> Vec<Cst> {
+        let mut path = Vec::new();
+        self.parts(self.root, limit, &mut path)
+            .into_iter()
+            .filter_map(|(mut trees, _)| trees.pop())
+            .collect()
+    }
+
+    /// The tree of a node without families, `None` for a predicate
+    fn leaf(&self, id: NodeId) -> Option<Option<Cst>> {
+        let node = &self.nodes[id];
+        let kind = match node.kind {
+            ForestKind::Literal => CstKind::Literal,
+            ForestKind::Builtin(rule) => CstKind::Builtin(rule),
+            ForestKind::Predicate | ForestKind::Skipped => return Some(None),
+            _ => return None,
+        };
+        Some(Some(Cst {
+            kind,
+            span: node.span,
+            children: Vec::new(),
+            choices: Vec::new(),
+            label: None,
+        }))
+    }
+
+    /// The trees of a node, as the children and choices it adds to a rule
+    fn parts(&self, id: NodeId, limit: usize, path: &mut Vec<NodeId>) -> Vec<Part> {
+        if let Some(leaf) = self.leaf(id) {
+            return vec![(Vec::from_iter(leaf), Vec::new())];
+        }
+        if path.contains(&id) {
+            return Vec::new();
+        }
+        path.push(id);
+        let node = &self.nodes[id];
+        let mut parts = Vec::new();
+        'families: for family in &node.families {
+            let mut combined: Vec<Part> = vec![(Vec::new(), Vec::from_iter(family.choice))];
+            for (i, &child) in family.children.iter().enumerate() {
+                let mut tails = self.parts(child, limit, path);
+                if i + 1 == family.children.len() {
+                    for (tail, _) in &mut tails {
+                        label(tail, family.label.as_deref());
+                    }
+                }
+                combined = combined
+                    .iter()
+                    .flat_map(|(children, choices)| {
+                        tails.iter().map(move |(tail, tail_choices)| {
+                            let children = [children.as_slice(), tail].concat();
+                            (children, [choices.as_slice(), tail_choices].concat())
+                        })
+                    })
+                    .take(limit)
+                    .collect();
+            }
+            for (children, choices) in combined {
+                let part = match node.kind {
+                    ForestKind::Rule(rule) => {
+                        let span = rule_span(&children, node.span);
+                        let cst = Cst {
+                            kind: CstKind::Rule(rule),
+                            span,
+                            children,
+                            choices,
+                            label: None,
+                        };
+                        (vec![cst], Vec::new())
+                    }
+                    _ => (children, choices),
+                };
+                parts.push(part);
+                if parts.len() >= limit {
+                    break 'families;
+                }
+            }
+        }
+        path.pop();
+        parts
+    }
+
+    /// The depth of the shallowest tree of every node
+    fn heights(&self) -> Vec<Option<usize>> {
+        let mut heights = vec![None; self.nodes.len()];
+        loop {
+            let mut changed = false;
+            for (id, node) in self.nodes.iter().enumerate() {
+                let height = if node.families.is_empty() {
+                    Some(0)
+                } else {
+                    node.families
+                        .iter()
+                        .filter_map(|f| family_height(f, &heights))
+                        .min()
+                };
+                if height != heights[id] {
+                    heights[id] = height;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return heights;
+            }
+        }
+    }
+}
+
+fn family_height(family: &Family, heights: &[Option<usize>]) -> Option<usize> {
+    let deepest = family
+        .children
+        .iter()
+        .try_fold(0, |deepest, &c| heights[c].map(|h| h.max(deepest)))?;
+    Some(deepest + 1)
+}
+
+/// The span of a rule without leading whitespace, like [`crate::interp`]
+fn rule_span(children: &[Cst], span: BSpan) -> BSpan {
+    let from = children.first().map_or(span.to, |c| c.span.from);
+    span.from(from.min(span.to))
+}
+
+/// Takes a single tree out of a [`Forest`]
+struct Extract<'f, F> {
+    forest: &'f Forest,
+    heights: Vec<Option<usize>>,
+    choose: F,
+    /// The nodes being extracted
+    path: Vec<NodeId>,
+}
+
+impl<F: FnMut(&Forest, NodeId) -> usize> Extract<'_, F> {
+    /// The family to take at `id`, and whether to only take the shallowest
+    /// families below it
+    fn family(&mut self, id: NodeId, shallowest: bool) -> (usize, bool) {
+        let families = &self.forest.nodes[id].families;
+        let heights = &self.heights;
+        if !shallowest {
+            let pick = match families.len() {
+                1 => 0,
+                len => (self.choose)(self.forest, id).min(len - 1),
+            };
+            let cyclic = families[pick]
+                .children
+                .iter()
+                .any(|c| self.path.contains(c) || heights[*c].is_none());
+            if !cyclic {
+                return (pick, false);
+            }
+        }
+        // the children of the shallowest family are shallower than `id`,
+        // so this always ends
+        let shallowest = (0..families.len())
+            .filter(|&i| family_height(&families[i], heights).is_some())
+            .min_by_key(|&i| family_height(&families[i], heights))
+            .expect("every node of a forest has a finite tree");
+        (shallowest, true)
+    }
+
+    fn node(&mut self, id: NodeId, shallowest: bool, parent: &mut Cst) {
+        if let Some(leaf) = self.forest.leaf(id) {
+            parent.children.extend(leaf);
+            return;
+        }
+        let forest = self.forest;
+        let node = &forest.nodes[id];
+        self.path.push(id);
+        let (pick, shallowest) = self.family(id, shallowest);
+        let family = &node.families[pick];
+        if let ForestKind::Rule(rule) = node.kind {
+            let mut cst = Cst {
+                kind: CstKind::Rule(rule),
+                span: node.span,
+                children: Vec::new(),
+                choices: Vec::from_iter(family.choice),
+                label: None,
+            };
+            self.children(family, shallowest, &mut cst);
+            cst.span = rule_span(&cst.children, node.span);
+            parent.children.push(cst);
+        } else {
+            parent.choices.extend(family.choice);
+            self.children(family, shallowest, parent);
+        }
+        self.path.pop();
+    }
+
+    fn children(&mut self, family: &Family, shallowest: bool, parent: &mut Cst) {
+        let Some((&last, rest)) = family.children.split_last() else {
+            return;
+        };
+        for &child in rest {
+            self.node(child, shallowest, parent);
+        }
+        let mark = parent.children.len();
+        self.node(last, shallowest, parent);
+        label(&mut parent.children[mark..], family.label.as_deref());
+    }
+}
+
+type ProdId = usize;
+
+/// A terminal or nonterminal of a [`Production`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Symbol {
+    Literal(String),
+    /// A case-insensitive literal, see [`NodeKind::Insensitive`]
+    Insensitive(String),
+    Builtin(RuleId),
+    /// A rule, a group, or the start of a parse, see [`Earley::nonterminal`]
+    Nonterminal(usize),
+    /// An ident that doesn't refer to any rule, it never matches
+    Unknown,
+    /// Whether the productions of `nt` match, or don't if `negative`
+    Predicate {
+        nt: usize,
+        negative: bool,
+    },
+    /// No whitespace follows
+    Adjacent,
+    /// Whitespace and trivia before a lexical rule
+    Skip,
+}
+
+/// A single alternative of a rule or group, flattened into symbols
+#[derive(Debug, Clone)]
+struct Production {
+    lhs: usize,
+    rhs: Vec<Symbol>,
+    /// The label of every symbol of `rhs`
+    labels: Vec<Option<String>>,
+    choice: Option<(AltId, usize)>,
+    /// Whether the production is part of a lexical rule, so nothing is
+    /// skipped before its symbols
+    lexical: bool,
+}
+
+/// Flattens the lowered rules into productions
+struct Compile<'g> {
+    grammar: &'g Grammar,
+    productions: Vec<Production>,
+    /// The next nonterminal for the body of a predicate
+    next: usize,
+    /// Whether the rule being compiled is lexical
+    lexical: bool,
+}
+
+impl Compile<'_> {
+    fn rule(&mut self, id: RuleId, body: &Node) {
+        self.lexical = self.grammar.rules[id].kind.is_lexical();
+        if let NodeKind::Alt(alt, nodes) = &body.kind {
+            self.alternatives(id, *alt, nodes);
+        } else {
+            let (rhs, labels) = self.symbols(body);
+            self.productions.push(Production {
+                lhs: id,
+                rhs,
+                labels,
+                choice: None,
+                lexical: self.lexical,
+            });
+        }
+    }
+
+    fn alternatives(&mut self, lhs: usize, alt: AltId, nodes: &[Node]) {
+        for (i, node) in nodes.iter().enumerate() {
+            let (rhs, labels) = self.symbols(node);
+            self.productions.push(Production {
+                lhs,
+                rhs,
+                labels,
+                choice: Some((alt, i)),
+                lexical: self.lexical,
+            });
+        }
+    }
+
+    /// The symbols of `node`, along with their labels
+    fn symbols(&mut self, node: &Node) -> (Vec<Symbol>, Vec<Option<String>>) {
+        let mut symbols = Vec::new();
+        self.push(node, None, &mut symbols);
+        symbols.into_iter().unzip()
+    }
+
+    /// Pushes the symbols of `node`, `label` being that of the innermost
+    /// labeled node around it
+    fn push(
+        &mut self,
+        node: &Node,
+        label: Option<&str>,
+        symbols: &mut Vec<(Symbol, Option<String>)>,
+    ) {
+        let label = node.label.as_deref().or(label);
+        let labeled = |symbol| (symbol, label.map(str::to_owned));
+        match &node.kind {
+            NodeKind::Nil => {}
+            NodeKind::Literal(lit) if lit.is_empty() => {}
+            NodeKind::Literal(lit) => symbols.push(labeled(Symbol::Literal(lit.clone()))),
+            NodeKind::Char(c) => symbols.push(labeled(Symbol::Literal(c.to_string()))),
+            NodeKind::Insensitive(lit) if lit.is_empty() => {}
+            NodeKind::Insensitive(lit) => {
+                symbols.push(labeled(Symbol::Insensitive(lit.clone())));
+            }
+            NodeKind::Rule(id) => {
+                if self.grammar.rules[*id].body.is_none() {
+                    symbols.push(labeled(Symbol::Builtin(*id)));
+                    return;
+                }
+                // lexical rules don't skip anything themselves
+                if !self.lexical && !self.grammar.is_syntactic(*id) {
+                    symbols.push(labeled(Symbol::Skip));
+                }
+                symbols.push(labeled(Symbol::Nonterminal(*id)));
+            }
+            NodeKind::Unknown(_) => symbols.push(labeled(Symbol::Unknown)),
+            NodeKind::Adjacent => symbols.push(labeled(Symbol::Adjacent)),
+            NodeKind::And(predicate) | NodeKind::Not(predicate) => {
+                let nt = self.next;
+                self.next += 1;
+                let (rhs, labels) = self.symbols(predicate);
+                self.productions.push(Production {
+                    lhs: nt,
+                    rhs,
+                    labels,
+                    choice: None,
+                    lexical: self.lexical,
+                });
+                let negative = matches!(node.kind, NodeKind::Not(_));
+                symbols.push(labeled(Symbol::Predicate { nt, negative }));
+            }
+            NodeKind::Seq(nodes) => {
+                for node in nodes {
+                    self.push(node, label, symbols);
+                }
+            }
+            NodeKind::Alt(alt, nodes) => {
+                let group = self.grammar.rules.len() + alt;
+                self.alternatives(group, *alt, nodes);
+                symbols.push(labeled(Symbol::Nonterminal(group)));
+            }
+        }
+    }
+}
+
+/// Parses input with Earley's algorithm, see the [module docs](self)
+pub struct Earley<'g> {
+    grammar: &'g Grammar,
+    builtins: Box<dyn Builtins + 'g>,
+    skip_whitespace: bool,
+    productions: Vec<Production>,
+    /// The productions of every nonterminal
+    by_lhs: Vec<Vec<ProdId>>,
+}
+
+impl<'g> Earley<'g> {
+    /// A parser using [`DefaultBuiltins`] that skips whitespace
+    #[must_use]
+    pub fn new(grammar: &'g Grammar) -> Self {
+        let starts = grammar.rules.len() + grammar.alts.len();
+        let mut compile = Compile {
+            grammar,
+            productions: Vec::new(),
+            next: starts + grammar.rules.len(),
+            lexical: false,
+        };
+        for (id, rule) in grammar.rules.iter().enumerate() {
+            if let Some(body) = &rule.body {
+                compile.rule(id, body);
+            }
+        }
+        let mut productions = compile.productions;
+        // every rule can start a parse
+        for (id, rule) in grammar.rules.iter().enumerate() {
+            let symbol = match rule.body {
+                Some(_) => Symbol::Nonterminal(id),
+                None => Symbol::Builtin(id),
+            };
+            productions.push(Production {
+                lhs: starts + id,
+                rhs: vec![symbol],
+                labels: vec![None],
+                choice: None,
+                lexical: false,
+            });
+        }
+        let mut by_lhs = vec![Vec::new(); compile.next];
+        for (id, production) in productions.iter().enumerate() {
+            by_lhs[production.lhs].push(id);
+        }
+        Self {
+            grammar,
+            builtins: Box::new(DefaultBuiltins),
+            skip_whitespace: true,
+            productions,
+            by_lhs,
+        }
+    }
+
+    #[must_use]
+    pub fn builtins(mut self, builtins: impl Builtins + 'g) -> Self {
+        self.builtins = Box::new(builtins);
+        self
+    }
+
+    /// Whether whitespace is skipped before literals and at the end of input
+    #[must_use]
+    pub const fn skip_whitespace(mut self, skip: bool) -> Self {
+        self.skip_whitespace = skip;
+        self
+    }
+
+    #[must_use]
+    pub const fn grammar(&self) -> &'g Grammar {
+        self.grammar
+    }
+
+    fn trivia<'a>(&'a self, input: &'a str) -> Trivia<'a> {
+        Trivia::new(self.grammar, &*self.builtins, self.skip_whitespace, input)
+    }
+
+    /// The nonterminal starting a parse of `rule`
+    const fn nonterminal(&self, rule: RuleId) -> usize {
+        self.grammar.rules.len() + self.grammar.alts.len() + rule
+    }
+
+    /// Whether all of `input` matches `start`
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    #[must_use]
+    pub fn recognize(&self, start: RuleId, input: &str) -> bool {
+        let start = self.by_lhs[self.nonterminal(start)][0];
+        let trivia = self.trivia(input);
+        let lookahead = RefCell::new(HashMap::new());
+        let mut chart = Chart::new(self, &trivia, &lookahead, start, 0, input);
+        chart.fill();
+        let accepted = chart.ends().next().is_some();
+        accepted
+    }
+
+    /// Parses all of `input` as `start`
+    ///
+    /// When whitespace is skipped, trailing whitespace may be matched in
+    /// more than one way, the forest holds the longest match.
+    ///
+    /// # Errors
+    /// When `input` doesn't match `start`.
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    pub fn parse(&self, start: RuleId, input: &str) -> Result<Forest, ParseError> {
+        let start = self.by_lhs[self.nonterminal(start)][0];
+        let trivia = self.trivia(input);
+        let lookahead = RefCell::new(HashMap::new());
+        let mut chart = Chart::new(self, &trivia, &lookahead, start, 0, input);
+        chart.fill();
+        let Some(end) = chart.ends().last() else {
+            return Err(chart.error());
+        };
+        let production = &self.productions[start];
+        let mut build = Build {
+            chart: &chart,
+            memo: HashMap::new(),
+            nodes: Vec::new(),
+        };
+        let root = build.symbol(&production.rhs[0], false, 0, end);
+        Ok(Forest {
+            nodes: build.nodes,
+            root,
+        })
+    }
+}
+
+/// A production with a position in its symbols, started at `origin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    production: ProdId,
+    dot: usize,
+    origin: usize,
+}
+
+/// The Earley sets of a single parse
+struct Chart<'a, 'g> {
+    earley: &'a Earley<'g>,
+    trivia: &'a Trivia<'a>,
+    input: &'a str,
+    /// The production being parsed, the start of a rule or the body of a
+    /// predicate
+    start: ProdId,
+    origin: usize,
+    /// The items ending at every position of the input, with every
+    /// position the symbol before the dot started at
+    sets: Vec<IndexMap<Item, Vec<usize>>>,
+    /// Whether the body of a predicate matches at a position, shared by
+    /// the charts of all predicates of a parse, `None` while it is being
+    /// worked out or when it depends on itself
+    lookahead: &'a RefCell<HashMap<(usize, usize), Option<bool>>>,
+    /// Whether a predicate of this chart depends on one being worked out
+    cyclic: bool,
+    furthest: usize,
+    expected: Vec<String>,
+}
+
+impl<'a, 'g> Chart<'a, 'g> {
+    fn new(
+        earley: &'a Earley<'g>,
+        trivia: &'a Trivia<'a>,
+        lookahead: &'a RefCell<HashMap<(usize, usize), Option<bool>>>,
+        start: ProdId,
+        origin: usize,
+        input: &'a str,
+    ) -> Self {
+        let mut sets = vec![IndexMap::new(); input.len() + 1];
+        let item = Item {
+            production: start,
+            dot: 0,
+            origin,
+        };
+        sets[origin].insert(item, Vec::new());
+        Self {
+            earley,
+            trivia,
+            input,
+            start,
+            origin,
+            sets,
+            lookahead,
+            cyclic: false,
+            furthest: origin,
+            expected: Vec::new(),
+        }
+    }
+
+    /// Whether the body of the predicate `nt` matches at `pos`, `None` when
+    /// working that out depends on itself, as in `a { !a "x" }`
+    fn lookahead(&mut self, nt: usize, pos: usize) -> Option<bool> {
+        let memo = self.lookahead.borrow().get(&(nt, pos)).copied();
+        if let Some(matched) = memo {
+            self.cyclic |= matched.is_none();
+            return matched;
+        }
+        self.lookahead.borrow_mut().insert((nt, pos), None);
+        let start = self.earley.by_lhs[nt][0];
+        let mut chart = Self::new(
+            self.earley,
+            self.trivia,
+            self.lookahead,
+            start,
+            pos,
+            self.input,
+        );
+        chart.fill();
+        let accepted = chart.accepted();
+        let matched =
+            (!chart.cyclic).then(|| chart.sets.iter().any(|set| set.contains_key(&accepted)));
+        self.lookahead.borrow_mut().insert((nt, pos), matched);
+        self.cyclic |= matched.is_none();
+        matched
+    }
+
+    fn skip(&self, pos: usize) -> usize {
+        self.trivia.skip(pos)
+    }
+
+    /// `pos` after anything skipped before the symbols of `production`
+    fn skip_in(&self, production: &Production, pos: usize) -> usize {
+        if production.lexical {
+            pos
+        } else {
+            self.skip(pos)
+        }
+    }
+
+    /// Records that something was expected but not found at `pos`
+    fn fail(&mut self, pos: usize, expected: impl FnOnce() -> String) {
+        if pos > self.furthest {
+            self.furthest = pos;
+            self.expected.clear();
+        }
+        if pos == self.furthest {
+            self.expected.push(expected());
+        }
+    }
+
+    fn error(mut self) -> ParseError {
+        for end in 0..self.sets.len() {
+            if self.sets[end].contains_key(&self.accepted()) {
+                let skipped = self.skip(end);
+                self.fail(skipped, || "end of input".into());
+            }
+        }
+        self.expected.sort();
+        self.expected.dedup();
+        ParseError {
+            pos: self.furthest,
+            found: self.input[self.furthest..].chars().next(),
+            expected: self.expected,
+        }
+    }
+
+    fn accepted(&self) -> Item {
+        Item {
+            production: self.start,
+            dot: self.earley.productions[self.start].rhs.len(),
+            origin: self.origin,
+        }
+    }
+
+    /// The positions a parse of all of the input can end at
+    fn ends(&self) -> impl Iterator<Item = usize> + '_ {
+        let accepted = self.accepted();
+        (self.origin..self.sets.len())
+            .filter(move |&end| self.sets[end].contains_key(&accepted))
+            .filter(|&end| self.skip(end) == self.input.len())
+    }
+
+    fn add(&mut self, set: usize, item: Item, from: Option<usize>) {
+        let links = self.sets[set].entry(item).or_default();
+        if let Some(from) = from {
+            if !links.contains(&from) {
+                links.push(from);
+            }
+        }
+    }
+
+    fn fill(&mut self) {
+        for pos in self.origin..self.sets.len() {
+            self.set(pos);
+        }
+    }
+
+    /// Predicts, scans and completes the items of the set at `pos`
+    fn set(&mut self, pos: usize) {
+        let earley = self.earley;
+        // nonterminals that matched nothing at `pos`
+        let mut nullable = Vec::new();
+        let mut i = 0;
+        while let Some((&item, _)) = self.sets[pos].get_index(i) {
+            i += 1;
+            let production = &earley.productions[item.production];
+            let next = Item {
+                dot: item.dot + 1,
+                ..item
+            };
+            match production.rhs.get(item.dot) {
+                None => {
+                    let nt = production.lhs;
+                    if item.origin == pos {
+                        nullable.push(nt);
+                    }
+                    let waiting: Vec<Item> = self.sets[item.origin]
+                        .keys()
+                        .filter(|w| {
+                            let rhs = &earley.productions[w.production].rhs;
+                            rhs.get(w.dot) == Some(&Symbol::Nonterminal(nt))
+                        })
+                        .copied()
+                        .collect();
+                    for waiting in waiting {
+                        let next = Item {
+                            dot: waiting.dot + 1,
+                            ..waiting
+                        };
+                        self.add(pos, next, Some(item.origin));
+                    }
+                }
+                Some(Symbol::Nonterminal(nt)) => {
+                    for &production in &earley.by_lhs[*nt] {
+                        let predicted = Item {
+                            production,
+                            dot: 0,
+                            origin: pos,
+                        };
+                        self.add(pos, predicted, None);
+                    }
+                    if nullable.contains(nt) {
+                        self.add(pos, next, Some(pos));
+                    }
+                }
+                Some(Symbol::Literal(lit)) => {
+                    let at = self.skip_in(production, pos);
+                    if self.input[at..].starts_with(lit.as_str()) {
+                        self.add(at + lit.len(), next, Some(pos));
+                    } else {
+                        self.fail(at, || format!("{lit:?}"));
+                    }
+                }
+                Some(Symbol::Insensitive(lit)) => {
+                    let at = self.skip_in(production, pos);
+                    if let Some(len) = insensitive_prefix(&self.input[at..], lit) {
+                        self.add(at + len, next, Some(pos));
+                    } else {
+                        self.fail(at, || format!("i{lit:?}"));
+                    }
+                }
+                Some(Symbol::Builtin(rule)) => {
+                    let name = earley.grammar.name(*rule);
+                    let at = Scan {
+                        input: self.input,
+                        pos,
+                        skipped: self.skip_in(production, pos),
+                    };
+                    let ends = earley.builtins.scan(name, at);
+                    if ends.is_empty() {
+                        self.fail(at.skipped, || name.to_owned());
+                    }
+                    for end in ends {
+                        self.add(end, next, Some(pos));
+                    }
+                }
+                Some(Symbol::Predicate { nt, negative }) => {
+                    // a predicate depending on itself holds neither way
+                    if self.lookahead(*nt, pos).is_some_and(|m| m != *negative) {
+                        self.add(pos, next, Some(pos));
+                    }
+                }
+                Some(Symbol::Skip) => {
+                    let end = self.skip(pos);
+                    self.add(end, next, Some(pos));
+                }
+                Some(Symbol::Adjacent) => {
+                    if self.skip_in(production, pos) == pos {
+                        self.add(pos, next, Some(pos));
+                    } else {
+                        self.fail(pos, || "no whitespace".into());
+                    }
+                }
+                Some(Symbol::Unknown) => {}
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Key<'a> {
+    /// A symbol, whether it's within a lexical rule and what it matched
+    Symbol(&'a Symbol, bool, usize, usize),
+    Partial(ProdId, usize, usize, usize),
+}
+
+/// Builds a [`Forest`] out of a filled [`Chart`]
+struct Build<'c, 'a, 'g> {
+    chart: &'c Chart<'a, 'g>,
+    memo: HashMap<Key<'c>, NodeId>,
+    nodes: Vec<ForestNode>,
+}
+
+impl<'c> Build<'c, '_, '_> {
+    /// A new node, or the existing one for `key`
+    fn node(&mut self, key: Key<'c>, kind: ForestKind, span: BSpan) -> Result<NodeId, NodeId> {
+        if let Some(&id) = self.memo.get(&key) {
+            return Err(id);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(ForestNode {
+            kind,
+            span,
+            families: Vec::new(),
+        });
+        self.memo.insert(key, id);
+        Ok(id)
+    }
+
+    /// The node of `symbol` matching `from..to`
+    ///
+    /// `lexical` is whether the symbol is within a lexical rule.
+    fn symbol(&mut self, symbol: &'c Symbol, lexical: bool, from: usize, to: usize) -> NodeId {
+        let key = Key::Symbol(symbol, lexical, from, to);
+        let start = if lexical { from } else { self.chart.skip(from) };
+        let grammar = self.chart.earley.grammar;
+        let (kind, start) = match symbol {
+            Symbol::Literal(_) | Symbol::Insensitive(_) => (ForestKind::Literal, start),
+            Symbol::Builtin(rule) => (ForestKind::Builtin(*rule), start.min(to)),
+            Symbol::Nonterminal(nt) if *nt < grammar.rules.len() => (ForestKind::Rule(*nt), from),
+            Symbol::Nonterminal(nt) => (ForestKind::Group(nt - grammar.rules.len()), from),
+            Symbol::Predicate { .. } | Symbol::Adjacent => (ForestKind::Predicate, from),
+            Symbol::Skip => (ForestKind::Skipped, from),
+            Symbol::Unknown => unreachable!("unknown idents never match"),
+        };
+        let id = match self.node(key, kind, span(start, to)) {
+            Ok(id) => id,
+            Err(id) => return id,
+        };
+        let Symbol::Nonterminal(nt) = symbol else {
+            return id;
+        };
+        let productions = &self.chart.earley.productions;
+        let mut families = Vec::new();
+        for (item, links) in &self.chart.sets[to] {
+            let production = &productions[item.production];
+            if production.lhs != *nt || item.origin != from || item.dot != production.rhs.len() {
+                continue;
+            }
+            if item.dot == 0 {
+                families.push((item.production, 0, Vec::new()));
+            }
+            for &pivot in links {
+                let children = self.split(item.production, item.dot, from, pivot, to);
+                families.push((item.production, pivot, children));
+            }
+        }
+        families.sort_by_key(|&(production, pivot, _)| (production, std::cmp::Reverse(pivot)));
+        self.nodes[id].families = families
+            .into_iter()
+            .map(|(production, _, children)| Family {
+                choice: productions[production].choice,
+                label: productions[production].labels.last().cloned().flatten(),
+                children,
+            })
+            .collect();
+        id
+    }
+
+    /// The first `dot` symbols of a production matching `from..to`
+    fn partial(&mut self, production: ProdId, dot: usize, from: usize, to: usize) -> NodeId {
+        let key = Key::Partial(production, dot, from, to);
+        let id = match self.node(key, ForestKind::Partial, span(from, to)) {
+            Ok(id) => id,
+            Err(id) => return id,
+        };
+        let item = Item {
+            production,
+            dot,
+            origin: from,
+        };
+        let mut pivots = self.chart.sets[to][&item].clone();
+        pivots.sort_unstable_by(|a, b| b.cmp(a));
+        self.nodes[id].families = pivots
+            .into_iter()
+            .map(|pivot| Family {
+                choice: None,
+                children: self.split(production, dot, from, pivot, to),
+                label: self.chart.earley.productions[production].labels[dot - 1].clone(),
+            })
+            .collect();
+        id
+    }
+
+    /// The children of the first `dot` symbols of a production matching
+    /// `from..to`, where the last symbol starts at `pivot`
+    fn split(
+        &mut self,
+        production: ProdId,
+        dot: usize,
+        from: usize,
+        pivot: usize,
+        to: usize,
+    ) -> Vec<NodeId> {
+        let Production { rhs, lexical, .. } = &self.chart.earley.productions[production];
+        let last = self.symbol(&rhs[dot - 1], *lexical, pivot, to);
+        if dot == 1 {
+            vec![last]
+        } else {
+            vec![self.partial(production, dot - 1, from, pivot), last]
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn span(from: usize, to: usize) -> BSpan {
+    BSpan::new(from as u32, to as u32)
+}
@@ -0,0 +1,278 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    earley::{Earley, ForestKind},
+    enumerate::Derivations,
+    grammar::{test::grammar, Grammar},
+    interp::Interpreter,
+    Cbnf,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, input: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let actual = match Earley::new(&grammar).parse(0, input) {
+        Ok(forest) => forest.tree().snapshot(&grammar, input),
+        Err(e) => e.to_string(),
+    };
+    expect.assert_eq(&actual);
+}
+
+/// Both parsers agree on unambiguous input
+fn agrees(src: &str, inputs: &[&str]) {
+    let grammar = grammar(src);
+    let earley = Earley::new(&grammar);
+    let interp = Interpreter::new(&grammar);
+    for input in inputs {
+        let expected = interp.parse(0, input);
+        let actual = earley.parse(0, input).map(|forest| forest.tree());
+        assert_eq!(actual, expected, "parsing {input:?}");
+        assert_eq!(earley.recognize(0, input), expected.is_ok());
+    }
+}
+
+#[test]
+fn sequence() {
+    check(
+        "pair { '(' ident ',' ident ')' } ident {}",
+        "( a , b )",
+        expect![[r#"
+            (pair
+              "("
+              (ident "a")
+              ","
+              (ident "b")
+              ")")"#]],
+    );
+}
+
+#[test]
+fn like_the_interpreter() {
+    agrees(
+        r#"a { "x" | "y" b } b { "z" | nil }"#,
+        &["x", "y", "yz", "xz", "", " y  z "],
+    );
+    agrees(
+        r#"expr { expr '+' num | num } num { "1" | "2" }"#,
+        &["1", "1 + 2 + 1", "1 +", "+ 1"],
+    );
+    agrees(
+        r#"list { "[" (items | nil) "]" } items { ident ("," items | nil) } ident {}"#,
+        &["[]", "[a]", "[a, b, c]", "[a,]", "[a b]"],
+    );
+    agrees(r#"a { "x" b } b { EOF } EOF {}"#, &["x", "x  ", "xy"]);
+    agrees(r#"a { nope "x" }"#, &["x"]);
+    agrees(
+        r#"path { ident , "." , ident } ident {}"#,
+        &["a.b", " a.b ", "a .b", "a. b"],
+    );
+}
+
+#[test]
+fn lexical_and_trivia() {
+    agrees(
+        r#"s { num | s "+" num } @lexical num { "1" "2" | "3" } @trivia c { "/" "/" ident } ident {}"#,
+        &[
+            "12",
+            "12 + 3",
+            "1 2",
+            "12+//x 3",
+            "12 //x//y + 12",
+            "12 + 1//x 2",
+        ],
+    );
+    // a syntactic rule within the trivia, an error that is still lowered,
+    // skips nothing
+    let src = r##"r { "a" "b" } @trivia ws { s } s { "#" "x" }"##;
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    let earley = Earley::new(&grammar);
+    assert!(earley.recognize(0, "a #x b"));
+    assert!(!earley.recognize(0, "a # x b"));
+}
+
+#[test]
+fn errors() {
+    check(
+        r#"a { "x" ("y" | "z") }"#,
+        "x w",
+        expect![[r#"unexpected 'w' at byte 2, expected one of: "y", "z""#]],
+    );
+    check(
+        r#"a { "x" }"#,
+        "x x",
+        expect![[r"unexpected 'x' at byte 2, expected one of: end of input"]],
+    );
+}
+
+#[test]
+fn nullable() {
+    let src = r#"a { b b "x" } b { nil | "y" }"#;
+    agrees(src, &["x", "y x", "y y x", "y y y x"]);
+    let grammar = grammar(src);
+    // `y x` has the `y` in either `b`
+    let forest = Earley::new(&grammar).parse(0, "y x").unwrap();
+    assert_eq!(forest.count(), Derivations::Finite(2));
+}
+
+#[test]
+fn hidden_left_recursion() {
+    agrees(r#"a { b a "x" | "y" } b { nil }"#, &["y", "y x x", "x"]);
+}
+
+#[test]
+fn ambiguous() {
+    let src = r#"e { e "+" e | "1" }"#;
+    let grammar = grammar(src);
+    let input = "1+1+1";
+    let forest = Earley::new(&grammar).parse(0, input).unwrap();
+    assert_eq!(forest.count(), Derivations::Finite(2));
+    assert!(forest.is_ambiguous());
+
+    let trees: Vec<_> = forest
+        .trees(10)
+        .iter()
+        .map(|tree| tree.snapshot(&grammar, input))
+        .collect();
+    expect![[r#"
+        (e
+          (e
+            (e
+              "1")
+            "+"
+            (e
+              "1"))
+          "+"
+          (e
+            "1"))
+        ---
+        (e
+          (e
+            "1")
+          "+"
+          (e
+            (e
+              "1")
+            "+"
+            (e
+              "1")))"#]]
+    .assert_eq(&trees.join("\n---\n"));
+    assert_eq!(forest.trees(1).len(), 1);
+
+    // the default tree takes the longest prefix, so is left associative
+    assert_eq!(forest.tree().snapshot(&grammar, input), trees[0]);
+    let last = forest.tree_with(|forest, id| forest.node(id).families.len() - 1);
+    assert_eq!(last.snapshot(&grammar, input), trees[1]);
+
+    let ambiguities = forest.ambiguities();
+    assert_eq!(ambiguities.len(), 1);
+    assert_eq!(forest.node(ambiguities[0]).kind, ForestKind::Rule(0));
+}
+
+#[test]
+fn counts() {
+    let grammar = grammar(r#"e { e "+" e | "1" }"#);
+    let earley = Earley::new(&grammar);
+    let count = |input: &str| earley.parse(0, input).unwrap().count();
+    assert_eq!(count("1"), Derivations::Finite(1));
+    assert_eq!(count("1+1+1+1"), Derivations::Finite(5));
+    assert_eq!(count("1+1+1+1+1+1"), Derivations::Finite(42));
+}
+
+#[test]
+fn arguments() {
+    // every use of an argument is an alternation of its own
+    let grammar = grammar(r#"r { twice<("a" | "b")> } twice<x> { x x }"#);
+    let forest = Earley::new(&grammar).parse(0, "a b").unwrap();
+    assert_eq!(forest.count(), Derivations::Finite(1));
+    assert!(!forest.is_ambiguous());
+}
+
+#[test]
+fn groups() {
+    let src = r#"a { ("x" | "y") ("x" | "y" | nil) }"#;
+    let grammar = grammar(src);
+    let forest = Earley::new(&grammar).parse(0, "y").unwrap();
+    let tree = forest.tree();
+    assert_eq!(tree.choices, [(0, 1), (1, 2)]);
+    assert_eq!(
+        tree.choices,
+        Interpreter::new(&grammar).parse(0, "y").unwrap().choices
+    );
+}
+
+#[test]
+fn cyclic() {
+    let src = r#"a { a | b | "x" } b { a }"#;
+    let grammar = grammar(src);
+    let forest = Earley::new(&grammar).parse(0, "x").unwrap();
+    assert_eq!(forest.count(), Derivations::Infinite);
+    // going around the cycle is never the shallowest tree
+    check(
+        src,
+        "x",
+        expect![[r#"
+            (a
+              "x")"#]],
+    );
+    let trees: Vec<_> = forest
+        .trees(10)
+        .iter()
+        .map(|tree| tree.snapshot(&grammar, "x"))
+        .collect();
+    expect![[r#"
+        (a
+          "x")"#]]
+    .assert_eq(&trees.join("\n---\n"));
+}
+
+#[test]
+fn recursive_predicate() {
+    // a predicate depending on itself holds neither way
+    let src = r#"a { !a "x" }"#;
+    assert!(!Earley::new(&grammar(src)).recognize(0, "x"));
+    agrees(src, &["x", ""]);
+    agrees(r#"a { !a "x" | "y" }"#, &["x", "y"]);
+}
+
+#[test]
+fn labels() {
+    agrees(
+        r#"s { lhs:num (op:"+" rhs:s | nil) | pair:("(" num num ")") } num { "1" | "2" }"#,
+        &["1", "1 + 2", "1 + 2 + 1", "(1 2)"],
+    );
+}
+
+#[test]
+fn precedence() {
+    let src = r#"@prec expr { ident | left "+" "-" | left "*" | right "^" } ident {}"#;
+    let inputs = [
+        "a",
+        "a + b * c",
+        "a * b + c - d",
+        "a ^ b ^ c * d",
+        "a + + b",
+    ];
+    agrees(src, &inputs);
+    // the tower has a single derivation of every sentence
+    let grammar = grammar(src);
+    let earley = Earley::new(&grammar);
+    for input in &inputs[..4] {
+        let count = earley.parse(0, input).unwrap().count();
+        assert_eq!(count, Derivations::Finite(1), "{input:?}");
+    }
+}
+
+#[test]
+fn precedence_operands() {
+    // operands are copied into the tower, alternatives and all
+    let grammar = grammar(r#"@prec e { ("1" | "2") | left "+" }"#);
+    let forest = Earley::new(&grammar).parse(0, "1+2").unwrap();
+    assert_eq!(forest.count(), Derivations::Finite(1));
+}
+
+#[test]
+fn literal_modifiers() {
+    let src = r#"stmt { i"select" ident b"\x3b" } ident {}"#;
+    agrees(src, &["select a;", "SELECT a;", "Select a", "selec a;"]);
+}
@@ -0,0 +1,277 @@
+//! exhaustive enumeration of short sentences
+//!
+//! [`Enumerator`] yields every sentence of a rule with at most `N` terminals,
+//! shortest first. Sentences of the same length are yielded in sorted order,
+//! each only once. Rules with an empty body, like `ident {}`, are a single
+//! opaque [`Terminal::Builtin`], and case-insensitive literals are
+//! [`Terminal::Insensitive`] whatever their case. Lookahead predicates such
+//! as `!"x"` are taken to always hold, so a grammar using them may not
+//! accept every sentence. Terms joined by `,` and the terms of `@lexical`
+//! rules are separated like any others.
+//!
+//! ```
+//! use cbnf::{enumerate::Enumerator, grammar::Grammar, Cbnf};
+//!
+//! let src = r#"a { "x" a | nil }"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! let sentences: Vec<_> = Enumerator::new(&grammar, 0, 2)
+//!     .map(|s| s.text(&grammar, " "))
+//!     .collect();
+//! assert_eq!(sentences, ["", "x", "x x"]);
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::grammar::{Grammar, Node, NodeKind, RuleId};
+
+#[cfg(test)]
+mod test;
+
+/// A single terminal of a [`Sentence`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Terminal {
+    Literal(String),
+    /// A case-insensitive literal, written as in the grammar
+    Insensitive(String),
+    /// Any text matched by a rule with an empty body
+    Builtin(RuleId),
+}
+
+impl Terminal {
+    /// The terminal matched by a literal node, `None` for other nodes
+    #[must_use]
+    pub fn of(kind: &NodeKind) -> Option<Self> {
+        match kind {
+            NodeKind::Literal(lit) => Some(Self::Literal(lit.clone())),
+            NodeKind::Insensitive(lit) => Some(Self::Insensitive(lit.clone())),
+            NodeKind::Char(c) => Some(Self::Literal(c.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// How many derivation trees a sentence has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Derivations {
+    Finite(u64),
+    /// Cyclic rules such as `a { a | "x" }` derive some sentences in
+    /// infinitely many ways, this is also used for more than `u64::MAX`
+    Infinite,
+}
+
+impl Derivations {
+    /// Whether the sentence has more than one derivation
+    #[must_use]
+    pub const fn is_ambiguous(self) -> bool {
+        !matches!(self, Self::Finite(0 | 1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sentence {
+    pub terminals: Vec<Terminal>,
+    /// Only counted with [`Enumerator::count_derivations`]
+    pub derivations: Option<Derivations>,
+}
+
+impl Sentence {
+    /// The sentence as text, builtins are written as `<name>`
+    #[must_use]
+    pub fn text(&self, grammar: &Grammar, separator: &str) -> String {
+        let terminals: Vec<_> = self
+            .terminals
+            .iter()
+            .map(|t| match t {
+                Terminal::Literal(lit) | Terminal::Insensitive(lit) => lit.clone(),
+                Terminal::Builtin(id) => format!("<{}>", grammar.name(*id)),
+            })
+            .collect();
+        terminals.join(separator)
+    }
+}
+
+/// Sentences of one length with their number of derivations
+///
+/// `u64::MAX` stands for infinitely many derivations.
+type Table = BTreeMap<Vec<Terminal>, u64>;
+
+/// Lazily yields every sentence of a rule up to a length, see the
+/// [module docs](self)
+pub struct Enumerator<'g> {
+    grammar: &'g Grammar,
+    start: RuleId,
+    max_len: usize,
+    count: bool,
+    /// The sentences of every rule by length
+    tables: Vec<Vec<Table>>,
+    /// The next length to compute
+    len: usize,
+    pending: std::vec::IntoIter<(Vec<Terminal>, u64)>,
+}
+
+impl<'g> Enumerator<'g> {
+    /// Enumerates the sentences of `start` with at most `max_len` terminals
+    #[must_use]
+    pub fn new(grammar: &'g Grammar, start: RuleId, max_len: usize) -> Self {
+        Self {
+            grammar,
+            start,
+            max_len,
+            count: false,
+            tables: vec![Vec::new(); grammar.rules.len()],
+            len: 0,
+            pending: Vec::new().into_iter(),
+        }
+    }
+
+    /// Also count the distinct derivations of every sentence
+    #[must_use]
+    pub const fn count_derivations(mut self, count: bool) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Computes the sentences of length `len` for every rule
+    ///
+    /// Rules may refer to each other without consuming any terminals, so
+    /// this grows the tables until they stop changing. Counts that keep
+    /// changing once the sentences are settled come from cycles, and are
+    /// infinite.
+    fn fill(&mut self, len: usize) {
+        for table in &mut self.tables {
+            table.push(Table::new());
+        }
+        let grammar = self.grammar;
+        let mut settled = 0;
+        loop {
+            let mut keys_changed = false;
+            let mut changed = Vec::new();
+            for (id, rule) in grammar.rules.iter().enumerate() {
+                let Some(body) = &rule.body else { continue };
+                let new = self.node(body, len);
+                let old = &self.tables[id][len];
+                if new != *old {
+                    keys_changed |= !new.keys().eq(old.keys());
+                    changed.push((id, new));
+                }
+            }
+            if changed.is_empty() {
+                break;
+            }
+            settled = if keys_changed { 0 } else { settled + 1 };
+            let cyclic = settled > grammar.rules.len();
+            for (id, mut new) in changed {
+                if cyclic {
+                    let old = &self.tables[id][len];
+                    for (sentence, count) in &mut new {
+                        if old.get(sentence) != Some(count) {
+                            *count = u64::MAX;
+                        }
+                    }
+                }
+                self.tables[id][len] = new;
+            }
+        }
+    }
+
+    /// The sentences of `node` with exactly `len` terminals
+    fn node(&self, node: &Node, len: usize) -> Table {
+        match &node.kind {
+            NodeKind::Nil | NodeKind::And(_) | NodeKind::Not(_) | NodeKind::Adjacent => {
+                unit(len, Vec::new())
+            }
+            NodeKind::Literal(lit) | NodeKind::Insensitive(lit) if lit.is_empty() => {
+                unit(len, Vec::new())
+            }
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                unit(len, vec![Terminal::of(&node.kind).expect("a literal")])
+            }
+            NodeKind::Rule(id) => match &self.grammar.rules[*id].body {
+                None => unit(len, vec![Terminal::Builtin(*id)]),
+                Some(_) => self.tables[*id].get(len).cloned().unwrap_or_default(),
+            },
+            NodeKind::Unknown(_) => Table::new(),
+            NodeKind::Seq(nodes) => self.seq(nodes, len),
+            NodeKind::Alt(_, nodes) => {
+                let mut table = Table::new();
+                for node in nodes {
+                    for (sentence, count) in self.node(node, len) {
+                        let total = table.entry(sentence).or_default();
+                        *total = self.add(*total, count);
+                    }
+                }
+                table
+            }
+        }
+    }
+
+    fn seq(&self, nodes: &[Node], len: usize) -> Table {
+        let Some((first, rest)) = nodes.split_first() else {
+            return unit(len, Vec::new());
+        };
+        let mut table = Table::new();
+        for split in 0..=len {
+            let heads = self.node(first, split);
+            if heads.is_empty() {
+                continue;
+            }
+            let tails = self.seq(rest, len - split);
+            for (head, a) in &heads {
+                for (tail, b) in &tails {
+                    let sentence = [head.as_slice(), tail].concat();
+                    let count = if self.count { a.saturating_mul(*b) } else { 1 };
+                    let total = table.entry(sentence).or_default();
+                    *total = self.add(*total, count);
+                }
+            }
+        }
+        table
+    }
+
+    const fn add(&self, a: u64, b: u64) -> u64 {
+        if self.count {
+            a.saturating_add(b)
+        } else {
+            1
+        }
+    }
+}
+
+/// `sentence` if it has `len` terminals
+fn unit(len: usize, sentence: Vec<Terminal>) -> Table {
+    if sentence.len() == len {
+        Table::from([(sentence, 1)])
+    } else {
+        Table::new()
+    }
+}
+
+impl Iterator for Enumerator<'_> {
+    type Item = Sentence;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((terminals, count)) = self.pending.next() {
+                let derivations = self.count.then_some(match count {
+                    u64::MAX => Derivations::Infinite,
+                    n => Derivations::Finite(n),
+                });
+                return Some(Sentence {
+                    terminals,
+                    derivations,
+                });
+            }
+            let len = self.len;
+            if len > self.max_len {
+                return None;
+            }
+            self.fill(len);
+            self.len += 1;
+            let sentences = match &self.grammar.rules[self.start].body {
+                None => unit(len, vec![Terminal::Builtin(self.start)]),
+                Some(_) => self.tables[self.start][len].clone(),
+            };
+            self.pending = sentences.into_iter().collect::<Vec<_>>().into_iter();
+        }
+    }
+}
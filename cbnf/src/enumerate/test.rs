@@ -0,0 +1,109 @@
+use pretty_assertions::assert_eq;
+
+use crate::{
+    enumerate::{Derivations, Enumerator, Terminal},
+    grammar::test::grammar,
+    interp::Interpreter,
+};
+
+fn sentences(src: &str, max_len: usize) -> Vec<String> {
+    let grammar = grammar(src);
+    Enumerator::new(&grammar, 0, max_len)
+        .map(|s| s.text(&grammar, " "))
+        .collect()
+}
+
+fn counts(src: &str, max_len: usize) -> Vec<(String, Derivations)> {
+    let grammar = grammar(src);
+    Enumerator::new(&grammar, 0, max_len)
+        .count_derivations(true)
+        .map(|s| (s.text(&grammar, ""), s.derivations.unwrap()))
+        .collect()
+}
+
+#[test]
+fn increasing_length() {
+    assert_eq!(
+        sentences(r#"a { ("x" | "y") a | "z" }"#, 3),
+        ["z", "x z", "y z", "x x z", "x y z", "y x z", "y y z"]
+    );
+}
+
+#[test]
+fn deduplicated() {
+    assert_eq!(sentences(r#"a { "x" | "x" | b } b { "x" }"#, 5), ["x"]);
+}
+
+#[test]
+fn left_recursion() {
+    assert_eq!(
+        sentences(r#"e { e "+" n | n } n { "1" }"#, 5),
+        ["1", "1 + 1", "1 + 1 + 1"]
+    );
+}
+
+#[test]
+fn builtins() {
+    let grammar = grammar(r#"pair { ident "=" ident } ident {}"#);
+    let sentence = Enumerator::new(&grammar, 0, 5).next().unwrap();
+    assert_eq!(
+        sentence.terminals,
+        [
+            Terminal::Builtin(1),
+            Terminal::Literal("=".into()),
+            Terminal::Builtin(1)
+        ]
+    );
+    assert_eq!(sentence.text(&grammar, " "), "<ident> = <ident>");
+}
+
+#[test]
+fn lazy() {
+    // infinitely many sentences, only the ones taken are computed
+    let grammar = grammar(r#"a { a a | "x" | nil }"#);
+    let mut sentences = Enumerator::new(&grammar, 0, usize::MAX);
+    let first: Vec<_> = sentences.by_ref().take(3).map(|s| s.terminals).collect();
+    assert_eq!(
+        first,
+        [
+            vec![],
+            vec![Terminal::Literal("x".into())],
+            vec![Terminal::Literal("x".into()); 2]
+        ]
+    );
+    assert!(sentences.next().is_some());
+}
+
+#[test]
+fn derivation_counts() {
+    use Derivations::*;
+    assert_eq!(
+        counts(r#"e { e "+" e | "1" }"#, 7),
+        [
+            ("1".into(), Finite(1)),
+            ("1+1".into(), Finite(1)),
+            ("1+1+1".into(), Finite(2)),
+            ("1+1+1+1".into(), Finite(5)),
+        ]
+    );
+    assert_eq!(
+        counts(r#"a { b | c } b { "x" } c { "x" | "y" }"#, 1),
+        [("x".into(), Finite(2)), ("y".into(), Finite(1))]
+    );
+    assert_eq!(
+        counts(r#"a { a | "x" } b { b }"#, 1),
+        [("x".into(), Infinite)]
+    );
+}
+
+#[test]
+fn agrees_with_interpreter() {
+    let src = r#"list { "[" (items | nil) "]" } items { "a" | items "," "a" }"#;
+    let grammar = grammar(src);
+    let interp = Interpreter::new(&grammar);
+    let sentences: Vec<_> = Enumerator::new(&grammar, 0, 7).collect();
+    assert_eq!(sentences.len(), 4);
+    for sentence in sentences {
+        assert!(interp.recognize(0, &sentence.text(&grammar, "")));
+    }
+}
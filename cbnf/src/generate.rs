@@ -0,0 +1,318 @@
+//! random sentence generation, for fuzzing parsers of a grammar
+//!
+//! [`Generator`] walks a [`Grammar`] from a start rule, picking random
+//! alternatives. Once it gets too deep or has produced too many terminals it
+//! only picks the alternatives with the shallowest derivation, so that every
+//! sentence finishes. Rules with an empty body are filled in by a [`Source`].
+//! Lookahead predicates produce nothing and aren't checked, terms joined by
+//! `,` and the terms of `@lexical` rules are written without the separator
+//! between them.
+//!
+//! ```
+//! use cbnf::{generate::Generator, grammar::Grammar, Cbnf};
+//!
+//! let src = r#"list { "[" items "]" } items { ident ("," items | nil) } ident {}"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! let sentence = Generator::new(&grammar).seed(7).generate(0).unwrap();
+//! assert!(sentence.starts_with('['));
+//! ```
+
+use std::fmt;
+
+use crate::grammar::{AltId, Grammar, Node, NodeKind, RuleId};
+
+#[cfg(test)]
+mod test;
+
+/// A small seedable random number generator (splitmix64)
+///
+/// Not suitable for cryptography, but the same seed always gives the same
+/// numbers on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, `n` must not be 0
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Produces the text of rules with an empty body, such as `ident {}`
+pub trait Source {
+    /// Some text matching `name`, `None` if `name` is unknown
+    fn generate(&mut self, name: &str, rng: &mut Rng) -> Option<String>;
+}
+
+/// Text for the builtins of [`crate::interp::DefaultBuiltins`]
+///
+/// `ident` gives short lowercase identifiers, `text` a few lowercase words
+/// and `any` a single lowercase letter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSource;
+
+impl Source for DefaultSource {
+    fn generate(&mut self, name: &str, rng: &mut Rng) -> Option<String> {
+        const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+        let word = |rng: &mut Rng| -> String {
+            let len = 1 + rng.below(6);
+            (0..len)
+                .map(|_| char::from(LETTERS[rng.below(LETTERS.len())]))
+                .collect()
+        };
+        match name {
+            "ident" => Some(word(rng)),
+            "text" => {
+                let words: Vec<_> = (0..rng.below(4)).map(|_| word(rng)).collect();
+                Some(words.join(" "))
+            }
+            "any" => Some(char::from(LETTERS[rng.below(LETTERS.len())]).into()),
+            "EOL" => Some("\n".into()),
+            "EOF" => Some(String::new()),
+            _ => None,
+        }
+    }
+}
+
+/// Why no sentence could be generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    /// The [`Source`] doesn't know this rule with an empty body
+    Unsupported(String),
+    /// An ident that doesn't refer to any rule
+    Unknown(String),
+    /// The rule never finishes deriving, such as `a { "x" a }`
+    Infinite(String),
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported(name) => write!(f, "no source for the empty rule `{name}`"),
+            Self::Unknown(name) => write!(f, "unknown rule `{name}`"),
+            Self::Infinite(name) => write!(f, "rule `{name}` has no finite derivation"),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+type Weights<'g> = Box<dyn Fn(AltId, usize) -> u32 + 'g>;
+
+/// Generates random sentences of a [`Grammar`]
+pub struct Generator<'g> {
+    grammar: &'g Grammar,
+    depths: Vec<Option<usize>>,
+    rng: Rng,
+    max_depth: usize,
+    max_size: usize,
+    separator: String,
+    weights: Option<Weights<'g>>,
+    source: Box<dyn Source + 'g>,
+    /// Whether the next terminal is joined to the last one, after a `,`
+    glue: bool,
+}
+
+impl<'g> Generator<'g> {
+    /// A generator with a seed of 0, a depth budget of 16 rules and a size
+    /// budget of 64 terminals, separating terminals with a space
+    #[must_use]
+    pub fn new(grammar: &'g Grammar) -> Self {
+        Self {
+            grammar,
+            depths: grammar.depths(),
+            rng: Rng::new(0),
+            max_depth: 16,
+            max_size: 64,
+            separator: " ".into(),
+            weights: None,
+            source: Box::new(DefaultSource),
+            glue: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// How many rules deep to go before only picking the shallowest
+    /// alternatives
+    #[must_use]
+    pub const fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// How many terminals to produce before only picking the shallowest
+    /// alternatives
+    ///
+    /// The budget is soft, finishing the sentence may take a few more.
+    #[must_use]
+    pub const fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// The text placed between terminals
+    #[must_use]
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Weights the alternatives of every alternation
+    ///
+    /// `weights` is given the alternation and the index of the alternative,
+    /// alternatives are picked with a probability proportional to their
+    /// weight. A weight of 0 only picks the alternative when it's needed to
+    /// finish the sentence. By default every alternative has a weight of 1.
+    #[must_use]
+    pub fn weights(mut self, weights: impl Fn(AltId, usize) -> u32 + 'g) -> Self {
+        self.weights = Some(Box::new(weights));
+        self
+    }
+
+    #[must_use]
+    pub fn source(mut self, source: impl Source + 'g) -> Self {
+        self.source = Box::new(source);
+        self
+    }
+
+    /// Generates a random sentence of `start`
+    ///
+    /// # Errors
+    /// When the derivation runs into a rule that can't produce text.
+    pub fn generate(&mut self, start: RuleId) -> Result<String, GenerateError> {
+        let mut out = Vec::new();
+        self.glue = false;
+        self.rule(start, 0, &mut out)?;
+        Ok(out.join(&self.separator))
+    }
+
+    fn rule(
+        &mut self,
+        id: RuleId,
+        depth: usize,
+        out: &mut Vec<String>,
+    ) -> Result<(), GenerateError> {
+        let grammar = self.grammar;
+        let rule = &grammar.rules[id];
+        let Some(body) = &rule.body else {
+            let text = self
+                .source
+                .generate(&rule.name, &mut self.rng)
+                .ok_or_else(|| GenerateError::Unsupported(rule.name.clone()))?;
+            self.push(text, out);
+            return Ok(());
+        };
+        if rule.kind.is_lexical() && self.depths[id].is_some() {
+            // a lexical rule is a single token
+            let glue = std::mem::take(&mut self.glue);
+            let mut token = Vec::new();
+            self.node(body, depth + 1, &mut token)?;
+            self.glue = glue;
+            self.push(token.concat(), out);
+            return Ok(());
+        }
+        if self.depths[id].is_none() {
+            let mut unknown = None;
+            body.walk(&mut |n| {
+                if let NodeKind::Unknown(name) = &n.kind {
+                    unknown = unknown.or(Some(name));
+                }
+            });
+            return Err(unknown.map_or_else(
+                || GenerateError::Infinite(rule.name.clone()),
+                |name| GenerateError::Unknown(name.clone()),
+            ));
+        }
+        self.node(body, depth + 1, out)
+    }
+
+    fn node(
+        &mut self,
+        node: &Node,
+        depth: usize,
+        out: &mut Vec<String>,
+    ) -> Result<(), GenerateError> {
+        match &node.kind {
+            NodeKind::Nil | NodeKind::And(_) | NodeKind::Not(_) => Ok(()),
+            NodeKind::Literal(lit) | NodeKind::Insensitive(lit) => {
+                self.push(lit.clone(), out);
+                Ok(())
+            }
+            NodeKind::Char(c) => {
+                self.push(c.to_string(), out);
+                Ok(())
+            }
+            NodeKind::Adjacent => {
+                self.glue = true;
+                Ok(())
+            }
+            NodeKind::Rule(id) => self.rule(*id, depth, out),
+            NodeKind::Unknown(name) => Err(GenerateError::Unknown(name.clone())),
+            NodeKind::Seq(nodes) => nodes.iter().try_for_each(|n| self.node(n, depth, out)),
+            NodeKind::Alt(alt, nodes) => {
+                let pick = self.pick(*alt, nodes, depth, out.len());
+                self.node(&nodes[pick], depth, out)
+            }
+        }
+    }
+
+    /// Adds a terminal, joined to the last one after a `,`
+    fn push(&mut self, text: String, out: &mut Vec<String>) {
+        if text.is_empty() {
+            return;
+        }
+        let glue = std::mem::take(&mut self.glue);
+        match out.last_mut() {
+            Some(last) if glue => last.push_str(&text),
+            _ => out.push(text),
+        }
+    }
+
+    /// Picks an alternative, the shallowest one once over budget
+    fn pick(&mut self, alt: AltId, nodes: &[Node], depth: usize, size: usize) -> usize {
+        let depths: Vec<_> = nodes.iter().map(|n| n.depth(&self.depths)).collect();
+        let shallowest = depths.iter().flatten().min().copied();
+        let over = depth >= self.max_depth || size >= self.max_size;
+        let candidates: Vec<(usize, u32)> = depths
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.is_some() && (!over || **d == shallowest))
+            .map(|(i, _)| (i, self.weights.as_ref().map_or(1, |w| w(alt, i))))
+            .collect();
+        let total: u64 = candidates.iter().map(|&(_, w)| u64::from(w)).sum();
+        if total == 0 {
+            // only zero weights left, take the shallowest
+            return depths
+                .iter()
+                .position(|d| *d == shallowest)
+                .unwrap_or_default();
+        }
+        let mut roll = self.rng.next_u64() % total;
+        for &(i, weight) in &candidates {
+            if roll < u64::from(weight) {
+                return i;
+            }
+            roll -= u64::from(weight);
+        }
+        unreachable!("roll is below the total weight")
+    }
+}
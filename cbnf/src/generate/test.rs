@@ -0,0 +1,106 @@
+use pretty_assertions::assert_eq;
+
+use crate::{
+    generate::{GenerateError, Generator, Rng, Source},
+    grammar::test::grammar,
+    interp::Interpreter,
+};
+
+const EXPR: &str = r#"
+expr { expr op term | term }
+term { num | ident | '(' expr ')' }
+op { "+" | "-" | "*" }
+num { "0" | "1" | "2" }
+ident {}
+"#;
+
+#[test]
+fn sentences_parse() {
+    let grammar = grammar(EXPR);
+    let interp = Interpreter::new(&grammar);
+    for seed in 0..50 {
+        let sentence = Generator::new(&grammar).seed(seed).generate(0).unwrap();
+        assert!(interp.recognize(0, &sentence), "{sentence:?}");
+    }
+}
+
+#[test]
+fn seeded() {
+    let grammar = grammar(EXPR);
+    let a = Generator::new(&grammar).seed(42).generate(0);
+    let b = Generator::new(&grammar).seed(42).generate(0);
+    assert_eq!(a, b);
+    let mut rng = Rng::new(1);
+    assert!((0..100).all(|_| rng.below(3) < 3));
+}
+
+#[test]
+fn budget() {
+    // without a budget this would almost never finish
+    let grammar = grammar(r#"a { a a | a a a | "x" }"#);
+    let mut gen = Generator::new(&grammar).max_depth(4).max_size(8);
+    for _ in 0..20 {
+        let sentence = gen.generate(0).unwrap();
+        assert!(sentence.split(' ').count() <= 3usize.pow(4), "{sentence}");
+    }
+    let sentence = Generator::new(&grammar).max_depth(0).generate(0).unwrap();
+    assert_eq!(sentence, "x");
+}
+
+#[test]
+fn weights() {
+    let grammar = grammar(r#"a { "x" | "y" | "z" }"#);
+    let mut gen = Generator::new(&grammar).weights(|_, i| u32::from(i == 1));
+    for _ in 0..20 {
+        assert_eq!(gen.generate(0).unwrap(), "y");
+    }
+}
+
+#[test]
+fn adjacent() {
+    let grammar = grammar(r#"a { "x" , "y" "z" } b { nil , "x" "y" }"#);
+    assert_eq!(Generator::new(&grammar).generate(0).unwrap(), "xy z");
+    assert_eq!(Generator::new(&grammar).generate(1).unwrap(), "x y");
+}
+
+#[test]
+fn lexical() {
+    let grammar = grammar(r#"a { num "+" num } @lexical num { "1" "2" }"#);
+    assert_eq!(Generator::new(&grammar).generate(0).unwrap(), "12 + 12");
+}
+
+#[test]
+fn source() {
+    struct Numbers;
+    impl Source for Numbers {
+        fn generate(&mut self, name: &str, rng: &mut Rng) -> Option<String> {
+            (name == "number").then(|| rng.below(10).to_string())
+        }
+    }
+    let grammar = grammar(r#"pair { number "," number } number {}"#);
+    let sentence = Generator::new(&grammar)
+        .source(Numbers)
+        .separator("")
+        .generate(0)
+        .unwrap();
+    assert_eq!(sentence.len(), 3);
+    assert!(sentence.chars().nth(1) == Some(','));
+}
+
+#[test]
+fn errors() {
+    let grammar = grammar(r#"a { b } b { "x" b } c { d } e { f } f {}"#);
+    let mut gen = Generator::new(&grammar);
+    assert_eq!(gen.generate(0), Err(GenerateError::Infinite("a".into())));
+    assert_eq!(gen.generate(2), Err(GenerateError::Unknown("d".into())));
+    assert_eq!(gen.generate(3), Err(GenerateError::Unsupported("f".into())));
+}
+
+#[test]
+fn depths() {
+    let grammar = grammar(r#"a { b | "x" } b { c } c { a "y" } d { e } e {} f { f }"#);
+    assert_eq!(
+        grammar.depths(),
+        vec![Some(1), Some(3), Some(2), Some(2), Some(1), None]
+    );
+}
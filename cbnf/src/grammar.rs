@@ -0,0 +1,773 @@
+//! a tree shaped view of a [`Cbnf`]
+//!
+//! The parser stores terms in a flat arena where alternatives are marked by
+//! `Term::Or`, groups by `Term::Group`, lookahead by `Term::And` and
+//! `Term::Not`, adjacency by `Term::Adjacent` and labels by `Term::Label`.
+//! Tools that execute or transform a grammar want a tree instead, so
+//! [`Grammar`] lowers every rule body into a [`Node`] with resolved rule
+//! references, decoded literals and the labels naming them.
+//!
+//! Parameterized rules such as `sep_by<item, sep>` are monomorphized: every
+//! distinct call like `sep_by<expr, ",">` becomes a plain rule of its own,
+//! placed after the rules of the [`Cbnf`], with the arguments substituted
+//! for the parameters.
+//!
+//! The levels of `@prec` rules are desugared into a tower of plain rules,
+//! one for every level, which are also placed after the rules of the
+//! [`Cbnf`].
+
+use std::collections::HashMap;
+
+use crate::{span::BSpan, Assoc, Cbnf, Level, List, LitKind, RuleKind, Term, Value};
+
+#[cfg(test)]
+pub(crate) mod test;
+
+/// The index of a rule within [`Grammar::rules`]
+///
+/// This is the same as the rule's index within [`Cbnf::rules`], the
+/// instances of parameterized rules come after those.
+pub type RuleId = usize;
+
+/// The index of an alternation within [`Grammar::alts`]
+pub type AltId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    pub rules: Vec<RuleDef>,
+    pub alts: Vec<Alt>,
+}
+
+/// A lowered [`crate::Rule`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleDef {
+    pub name: String,
+    /// The span of the rule's name
+    pub span: BSpan,
+    pub kind: RuleKind,
+    /// The names of the rule's attributes, such as `start`
+    pub attributes: Vec<String>,
+    /// The names of the parameters of a parameterized rule, whose body
+    /// refers to them as unknown rules
+    pub params: Vec<String>,
+    /// The parameterized rule this is an instance of
+    pub template: Option<RuleId>,
+    /// The `@prec` rule this is one of the tighter levels of, such as
+    /// `expr.1` of `expr`
+    pub tower: Option<RuleId>,
+    /// `None` for rules with an empty body, such as `ident {}`, which are
+    /// defined outside of the grammar.
+    pub body: Option<Node>,
+}
+
+impl RuleDef {
+    #[must_use]
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attributes.iter().any(|a| a == name)
+    }
+
+    /// The labels within the rule's body in the order they are written,
+    /// each once
+    ///
+    /// These name the fields of the rule's syntax tree, such as `lhs` and
+    /// `rhs` for `lhs:expr "+" rhs:expr`. Labels within lookahead name
+    /// nothing, as lookahead adds nothing to the tree.
+    #[must_use]
+    pub fn labels(&self) -> Vec<&str> {
+        fn walk<'a>(node: &'a Node, labels: &mut Vec<&'a str>) {
+            if let Some(label) = node.label.as_deref() {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+            if let NodeKind::Seq(nodes) | NodeKind::Alt(_, nodes) = &node.kind {
+                for node in nodes {
+                    walk(node, labels);
+                }
+            }
+        }
+        let mut labels = Vec::new();
+        if let Some(body) = &self.body {
+            walk(body, &mut labels);
+        }
+        labels
+    }
+}
+
+/// An alternation, with the spans of each of its alternatives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alt {
+    pub rule: RuleId,
+    pub span: BSpan,
+    /// The first alternative spans its terms, the others also include
+    /// their leading `|`
+    pub alternatives: Vec<BSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: BSpan,
+    /// The label naming the node, such as `lhs` in `lhs:expr`
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    /// `nil`, or an empty group
+    Nil,
+    /// A string literal, with escapes decoded
+    Literal(String),
+    /// A char literal such as `'{'`, always a single char
+    Char(char),
+    /// A case-insensitive literal such as `i"select"`, with escapes decoded
+    Insensitive(String),
+    /// A reference to a rule of the grammar
+    Rule(RuleId),
+    /// An ident that doesn't refer to any rule, or a byte literal that isn't
+    /// valid UTF-8, never matches
+    Unknown(String),
+    /// Terms that follow each other
+    Seq(Vec<Node>),
+    /// Terms seperated by `|`
+    Alt(AltId, Vec<Node>),
+    /// `&node`, matches nothing when `node` would match
+    And(Box<Node>),
+    /// `!node`, matches nothing when `node` wouldn't match
+    Not(Box<Node>),
+    /// `,` between two terms, matches nothing when whitespace follows
+    Adjacent,
+}
+
+impl NodeKind {
+    /// The length of the start of `input` matched by this literal, `None`
+    /// when it doesn't match or this isn't a literal
+    #[must_use]
+    pub fn prefix(&self, input: &str) -> Option<usize> {
+        match self {
+            Self::Literal(lit) => input.starts_with(lit.as_str()).then_some(lit.len()),
+            Self::Char(c) => input.starts_with(*c).then_some(c.len_utf8()),
+            Self::Insensitive(lit) => insensitive_prefix(input, lit),
+            _ => None,
+        }
+    }
+
+    /// The literal as shown in messages, such as `i"select"`
+    #[must_use]
+    pub fn quoted(&self) -> Option<String> {
+        match self {
+            Self::Literal(lit) => Some(format!("{lit:?}")),
+            Self::Char(c) => Some(format!("{c:?}")),
+            Self::Insensitive(lit) => Some(format!("i{lit:?}")),
+            _ => None,
+        }
+    }
+}
+
+/// The length of the start of `input` that is `lit` in any case
+#[must_use]
+pub fn insensitive_prefix(input: &str, lit: &str) -> Option<usize> {
+    let mut rest = input.chars();
+    let matches = lit.chars().all(|c| {
+        rest.next()
+            .is_some_and(|d| c.to_lowercase().eq(d.to_lowercase()))
+    });
+    matches.then(|| input.len() - rest.as_str().len())
+}
+
+impl Node {
+    #[must_use]
+    pub const fn new(kind: NodeKind, span: BSpan) -> Self {
+        Self {
+            kind,
+            span,
+            label: None,
+        }
+    }
+
+    /// The nodes directly nested in this one
+    #[must_use]
+    pub fn children(&self) -> &[Self] {
+        match &self.kind {
+            NodeKind::Seq(nodes) | NodeKind::Alt(_, nodes) => nodes,
+            NodeKind::And(node) | NodeKind::Not(node) => std::slice::from_ref(node),
+            NodeKind::Nil
+            | NodeKind::Literal(_)
+            | NodeKind::Char(_)
+            | NodeKind::Insensitive(_)
+            | NodeKind::Rule(_)
+            | NodeKind::Unknown(_)
+            | NodeKind::Adjacent => &[],
+        }
+    }
+
+    /// The depth of the shallowest derivation of this node
+    ///
+    /// `rules` are the depths of every rule, see [`Grammar::depths`].
+    #[must_use]
+    pub fn depth(&self, rules: &[Option<usize>]) -> Option<usize> {
+        match &self.kind {
+            NodeKind::Nil
+            | NodeKind::Literal(_)
+            | NodeKind::Char(_)
+            | NodeKind::Insensitive(_)
+            | NodeKind::And(_)
+            | NodeKind::Not(_)
+            | NodeKind::Adjacent => Some(0),
+            NodeKind::Rule(id) => rules[*id],
+            NodeKind::Unknown(_) => None,
+            NodeKind::Seq(nodes) => nodes
+                .iter()
+                .try_fold(0, |max, n| Some(max.max(n.depth(rules)?))),
+            NodeKind::Alt(_, nodes) => nodes.iter().filter_map(|n| n.depth(rules)).min(),
+        }
+    }
+
+    /// Visits this node and every node nested within it, parents first
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Self)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+}
+
+impl Grammar {
+    /// Lowers every rule of `cbnf`, `src` being the text it was parsed from
+    #[must_use]
+    pub fn new(cbnf: &Cbnf, src: &str) -> Self {
+        let mut lower = Lower {
+            cbnf,
+            src,
+            alts: Vec::new(),
+            rule: 0,
+            args: Vec::new(),
+            instances: Vec::new(),
+            named: HashMap::new(),
+            generic: false,
+        };
+        let mut rules: Vec<_> = cbnf
+            .rules()
+            .iter()
+            .enumerate()
+            .map(|(id, (name, rule))| {
+                lower.rule = id;
+                let params = cbnf.params(rule);
+                lower.generic = !params.is_empty();
+                lower.args = params
+                    .iter()
+                    .map(|p| {
+                        let name = p.slice(src);
+                        (name, Node::new(NodeKind::Unknown(name.to_owned()), *p))
+                    })
+                    .collect();
+                RuleDef {
+                    name: name.clone(),
+                    params: params.iter().map(|p| p.slice(src).to_owned()).collect(),
+                    template: None,
+                    body: lower.body(rule),
+                    ..lower.def(rule)
+                }
+            })
+            .collect();
+        rules.append(&mut lower.instances);
+        let alts = number_alts(&mut rules, &lower.alts);
+        Self { rules, alts }
+    }
+
+    #[must_use]
+    pub fn rule(&self, name: &str) -> Option<RuleId> {
+        self.rules.iter().position(|r| r.name == name)
+    }
+
+    #[must_use]
+    pub fn name(&self, id: RuleId) -> &str {
+        &self.rules[id].name
+    }
+
+    /// Whether whitespace and trivia are skipped between the terms of a
+    /// rule, see [`crate::Rule::is_syntactic`]
+    #[must_use]
+    pub fn is_syntactic(&self, id: RuleId) -> bool {
+        let rule = &self.rules[id];
+        rule.body.is_some() && !rule.kind.is_lexical()
+    }
+
+    /// The rule marked `#[start]`, or else the first rule
+    ///
+    /// `None` when there are no rules.
+    #[must_use]
+    pub fn start(&self) -> Option<RuleId> {
+        let marked = self.rules.iter().position(|r| r.has_attribute("start"));
+        marked.or_else(|| (!self.rules.is_empty()).then_some(0))
+    }
+
+    /// The rule marked `@trivia`, if any
+    #[must_use]
+    pub fn trivia(&self) -> Option<RuleId> {
+        self.rules.iter().position(|r| r.kind == RuleKind::Trivia)
+    }
+
+    /// The depth of the shallowest derivation of every rule
+    ///
+    /// A rule that only refers to literals has a depth of 1, rules with an
+    /// empty body are taken to always match and also have a depth of 1.
+    /// `None` means the rule can never finish deriving, like `a { "x" a }`.
+    #[must_use]
+    pub fn depths(&self) -> Vec<Option<usize>> {
+        let mut depths = vec![None; self.rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, rule) in self.rules.iter().enumerate() {
+                let depth = rule.body.as_ref().map_or(Some(0), |b| b.depth(&depths));
+                let depth = depth.map(|d| d + 1);
+                if depth.is_some() && depths[id].is_none_or(|d| Some(d) > depth) {
+                    depths[id] = depth;
+                    changed = true;
+                }
+            }
+        }
+        depths
+    }
+}
+
+/// The longest name of an instance, so that rules like `f<x> { f<(x x)> }`
+/// don't expand forever
+const MAX_NAME: usize = 256;
+
+/// The most instances of parameterized rules in a grammar
+const MAX_INSTANCES: usize = 1024;
+
+struct Lower<'a> {
+    cbnf: &'a Cbnf,
+    src: &'a str,
+    alts: Vec<Alt>,
+    rule: RuleId,
+    /// The arguments of the rule being lowered, by parameter name
+    args: Vec<(&'a str, Node)>,
+    /// The instances of parameterized rules, in order of their ids
+    instances: Vec<RuleDef>,
+    /// The ids of the instances by name, such as `sep_by<expr, ",">`
+    named: HashMap<String, RuleId>,
+    /// Whether a parameterized rule itself is being lowered, whose calls
+    /// aren't instantiated
+    generic: bool,
+}
+
+impl Lower<'_> {
+    /// A rule without its name, parameters or body
+    fn def(&self, rule: &crate::Rule) -> RuleDef {
+        RuleDef {
+            name: String::new(),
+            span: rule.name,
+            kind: rule.kind,
+            attributes: self
+                .cbnf
+                .attributes(rule)
+                .iter()
+                .map(|a| a.name.slice(self.src).to_owned())
+                .collect(),
+            params: Vec::new(),
+            template: None,
+            tower: None,
+            body: None,
+        }
+    }
+
+    fn body(&mut self, rule: &crate::Rule) -> Option<Node> {
+        rule.expr
+            .filter(|list| !list.terms().is_empty())
+            .map(|list| match rule.kind {
+                RuleKind::Precedence => self.precedence(rule, list),
+                _ => self.list(list.terms().range(), list.span()),
+            })
+    }
+
+    /// Lowers a `@prec` rule into a tower of rules, one for every level
+    ///
+    /// The rule itself is the loosest level, the others are added as rules
+    /// named like `expr.1`, `expr.2` and so on. Each level matches either a
+    /// single operand of the next, or two joined by one of its operators:
+    ///
+    /// ```text
+    /// left      expr   { expr op expr.1 | expr.1 }
+    /// right     expr   { expr.1 op expr | expr.1 }
+    /// nonassoc  expr   { expr.1 op expr.1 | expr.1 }
+    /// ```
+    ///
+    /// The operands of the tightest level are the terms before the first
+    /// level, copied into every place they are used with alternations of
+    /// their own (see [`number_alts`]). Every sentence has a single
+    /// derivation through the tower.
+    fn precedence(&mut self, rule: &crate::Rule, list: List) -> Node {
+        let terms = self.cbnf.terms();
+        let operand = self.cbnf.operand(rule);
+        let span = match &terms[operand.range()] {
+            [first, .., end] => first.span().to(end.span().to),
+            [only] => only.span(),
+            [] => list.span(),
+        };
+        let operand = self.list(operand.range(), span);
+        let levels = self.cbnf.levels(rule);
+        let name = self.name(self.rule).to_owned();
+        let base = self.cbnf.rules().len();
+        let mut ids = vec![self.rule];
+        for (n, level) in levels.iter().enumerate().skip(1) {
+            ids.push(base + self.instances.len());
+            self.instances.push(RuleDef {
+                name: format!("{name}.{n}"),
+                span: level.span,
+                kind: RuleKind::Syntactic,
+                attributes: Vec::new(),
+                params: Vec::new(),
+                template: None,
+                tower: Some(self.rule),
+                body: None,
+            });
+        }
+        let outer = self.rule;
+        let mut top = operand.clone();
+        for (n, level) in levels.iter().enumerate() {
+            self.rule = ids[n];
+            let next = match (ids.get(n + 1), levels.get(n + 1)) {
+                (Some(&id), Some(next)) => Node::new(NodeKind::Rule(id), next.span),
+                _ => operand.clone(),
+            };
+            let this = Node::new(NodeKind::Rule(ids[n]), level.span);
+            let op = self.operators(level);
+            let binary = match level.assoc {
+                Assoc::Left => vec![this, op, next.clone()],
+                Assoc::Right => vec![next.clone(), op, this],
+                Assoc::NonAssoc => vec![next.clone(), op, next.clone()],
+            };
+            // the loosest level is the body of the rule itself
+            let span = if n == 0 { list.span() } else { level.span };
+            let alt = self.alts.len();
+            self.alts.push(Alt {
+                rule: ids[n],
+                span,
+                alternatives: vec![level.span, next.span],
+            });
+            let binary = Node::new(NodeKind::Seq(binary), level.span);
+            let body = Node::new(NodeKind::Alt(alt, vec![binary, next]), span);
+            if n == 0 {
+                top = body;
+            } else {
+                self.instances[ids[n] - base].body = Some(body);
+            }
+        }
+        self.rule = outer;
+        top
+    }
+
+    /// The operators of a level, as alternatives when there is more than
+    /// one
+    fn operators(&mut self, level: &Level) -> Node {
+        let items: Vec<_> = self.cbnf.operators(level).collect();
+        let mut nodes = self.items(&items, level.operators.to as usize);
+        if nodes.len() == 1 {
+            return nodes.pop().expect("one operator");
+        }
+        let span = level.span.from(nodes[0].span.from);
+        let id = self.alts.len();
+        self.alts.push(Alt {
+            rule: self.rule,
+            span,
+            alternatives: nodes.iter().map(|n| n.span).collect(),
+        });
+        Node::new(NodeKind::Alt(id, nodes), span)
+    }
+
+    /// Lowers the terms starting at each of `items`, the last ending at `to`
+    fn items(&mut self, items: &[usize], to: usize) -> Vec<Node> {
+        let terms = self.cbnf.terms();
+        let ends = items.iter().skip(1).copied().chain([to]);
+        items
+            .iter()
+            .zip(ends)
+            .map(|(&item, end)| self.list(item..end, terms[item].span()))
+            .collect()
+    }
+
+    /// The name of a rule of the grammar being lowered
+    fn name(&self, id: RuleId) -> &str {
+        match self.cbnf.rules().get_index(id) {
+            Some((name, _)) => name,
+            None => &self.instances[id - self.cbnf.rules().len()].name,
+        }
+    }
+
+    /// Lowers a call to a parameterized rule, such as `sep_by<expr, ",">`
+    fn call(&mut self, at: usize, call: List) -> Node {
+        let terms = self.cbnf.terms();
+        let name = terms[at + 1].span().slice(self.src);
+        let args: Vec<_> = self.cbnf.args(call).collect();
+        let args = self.items(&args, call.terms().to as usize);
+        let unknown = || Node::new(NodeKind::Unknown(name.to_owned()), call.span());
+        if let Some((_, arg)) = self.args.iter().rev().find(|(param, _)| *param == name) {
+            // parameters don't take arguments themselves
+            return if args.is_empty() {
+                Node::new(arg.kind.clone(), call.span())
+            } else {
+                unknown()
+            };
+        }
+        let Some((template, _, rule)) = self.cbnf.rules().get_full(name) else {
+            return unknown();
+        };
+        if self.generic {
+            return unknown();
+        }
+        if self.cbnf.params(rule).len() != args.len() {
+            return unknown();
+        }
+        self.instance(template, args)
+            .map_or_else(unknown, |id| Node::new(NodeKind::Rule(id), call.span()))
+    }
+
+    /// The id of the instance of `template` with `args`, lowering it the
+    /// first time
+    fn instance(&mut self, template: RuleId, args: Vec<Node>) -> Option<RuleId> {
+        let rule = &self.cbnf.rules()[template];
+        let shown: Vec<_> = args.iter().map(|a| self.show(a)).collect();
+        let name = format!(
+            "{}<{}>",
+            self.cbnf.rules().get_index(template)?.0,
+            shown.join(", ")
+        );
+        if let Some(&id) = self.named.get(&name) {
+            return Some(id);
+        }
+        if name.len() > MAX_NAME || self.instances.len() >= MAX_INSTANCES {
+            return None;
+        }
+        let id = self.cbnf.rules().len() + self.instances.len();
+        self.named.insert(name.clone(), id);
+        self.instances.push(RuleDef {
+            name,
+            template: Some(template),
+            ..self.def(rule)
+        });
+        let params = self.cbnf.params(rule).iter().map(|p| p.slice(self.src));
+        let args = std::mem::replace(&mut self.args, params.zip(args).collect());
+        let outer = std::mem::replace(&mut self.rule, id);
+        let body = self.body(rule);
+        self.rule = outer;
+        self.args = args;
+        self.instances[id - self.cbnf.rules().len()].body = body;
+        Some(id)
+    }
+
+    /// The text of an argument within the name of an instance
+    fn show(&self, node: &Node) -> String {
+        let join = |nodes: &[Node], sep| {
+            let nodes: Vec<_> = nodes.iter().map(|n| self.show(n)).collect();
+            format!("({})", nodes.join(sep))
+        };
+        match &node.kind {
+            NodeKind::Nil => "nil".into(),
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                node.kind.quoted().expect("a literal")
+            }
+            NodeKind::Rule(id) => self.name(*id).to_owned(),
+            NodeKind::Unknown(name) => name.clone(),
+            NodeKind::Seq(nodes) => join(nodes, " "),
+            NodeKind::Alt(_, nodes) => join(nodes, " | "),
+            NodeKind::And(node) => format!("&{}", self.show(node)),
+            NodeKind::Not(node) => format!("!{}", self.show(node)),
+            NodeKind::Adjacent => ",".into(),
+        }
+    }
+
+    /// Lowers the terms of a list or group, `span` being its full span
+    fn list(&mut self, range: std::ops::Range<usize>, span: BSpan) -> Node {
+        let terms = self.cbnf.terms();
+        let mut alternatives = vec![(Vec::new(), None)];
+        let mut i = range.start;
+        while i < range.end {
+            let node = match terms[i] {
+                Term::Or(list) => {
+                    alternatives.push((Vec::new(), Some(list.span().from)));
+                    i += 1;
+                    continue;
+                }
+                Term::Group(list) => {
+                    let to = list.terms().to as usize;
+                    let node = self.list(i + 1..to, list.span());
+                    i = to;
+                    node
+                }
+                Term::And(list) | Term::Not(list) => {
+                    let to = list.terms().to as usize;
+                    // the term looked ahead at, if it isn't missing
+                    let span = if i + 1 < to {
+                        terms[i + 1].span()
+                    } else {
+                        list.span()
+                    };
+                    let inner = Box::new(self.list(i + 1..to, span));
+                    let kind = match terms[i] {
+                        Term::And(_) => NodeKind::And(inner),
+                        _ => NodeKind::Not(inner),
+                    };
+                    i = to;
+                    Node::new(kind, list.span())
+                }
+                Term::Adjacent(span) => {
+                    i += 1;
+                    Node::new(NodeKind::Adjacent, span)
+                }
+                Term::Ident(span) => {
+                    i += 1;
+                    let name = span.slice(self.src);
+                    match self.args.iter().rev().find(|(param, _)| *param == name) {
+                        Some((_, arg)) => Node::new(arg.kind.clone(), span),
+                        None => Node::new(self.ident(name), span),
+                    }
+                }
+                Term::Call(list) => {
+                    let node = self.call(i, list);
+                    i = list.terms().to as usize;
+                    node
+                }
+                Term::Literal(span, kind) => {
+                    i += 1;
+                    Node::new(literal(span.slice(self.src), kind), span)
+                }
+                Term::Label(name, list) => {
+                    let to = list.terms().to as usize;
+                    let mut node = self.list(i + 1..to, list.span());
+                    // the innermost of nested labels names the node
+                    node.label
+                        .get_or_insert_with(|| name.slice(self.src).to_owned());
+                    node.span = list.span();
+                    i = to;
+                    node
+                }
+            };
+            alternatives.last_mut().expect("never empty").0.push(node);
+        }
+        let (mut nodes, spans): (Vec<_>, Vec<_>) = alternatives
+            .into_iter()
+            .map(|(nodes, bar)| alternative(nodes, bar, span))
+            .unzip();
+        if nodes.len() == 1 {
+            let node = nodes.pop().expect("one node");
+            return match node.kind {
+                // groups and rule bodies keep their delimiters, labeled
+                // nodes keep their label
+                NodeKind::Seq(_) | NodeKind::Nil if node.label.is_none() => {
+                    Node::new(node.kind, span)
+                }
+                _ => node,
+            };
+        }
+        let id = self.alts.len();
+        self.alts.push(Alt {
+            rule: self.rule,
+            span,
+            alternatives: spans,
+        });
+        Node::new(NodeKind::Alt(id, nodes), span)
+    }
+
+    fn ident(&self, name: &str) -> NodeKind {
+        match self.cbnf.rules().get_index_of(name) {
+            Some(id) => NodeKind::Rule(id),
+            None if name == "nil" => NodeKind::Nil,
+            None => NodeKind::Unknown(name.to_owned()),
+        }
+    }
+}
+
+/// Gives every alternation in the bodies of `rules` an id of its own, rule
+/// by rule, and returns their [`Alt`]s
+///
+/// Arguments and the operands of `@prec` rules are copied into every place
+/// they are used, alternations and all, and a derivation through one copy
+/// isn't one through another. The arguments as lowered at the call are
+/// left out.
+fn number_alts(rules: &mut [RuleDef], lowered: &[Alt]) -> Vec<Alt> {
+    fn visit(node: &mut Node, rule: RuleId, lowered: &[Alt], alts: &mut Vec<Alt>) {
+        match &mut node.kind {
+            NodeKind::Seq(nodes) => {
+                for node in nodes {
+                    visit(node, rule, lowered, alts);
+                }
+            }
+            NodeKind::Alt(id, nodes) => {
+                for node in nodes {
+                    visit(node, rule, lowered, alts);
+                }
+                alts.push(Alt {
+                    rule,
+                    ..lowered[*id].clone()
+                });
+                *id = alts.len() - 1;
+            }
+            NodeKind::And(node) | NodeKind::Not(node) => visit(node, rule, lowered, alts),
+            _ => {}
+        }
+    }
+    let mut alts = Vec::new();
+    for (id, rule) in rules.iter_mut().enumerate() {
+        if let Some(body) = &mut rule.body {
+            visit(body, id, lowered, &mut alts);
+        }
+    }
+    alts
+}
+
+/// Builds the node for a single alternative, along with its span
+///
+/// `bar` is the position of the `|` before it, an empty first alternative is
+/// placed at the start of `list`.
+fn alternative(mut nodes: Vec<Node>, bar: Option<u32>, list: BSpan) -> (Node, BSpan) {
+    let from = nodes.first().map(|n| n.span.from);
+    let from = from.or(bar).unwrap_or(list.from);
+    let to = nodes.last().map(|n| n.span.to);
+    let to = to.or_else(|| bar.map(|b| b + 1)).unwrap_or(from);
+    let span = BSpan::new(bar.unwrap_or(from), to);
+    let node = match nodes.len() {
+        0 => Node::new(NodeKind::Nil, span),
+        1 => nodes.pop().expect("one node"),
+        _ => Node::new(NodeKind::Seq(nodes), BSpan::new(from, to)),
+    };
+    (node, span)
+}
+
+/// The node matching a literal of `kind`, written as `literal`
+fn literal(literal: &str, kind: LitKind) -> NodeKind {
+    // invalid escapes were reported by the parser, such literals are taken
+    // as written
+    let value = kind
+        .decode(literal)
+        .unwrap_or_else(|| Value::Text(kind.contents(literal).1.to_owned()));
+    match value {
+        Value::Text(text) if kind == LitKind::Insensitive => NodeKind::Insensitive(text),
+        Value::Text(text) => NodeKind::Literal(text),
+        Value::Char(c) => NodeKind::Char(c),
+        // the input is text, so it never holds bytes that aren't UTF-8, the
+        // parser reported such literals
+        Value::Bytes(bytes) => String::from_utf8(bytes)
+            .map_or_else(|_| NodeKind::Unknown(literal.to_owned()), NodeKind::Literal),
+    }
+}
+
+/// The text a string or char literal matches
+///
+/// Literals with invalid escapes are taken as written.
+#[must_use]
+pub fn decode(literal: &str) -> String {
+    let kind = if literal.starts_with('\'') {
+        LitKind::Char
+    } else {
+        LitKind::Str
+    };
+    match kind.decode(literal) {
+        Some(Value::Text(text)) => text,
+        Some(Value::Char(c)) => c.into(),
+        _ => kind.contents(literal).1.to_owned(),
+    }
+}
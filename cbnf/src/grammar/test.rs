@@ -0,0 +1,303 @@
+use std::fmt::Write;
+
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    grammar::{decode, Grammar, Node, NodeKind, MAX_NAME},
+    Cbnf,
+};
+
+/// Lowers `src`, which must parse without errors, for the tests of every
+/// module
+pub fn grammar(src: &str) -> Grammar {
+    let cbnf = Cbnf::parse(src);
+    assert_eq!(cbnf.errors(), &[]);
+    Grammar::new(&cbnf, src)
+}
+
+fn print(out: &mut String, src: &str, grammar: &Grammar, node: &Node, depth: usize) {
+    let mut indent = "  ".repeat(depth);
+    if let Some(label) = &node.label {
+        let _ = write!(indent, "{label}: ");
+    }
+    let text = node.span.slice(src);
+    let _ = match &node.kind {
+        NodeKind::Nil => writeln!(out, "{indent}nil {text:?}"),
+        NodeKind::Insensitive(lit) => writeln!(out, "{indent}insensitive {lit:?}"),
+        NodeKind::Literal(lit) => writeln!(out, "{indent}literal {lit:?}"),
+        NodeKind::Char(c) => writeln!(out, "{indent}char {c:?}"),
+        NodeKind::Rule(id) => writeln!(out, "{indent}rule {}", grammar.name(*id)),
+        NodeKind::Unknown(name) => writeln!(out, "{indent}unknown {name}"),
+        NodeKind::Seq(_) => writeln!(out, "{indent}seq {text:?}"),
+        NodeKind::And(_) => writeln!(out, "{indent}and {text:?}"),
+        NodeKind::Not(_) => writeln!(out, "{indent}not {text:?}"),
+        NodeKind::Adjacent => writeln!(out, "{indent}adjacent"),
+        NodeKind::Alt(id, _) => {
+            let alts = grammar.alts[*id].alternatives.iter();
+            let alts: Vec<_> = alts.map(|a| a.slice(src)).collect();
+            writeln!(out, "{indent}alt {alts:?}")
+        }
+    };
+    for child in node.children() {
+        print(out, src, grammar, child, depth + 1);
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, expect: Expect) {
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    let mut out = String::new();
+    for rule in &grammar.rules {
+        let _ = writeln!(out, "{}:", rule.name);
+        if let Some(body) = &rule.body {
+            print(&mut out, src, &grammar, body, 1);
+        }
+    }
+    expect.assert_eq(&out);
+}
+
+#[test]
+fn sequence() {
+    check(
+        r#"a { "x" b nil } b { c } ext {}"#,
+        expect![[r#"
+            a:
+              seq "{ \"x\" b nil }"
+                literal "x"
+                rule b
+                nil "nil"
+            b:
+              unknown c
+            ext:
+        "#]],
+    );
+}
+
+#[test]
+fn alternatives() {
+    check(
+        r#"a { "x" | b "y" | } b { ("(" | nil) a }"#,
+        expect![[r#"
+            a:
+              alt ["\"x\"", "| b \"y\"", "|"]
+                literal "x"
+                seq "b \"y\""
+                  rule b
+                  literal "y"
+                nil "|"
+            b:
+              seq "{ (\"(\" | nil) a }"
+                alt ["\"(\"", "| nil"]
+                  literal "("
+                  nil "nil"
+                rule a
+        "#]],
+    );
+}
+
+#[test]
+fn groups() {
+    check(
+        r#"a { () ("x") ("x" "y") }"#,
+        expect![[r#"
+            a:
+              seq "{ () (\"x\") (\"x\" \"y\") }"
+                nil "()"
+                literal "x"
+                seq "(\"x\" \"y\")"
+                  literal "x"
+                  literal "y"
+        "#]],
+    );
+}
+
+#[test]
+fn predicates() {
+    check(
+        r#"a { !kw ident | &("x" | "y") b } kw { "if" } b { !!"z" "z" }"#,
+        expect![[r#"
+            a:
+              alt ["!kw ident", "| &(\"x\" | \"y\") b"]
+                seq "!kw ident"
+                  not "!kw"
+                    rule kw
+                  unknown ident
+                seq "&(\"x\" | \"y\") b"
+                  and "&(\"x\" | \"y\")"
+                    alt ["\"x\"", "| \"y\""]
+                      literal "x"
+                      literal "y"
+                  rule b
+            kw:
+              literal "if"
+            b:
+              seq "{ !!\"z\" \"z\" }"
+                not "!!\"z\""
+                  not "!\"z\""
+                    literal "z"
+                literal "z"
+        "#]],
+    );
+}
+
+#[test]
+fn adjacent() {
+    check(
+        r#"a { b , "." , b } b {}"#,
+        expect![[r#"
+            a:
+              seq "{ b , \".\" , b }"
+                rule b
+                adjacent
+                literal "."
+                adjacent
+                rule b
+            b:
+        "#]],
+    );
+}
+
+#[test]
+fn decoding() {
+    assert_eq!(decode(r#""a\tb""#), "a\tb");
+    assert_eq!(decode(r"'\u{41}'"), "A");
+    assert_eq!(decode(r##"r#"a\"#"##), "a\\");
+    assert_eq!(decode(r#""\q""#), "\\q");
+}
+
+#[test]
+fn start() {
+    let src = "a { b } #[inline] #[start] b { a } c {}";
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    assert_eq!(grammar.start(), Some(1));
+    assert_eq!(grammar.rules[1].attributes, ["inline", "start"]);
+    assert!(grammar.rules[1].has_attribute("inline"));
+    let src = "a { b } b {}";
+    assert_eq!(Grammar::new(&Cbnf::parse(src), src).start(), Some(0));
+    assert_eq!(Grammar::new(&Cbnf::parse(""), "").start(), None);
+}
+
+#[test]
+fn instances() {
+    check(
+        r#"list { sep_by<item, ","> sep_by<(item | "x"), ";"> } sep_by<item, sep> { item (sep sep_by<item, sep> | nil) } item {}"#,
+        expect![[r#"
+            list:
+              seq "{ sep_by<item, \",\"> sep_by<(item | \"x\"), \";\"> }"
+                rule sep_by<item, ",">
+                rule sep_by<(item | "x"), ";">
+            sep_by:
+              seq "{ item (sep sep_by<item, sep> | nil) }"
+                unknown item
+                alt ["sep sep_by<item, sep>", "| nil"]
+                  seq "sep sep_by<item, sep>"
+                    unknown sep
+                    unknown sep_by
+                  nil "nil"
+            item:
+            sep_by<item, ",">:
+              seq "{ item (sep sep_by<item, sep> | nil) }"
+                rule item
+                alt ["sep sep_by<item, sep>", "| nil"]
+                  seq "sep sep_by<item, sep>"
+                    literal ","
+                    rule sep_by<item, ",">
+                  nil "nil"
+            sep_by<(item | "x"), ";">:
+              seq "{ item (sep sep_by<item, sep> | nil) }"
+                alt ["item", "| \"x\""]
+                  rule item
+                  literal "x"
+                alt ["sep sep_by<item, sep>", "| nil"]
+                  seq "sep sep_by<item, sep>"
+                    literal ";"
+                    rule sep_by<(item | "x"), ";">
+                  nil "nil"
+        "#]],
+    );
+    let src = r#"a { f<"x"> } f<x> { f<(x x)> }"#;
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    assert!(grammar.rules.iter().all(|r| r.name.len() <= MAX_NAME));
+    assert_eq!(grammar.rules.len(), 8);
+    assert_eq!(grammar.rules[2].template, Some(1));
+    assert_eq!(grammar.rules[1].params, ["x"]);
+}
+
+#[test]
+fn labels() {
+    check(
+        r#"bin { lhs:atom op:("+" | "-") rhs:atom | &x:atom pair:(a:atom b:atom) } atom {}"#,
+        expect![[r#"
+            bin:
+              alt ["lhs:atom op:(\"+\" | \"-\") rhs:atom", "| &x:atom pair:(a:atom b:atom)"]
+                seq "lhs:atom op:(\"+\" | \"-\") rhs:atom"
+                  lhs: rule atom
+                  op: alt ["\"+\"", "| \"-\""]
+                    literal "+"
+                    literal "-"
+                  rhs: rule atom
+                seq "&x:atom pair:(a:atom b:atom)"
+                  and "&x:atom"
+                    x: rule atom
+                  pair: seq "pair:(a:atom b:atom)"
+                    a: rule atom
+                    b: rule atom
+            atom:
+        "#]],
+    );
+    let src =
+        r#"bin { lhs:atom op:("+" | "-") rhs:atom | &x:atom pair:(a:atom lhs:atom) } atom {}"#;
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    assert_eq!(grammar.rules[0].labels(), ["lhs", "op", "rhs", "pair", "a"]);
+}
+
+#[test]
+fn precedence() {
+    check(
+        r#"@prec expr { atom | left "+" "-" | right "^" | nonassoc "==" } atom {}"#,
+        expect![[r#"
+            expr:
+              alt ["left \"+\" \"-\"", "right \"^\""]
+                seq "left \"+\" \"-\""
+                  rule expr
+                  alt ["\"+\"", "\"-\""]
+                    literal "+"
+                    literal "-"
+                  rule expr.1
+                rule expr.1
+            atom:
+            expr.1:
+              alt ["right \"^\"", "nonassoc \"==\""]
+                seq "right \"^\""
+                  rule expr.2
+                  literal "^"
+                  rule expr.1
+                rule expr.2
+            expr.2:
+              alt ["nonassoc \"==\"", "atom"]
+                seq "nonassoc \"==\""
+                  rule atom
+                  literal "=="
+                  rule atom
+                rule atom
+        "#]],
+    );
+}
+
+#[test]
+fn literal_modifiers() {
+    check(
+        r#"a { i"Select" b"\x41\x42" b"\xff" i"\u{e9}" '\'' "'" }"#,
+        expect![[r#"
+            a:
+              seq "{ i\"Select\" b\"\\x41\\x42\" b\"\\xff\" i\"\\u{e9}\" '\\'' \"'\" }"
+                insensitive "Select"
+                literal "AB"
+                unknown b"\xff"
+                insensitive "é"
+                char '\''
+                literal "'"
+        "#]],
+    );
+}
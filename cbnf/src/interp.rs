@@ -0,0 +1,727 @@
+//! an interpreter that parses input directly with a [`Grammar`]
+//!
+//! Instead of generating a parser, [`Interpreter`] walks the lowered rules
+//! and computes, for each rule and start position, every position a match
+//! could end at. Sets of ends make ambiguity and backtracking free, and left
+//! recursion is handled by growing the sets until they stop changing.
+//!
+//! Rules with an empty body, like `ident {}`, are matched by [`Builtins`].
+//! Within syntactic rules, whitespace and the longest match of the
+//! `@trivia` rule are skipped before every literal, builtin and reference to
+//! a lexical rule, except right after a `,` such as in `ident , "."`.
+//! Nothing is skipped within `@lexical` rules.
+
+use std::{cell::RefCell, collections::HashMap, fmt, fmt::Write, rc::Rc};
+
+use crate::{
+    grammar::{AltId, Grammar, Node, NodeKind, RuleId},
+    span::BSpan,
+    util::{is_id_continue, is_id_start, is_whitespace},
+};
+
+#[cfg(test)]
+mod test;
+
+/// The position a rule with an empty body is asked to match at
+#[derive(Debug, Clone, Copy)]
+pub struct Scan<'a> {
+    pub input: &'a str,
+    pub pos: usize,
+    /// `pos` after any skipped whitespace
+    pub skipped: usize,
+}
+
+impl Scan<'_> {
+    /// The input after `skipped`
+    #[must_use]
+    pub fn rest(&self) -> &str {
+        &self.input[self.skipped..]
+    }
+}
+
+/// Matches rules that have an empty body
+pub trait Builtins {
+    /// Every position a match of `name` starting at `at` could end at
+    ///
+    /// Unknown names should return no positions.
+    fn scan(&self, name: &str, at: Scan<'_>) -> Vec<usize>;
+}
+
+impl<B: Builtins + ?Sized> Builtins for &B {
+    fn scan(&self, name: &str, at: Scan<'_>) -> Vec<usize> {
+        (**self).scan(name, at)
+    }
+}
+
+/// The builtins used by `cbnf.cbnf`, along with `text`
+///
+/// - `ident` an identifier, such as `foo_bar`
+/// - `text` any text, including none
+/// - `any` a single char
+/// - `EOL` a line break, after optional spaces or tabs
+/// - `EOF` the end of the input
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultBuiltins;
+
+impl Builtins for DefaultBuiltins {
+    fn scan(&self, name: &str, at: Scan<'_>) -> Vec<usize> {
+        match name {
+            "ident" => {
+                let mut chars = at.rest().char_indices();
+                match chars.next() {
+                    Some((_, c)) if is_id_start(c) => {
+                        let len = chars
+                            .find(|&(_, c)| !is_id_continue(c))
+                            .map_or_else(|| at.rest().len(), |(i, _)| i);
+                        vec![at.skipped + len]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "text" => at.input[at.pos..]
+                .char_indices()
+                .map(|(i, _)| at.pos + i)
+                .chain([at.input.len()])
+                .collect(),
+            "any" => at.input[at.pos..]
+                .chars()
+                .next()
+                .map(|c| vec![at.pos + c.len_utf8()])
+                .unwrap_or_default(),
+            "EOL" => {
+                let rest = &at.input[at.pos..];
+                let line = rest.trim_start_matches([' ', '\t']);
+                let spaces = rest.len() - line.len();
+                ["\n", "\r\n"]
+                    .into_iter()
+                    .find(|eol| line.starts_with(eol))
+                    .map(|eol| vec![at.pos + spaces + eol.len()])
+                    .unwrap_or_default()
+            }
+            "EOF" if at.skipped == at.input.len() => vec![at.skipped],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// What a [`Cst`] node matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CstKind {
+    Rule(RuleId),
+    Literal,
+    /// A rule with an empty body, matched by [`Builtins`]
+    Builtin(RuleId),
+}
+
+/// A concrete syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst {
+    pub kind: CstKind,
+    /// The matched text, without leading whitespace
+    pub span: BSpan,
+    pub children: Vec<Self>,
+    /// The alternative taken at each alternation within the rule's body, in
+    /// the order they were entered
+    pub choices: Vec<(AltId, usize)>,
+    /// The label of the term this tree matched within its parent's rule,
+    /// such as `lhs` in `lhs:expr`
+    pub label: Option<String>,
+}
+
+impl Cst {
+    /// Renders the tree as an s-expression, one node per line
+    ///
+    /// ```text
+    /// (rule
+    ///   name: (ident "yeah")
+    ///   "{"
+    ///   "}")
+    /// ```
+    #[must_use]
+    pub fn snapshot(&self, grammar: &Grammar, input: &str) -> String {
+        let mut out = String::new();
+        self.write(&mut out, grammar, input, 0);
+        out
+    }
+
+    /// The children labeled `label`
+    pub fn field<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a Self> + 'a {
+        self.children
+            .iter()
+            .filter(move |c| c.label.as_deref() == Some(label))
+    }
+
+    fn write(&self, out: &mut String, grammar: &Grammar, input: &str, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        if let Some(label) = &self.label {
+            _ = write!(out, "{label}: ");
+        }
+        let text = self.span.slice(input);
+        match self.kind {
+            CstKind::Literal => _ = write!(out, "{text:?}"),
+            CstKind::Builtin(id) => _ = write!(out, "({} {text:?})", grammar.name(id)),
+            CstKind::Rule(id) => {
+                out.push('(');
+                out.push_str(grammar.name(id));
+                for child in &self.children {
+                    out.push('\n');
+                    child.write(out, grammar, input, depth + 1);
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// The input could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The furthest position any match failed at
+    pub pos: usize,
+    /// What was expected at `pos`, literals are quoted
+    pub expected: Vec<String>,
+    /// The char found at `pos`, `None` at the end of the input
+    pub found: Option<char>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.found {
+            Some(c) => write!(f, "unexpected {c:?} at byte {}", self.pos)?,
+            None => write!(f, "unexpected end of input at byte {}", self.pos)?,
+        }
+        if let Some((first, rest)) = self.expected.split_first() {
+            write!(f, ", expected one of: {first}")?;
+            for e in rest {
+                write!(f, ", {e}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses input with the rules of a [`Grammar`]
+pub struct Interpreter<'g> {
+    grammar: &'g Grammar,
+    builtins: Box<dyn Builtins + 'g>,
+    skip_whitespace: bool,
+}
+
+impl<'g> Interpreter<'g> {
+    /// An interpreter using [`DefaultBuiltins`] that skips whitespace
+    #[must_use]
+    pub fn new(grammar: &'g Grammar) -> Self {
+        Self {
+            grammar,
+            builtins: Box::new(DefaultBuiltins),
+            skip_whitespace: true,
+        }
+    }
+
+    #[must_use]
+    pub fn builtins(mut self, builtins: impl Builtins + 'g) -> Self {
+        self.builtins = Box::new(builtins);
+        self
+    }
+
+    /// Whether whitespace is skipped before literals and at the end of input
+    #[must_use]
+    pub const fn skip_whitespace(mut self, skip: bool) -> Self {
+        self.skip_whitespace = skip;
+        self
+    }
+
+    #[must_use]
+    pub const fn grammar(&self) -> &'g Grammar {
+        self.grammar
+    }
+
+    /// Whether all of `input` matches `start`
+    #[must_use]
+    pub fn recognize(&self, start: RuleId, input: &str) -> bool {
+        let mut run = Run::new(self, input);
+        let ends = run.rule(start, 0).0;
+        ends.iter().any(|&end| run.skip(end) == input.len())
+    }
+
+    /// Parses all of `input` as `start`
+    ///
+    /// When the input is ambiguous, earlier alternatives are preferred.
+    ///
+    /// # Errors
+    /// When `input` doesn't match `start`.
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    pub fn parse(&self, start: RuleId, input: &str) -> Result<Cst, ParseError> {
+        let mut run = Run::new(self, input);
+        let ends = run.rule(start, 0).0;
+        let mut found = None;
+        for &end in ends.iter().rev() {
+            let skipped = run.skip(end);
+            if skipped == input.len() {
+                found = found.or(Some(end));
+            } else {
+                run.fail(skipped, || "end of input".into());
+            }
+        }
+        let Some(end) = found else {
+            return Err(run.error());
+        };
+        let mut stack = Vec::new();
+        Ok(run
+            .build_rule(start, 0, end, &mut stack)
+            .expect("a recognized input has a derivation"))
+    }
+}
+
+/// Skips what comes before the terms of syntactic rules, shared by every
+/// parser of a [`Grammar`]
+pub(crate) struct Trivia<'a> {
+    interp: Interpreter<'a>,
+    rule: Option<RuleId>,
+    whitespace: bool,
+    input: &'a str,
+    /// The end of what was skipped from a position
+    skipped: RefCell<HashMap<usize, usize>>,
+}
+
+impl<'a> Trivia<'a> {
+    pub(crate) fn new(
+        grammar: &'a Grammar,
+        builtins: &'a dyn Builtins,
+        whitespace: bool,
+        input: &'a str,
+    ) -> Self {
+        Self {
+            interp: Interpreter::new(grammar).builtins(builtins),
+            rule: grammar.trivia(),
+            whitespace,
+            input,
+            skipped: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn whitespace(&self, pos: usize) -> usize {
+        if !self.whitespace {
+            return pos;
+        }
+        let rest = &self.input[pos..];
+        self.input.len() - rest.trim_start_matches(is_whitespace).len()
+    }
+
+    /// `pos` after any whitespace and trivia
+    pub(crate) fn skip(&self, pos: usize) -> usize {
+        let Some(rule) = self.rule else {
+            return self.whitespace(pos);
+        };
+        if let Some(&end) = self.skipped.borrow().get(&pos) {
+            return end;
+        }
+        let mut end = self.whitespace(pos);
+        loop {
+            let mut run = Run::new(&self.interp, self.input);
+            // the rules the trivia is made of may be syntactic, skipping
+            // trivia within them would never end
+            run.skipping = false;
+            match run.rule(rule, end).0.iter().max() {
+                Some(&next) if next > end => end = self.whitespace(next),
+                _ => break,
+            }
+        }
+        self.skipped.borrow_mut().insert(pos, end);
+        end
+    }
+}
+
+/// No dependency on a rule that is still being computed
+const SETTLED: usize = usize::MAX;
+
+/// The state of a single parse
+struct Run<'a, 'g> {
+    interp: &'a Interpreter<'g>,
+    input: &'a str,
+    trivia: Trivia<'a>,
+    /// Whether the rule being matched is lexical, so nothing is skipped
+    lexical: bool,
+    /// Whether anything is skipped at all, not while matching trivia
+    skipping: bool,
+    /// Final end positions of rules at a start position
+    done: HashMap<(RuleId, usize), Rc<[usize]>>,
+    /// Approximations of rules that are still being computed
+    approx: HashMap<(RuleId, usize), Rc<[usize]>>,
+    /// Rules being computed, innermost last
+    stack: Vec<(RuleId, usize)>,
+    furthest: usize,
+    expected: Vec<String>,
+}
+
+impl<'a, 'g> Run<'a, 'g> {
+    fn new(interp: &'a Interpreter<'g>, input: &'a str) -> Self {
+        Self {
+            interp,
+            input,
+            trivia: Trivia::new(
+                interp.grammar,
+                &*interp.builtins,
+                interp.skip_whitespace,
+                input,
+            ),
+            lexical: false,
+            skipping: true,
+            done: HashMap::new(),
+            approx: HashMap::new(),
+            stack: Vec::new(),
+            furthest: 0,
+            expected: Vec::new(),
+        }
+    }
+
+    fn skip(&self, pos: usize) -> usize {
+        if self.lexical || !self.skipping {
+            return pos;
+        }
+        self.trivia.skip(pos)
+    }
+
+    /// Where a reference to `rule` starts matching, lexical rules don't
+    /// skip anything themselves
+    fn start(&self, rule: RuleId, pos: usize) -> usize {
+        let grammar = self.interp.grammar;
+        match grammar.rules[rule].body {
+            Some(_) if !grammar.is_syntactic(rule) => self.skip(pos),
+            _ => pos,
+        }
+    }
+
+    /// Records that something was expected but not found at `pos`
+    fn fail(&mut self, pos: usize, expected: impl FnOnce() -> String) {
+        if pos > self.furthest {
+            self.furthest = pos;
+            self.expected.clear();
+        }
+        if pos == self.furthest {
+            self.expected.push(expected());
+        }
+    }
+
+    fn error(mut self) -> ParseError {
+        self.expected.sort();
+        self.expected.dedup();
+        ParseError {
+            pos: self.furthest,
+            found: self.input[self.furthest..].chars().next(),
+            expected: self.expected,
+        }
+    }
+
+    /// The ends of `rule` starting at `pos`
+    ///
+    /// Also returns the depth of the outermost rule on the stack the result
+    /// depends on, or [`SETTLED`] if the result is final.
+    fn rule(&mut self, rule: RuleId, pos: usize) -> (Rc<[usize]>, usize) {
+        let key = (rule, pos);
+        let Some(body) = &self.interp.grammar.rules[rule].body else {
+            return (self.builtin(rule, pos).into(), SETTLED);
+        };
+        if let Some(ends) = self.done.get(&key) {
+            return (ends.clone(), SETTLED);
+        }
+        if let Some(depth) = self.stack.iter().position(|k| *k == key) {
+            let ends = self.approx.get(&key).cloned();
+            return (ends.unwrap_or_else(|| Rc::from([])), depth);
+        }
+        let depth = self.stack.len();
+        self.stack.push(key);
+        let outer = self.lexical;
+        self.lexical = self.interp.grammar.rules[rule].kind.is_lexical();
+        // grow the ends until using them for recursive calls changes nothing
+        let (ends, low) = loop {
+            let (ends, low) = self.node(body, pos);
+            let ends: Rc<[usize]> = ends.into();
+            let prev = self.approx.insert(key, ends.clone());
+            if low > depth || prev.as_ref() == Some(&ends) {
+                break (ends, low);
+            }
+        };
+        self.lexical = outer;
+        self.stack.pop();
+        if low >= depth {
+            self.approx.remove(&key);
+            self.done.insert(key, ends.clone());
+            return (ends, SETTLED);
+        }
+        (ends, low)
+    }
+
+    fn builtin(&mut self, rule: RuleId, pos: usize) -> Vec<usize> {
+        let name = self.interp.grammar.name(rule);
+        let at = Scan {
+            input: self.input,
+            pos,
+            skipped: self.skip(pos),
+        };
+        let ends = self.interp.builtins.scan(name, at);
+        if ends.is_empty() {
+            self.fail(at.skipped, || name.to_owned());
+        }
+        ends
+    }
+
+    /// The sorted ends of `node` starting at `pos`, see [`Run::rule`]
+    fn node(&mut self, node: &Node, pos: usize) -> (Vec<usize>, usize) {
+        match &node.kind {
+            NodeKind::Nil => (vec![pos], SETTLED),
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                let at = self.skip(pos);
+                let Some(len) = node.kind.prefix(&self.input[at..]) else {
+                    self.fail(at, || node.kind.quoted().expect("a literal"));
+                    return (Vec::new(), SETTLED);
+                };
+                (vec![at + len], SETTLED)
+            }
+            NodeKind::Rule(rule) => {
+                let (ends, low) = self.rule(*rule, self.start(*rule, pos));
+                (ends.to_vec(), low)
+            }
+            NodeKind::Unknown(_) => (Vec::new(), SETTLED),
+            NodeKind::Adjacent => {
+                if self.skip(pos) == pos {
+                    (vec![pos], SETTLED)
+                } else {
+                    self.fail(pos, || "no whitespace".into());
+                    (Vec::new(), SETTLED)
+                }
+            }
+            NodeKind::And(node) => {
+                let (ends, low) = self.node(node, pos);
+                // a predicate depending on a rule still being computed, as
+                // in `a { !a "x" }`, has no fixed point and holds neither way
+                if ends.is_empty() || low != SETTLED {
+                    (Vec::new(), SETTLED)
+                } else {
+                    (vec![pos], SETTLED)
+                }
+            }
+            NodeKind::Not(node) => {
+                // what `node` expected is no help when it must not match
+                let furthest = self.furthest;
+                let expected = std::mem::take(&mut self.expected);
+                let (ends, low) = self.node(node, pos);
+                (self.furthest, self.expected) = (furthest, expected);
+                if ends.is_empty() && low == SETTLED {
+                    (vec![pos], SETTLED)
+                } else {
+                    (Vec::new(), SETTLED)
+                }
+            }
+            NodeKind::Seq(nodes) => {
+                let mut ends = vec![pos];
+                let mut low = SETTLED;
+                for node in nodes {
+                    let mut next = Vec::new();
+                    for pos in ends {
+                        let (e, l) = self.node(node, pos);
+                        next.extend(e);
+                        low = low.min(l);
+                    }
+                    next.sort_unstable();
+                    next.dedup();
+                    ends = next;
+                }
+                (ends, low)
+            }
+            NodeKind::Alt(_, nodes) => {
+                let mut ends = Vec::new();
+                let mut low = SETTLED;
+                for node in nodes {
+                    let (e, l) = self.node(node, pos);
+                    ends.extend(e);
+                    low = low.min(l);
+                }
+                ends.sort_unstable();
+                ends.dedup();
+                (ends, low)
+            }
+        }
+    }
+
+    fn ends(&mut self, node: &Node, pos: usize) -> Vec<usize> {
+        self.node(node, pos).0
+    }
+
+    /// Builds the tree of `rule` matching exactly `pos..end`
+    ///
+    /// `stack` holds the rules being built, so that cyclic derivations such
+    /// as `a { a | "x" }` pick a different alternative instead of looping.
+    fn build_rule(
+        &mut self,
+        rule: RuleId,
+        pos: usize,
+        end: usize,
+        stack: &mut Vec<(RuleId, usize, usize)>,
+    ) -> Option<Cst> {
+        let grammar = self.interp.grammar;
+        let Some(body) = &grammar.rules[rule].body else {
+            let from = self.skip(pos).min(end);
+            return Some(Cst {
+                kind: CstKind::Builtin(rule),
+                span: span(from, end),
+                children: Vec::new(),
+                choices: Vec::new(),
+                label: None,
+            });
+        };
+        if stack.contains(&(rule, pos, end)) {
+            return None;
+        }
+        stack.push((rule, pos, end));
+        let mut cst = Cst {
+            kind: CstKind::Rule(rule),
+            span: span(pos, end),
+            children: Vec::new(),
+            choices: Vec::new(),
+            label: None,
+        };
+        let outer = self.lexical;
+        self.lexical = grammar.rules[rule].kind.is_lexical();
+        let built = self.build(body, pos, end, &mut cst, stack);
+        self.lexical = outer;
+        stack.pop();
+        if !built {
+            return None;
+        }
+        let from = cst.children.first().map_or(end, |c| c.span.from as usize);
+        cst.span = span(from.min(end), end);
+        Some(cst)
+    }
+
+    /// Adds the trees of `node` matching exactly `pos..end` to `parent`
+    fn build(
+        &mut self,
+        node: &Node,
+        pos: usize,
+        end: usize,
+        parent: &mut Cst,
+        stack: &mut Vec<(RuleId, usize, usize)>,
+    ) -> bool {
+        let mark = parent.children.len();
+        let built = self.build_kind(node, pos, end, parent, stack);
+        if built {
+            label(&mut parent.children[mark..], node.label.as_deref());
+        }
+        built
+    }
+
+    fn build_kind(
+        &mut self,
+        node: &Node,
+        pos: usize,
+        end: usize,
+        parent: &mut Cst,
+        stack: &mut Vec<(RuleId, usize, usize)>,
+    ) -> bool {
+        match &node.kind {
+            NodeKind::Nil => pos == end,
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                if !self.ends(node, pos).contains(&end) {
+                    return false;
+                }
+                parent.children.push(Cst {
+                    kind: CstKind::Literal,
+                    span: span(self.skip(pos), end),
+                    children: Vec::new(),
+                    choices: Vec::new(),
+                    label: None,
+                });
+                true
+            }
+            NodeKind::Rule(rule) => {
+                if !self.ends(node, pos).contains(&end) {
+                    return false;
+                }
+                self.build_rule(*rule, self.start(*rule, pos), end, stack)
+                    .map(|cst| parent.children.push(cst))
+                    .is_some()
+            }
+            NodeKind::Unknown(_) => false,
+            NodeKind::And(_) | NodeKind::Not(_) | NodeKind::Adjacent => {
+                self.ends(node, pos).contains(&end)
+            }
+            NodeKind::Seq(nodes) => self.build_seq(nodes, pos, end, parent, stack),
+            NodeKind::Alt(alt, nodes) => {
+                for (i, node) in nodes.iter().enumerate() {
+                    if !self.ends(node, pos).contains(&end) {
+                        continue;
+                    }
+                    let mark = (parent.children.len(), parent.choices.len());
+                    parent.choices.push((*alt, i));
+                    if self.build(node, pos, end, parent, stack) {
+                        return true;
+                    }
+                    parent.children.truncate(mark.0);
+                    parent.choices.truncate(mark.1);
+                }
+                false
+            }
+        }
+    }
+
+    fn build_seq(
+        &mut self,
+        nodes: &[Node],
+        pos: usize,
+        end: usize,
+        parent: &mut Cst,
+        stack: &mut Vec<(RuleId, usize, usize)>,
+    ) -> bool {
+        let Some((first, rest)) = nodes.split_first() else {
+            return pos == end;
+        };
+        // prefer the longest match of the first node
+        for mid in self.ends(first, pos).into_iter().rev() {
+            if mid > end || !self.seq_ends(rest, mid).contains(&end) {
+                continue;
+            }
+            let mark = (parent.children.len(), parent.choices.len());
+            if self.build(first, pos, mid, parent, stack)
+                && self.build_seq(rest, mid, end, parent, stack)
+            {
+                return true;
+            }
+            parent.children.truncate(mark.0);
+            parent.choices.truncate(mark.1);
+        }
+        false
+    }
+
+    fn seq_ends(&mut self, nodes: &[Node], pos: usize) -> Vec<usize> {
+        let mut ends = vec![pos];
+        for node in nodes {
+            let mut next = Vec::new();
+            for pos in ends {
+                next.extend(self.ends(node, pos));
+            }
+            next.sort_unstable();
+            next.dedup();
+            ends = next;
+        }
+        ends
+    }
+}
+
+/// Gives the trees matched by a node labeled `label` that label, unless a
+/// label within the node already named them
+pub(crate) fn label(trees: &mut [Cst], label: Option<&str>) {
+    let Some(label) = label else { return };
+    for tree in trees {
+        tree.label.get_or_insert_with(|| label.to_owned());
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn span(from: usize, to: usize) -> BSpan {
+    BSpan::new(from as u32, to as u32)
+}
@@ -0,0 +1,364 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    earley::Earley,
+    grammar::{test::grammar, Grammar},
+    interp::{Builtins, Interpreter, Scan},
+    parser::error::ErrorKind,
+    peg::Peg,
+    Cbnf,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, input: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let actual = match Interpreter::new(&grammar).parse(0, input) {
+        Ok(cst) => cst.snapshot(&grammar, input),
+        Err(e) => e.to_string(),
+    };
+    expect.assert_eq(&actual);
+}
+
+fn accepts(src: &str, input: &str) -> bool {
+    let grammar = grammar(src);
+    let interp = Interpreter::new(&grammar);
+    interp.recognize(0, input)
+}
+
+#[test]
+fn sequence() {
+    check(
+        "pair { '(' ident ',' ident ')' } ident {}",
+        "( a , b )",
+        expect![[r#"
+            (pair
+              "("
+              (ident "a")
+              ","
+              (ident "b")
+              ")")"#]],
+    );
+}
+
+#[test]
+fn alternatives() {
+    let src = r#"a { "x" | "y" b } b { "z" | nil }"#;
+    assert!(accepts(src, "x"));
+    assert!(accepts(src, "y"));
+    assert!(accepts(src, "yz"));
+    assert!(!accepts(src, "xz"));
+    assert!(!accepts(src, ""));
+}
+
+#[test]
+fn left_recursion() {
+    let src = r#"expr { expr '+' num | num } num { "1" | "2" }"#;
+    assert!(accepts(src, "1 + 2 + 1"));
+    assert!(!accepts(src, "1 + "));
+    check(
+        src,
+        "1+2+1",
+        expect![[r#"
+            (expr
+              (expr
+                (expr
+                  (num
+                    "1"))
+                "+"
+                (num
+                  "2"))
+              "+"
+              (num
+                "1"))"#]],
+    );
+}
+
+#[test]
+fn indirect_left_recursion() {
+    let src = r#"a { b "x" | "y" } b { a "z" | c } c { a | nil }"#;
+    assert!(accepts(src, "y"));
+    assert!(accepts(src, "x"));
+    assert!(accepts(src, "yzx"));
+    assert!(accepts(src, "yxzx"));
+    assert!(!accepts(src, "z"));
+}
+
+#[test]
+fn cyclic_rule() {
+    check(
+        r#"a { a | "x" }"#,
+        "x",
+        expect![[r#"
+            (a
+              "x")"#]],
+    );
+}
+
+#[test]
+fn recursive_predicate() {
+    // a predicate depending on the rule it is in holds neither way
+    assert!(!accepts(r#"a { !a "x" }"#, "x"));
+    let src = r#"a { !a "x" | "y" }"#;
+    assert!(!accepts(src, "x"));
+    assert!(accepts(src, "y"));
+}
+
+#[test]
+fn builtins() {
+    let src = r#"comment { "//" text (EOL | EOF) } text {} EOL {} EOF {}"#;
+    assert!(accepts(src, "// hello"));
+    assert!(accepts(src, "// hello\n"));
+    assert!(!accepts(src, "/ hello"));
+}
+
+#[test]
+fn custom_builtins() {
+    struct Digits;
+    impl Builtins for Digits {
+        fn scan(&self, name: &str, at: Scan<'_>) -> Vec<usize> {
+            let len = at.rest().len() - at.rest().trim_start_matches(char::is_numeric).len();
+            match name {
+                "digits" if len > 0 => vec![at.skipped + len],
+                _ => Vec::new(),
+            }
+        }
+    }
+    let grammar = grammar(r#"num { digits ("." digits | nil) } digits {}"#);
+    let interp = Interpreter::new(&grammar).builtins(Digits);
+    assert!(interp.recognize(0, "12.5"));
+    assert!(!interp.recognize(0, "12."));
+}
+
+#[test]
+fn whitespace() {
+    let grammar = grammar(r#"a { "x" "y" }"#);
+    assert!(Interpreter::new(&grammar).recognize(0, " x y "));
+    let interp = Interpreter::new(&grammar).skip_whitespace(false);
+    assert!(!interp.recognize(0, "x y"));
+    assert!(interp.recognize(0, "xy"));
+}
+
+#[test]
+fn adjacent() {
+    let src = r#"path { ident , "." , ident } ident {}"#;
+    assert!(accepts(src, "a.b"));
+    assert!(accepts(src, " a.b "));
+    assert!(!accepts(src, "a .b"));
+    assert!(!accepts(src, "a. b"));
+    check(
+        src,
+        "a . b",
+        expect!["unexpected ' ' at byte 1, expected one of: no whitespace"],
+    );
+}
+
+#[test]
+fn lexical() {
+    let src = r#"pair { num num } @lexical num { "1" "2" }"#;
+    assert!(accepts(src, "12 12"));
+    assert!(accepts(src, " 12 12 "));
+    assert!(!accepts(src, "1 2 12"));
+    assert!(!accepts(src, "12 1 2"));
+}
+
+#[test]
+fn trivia() {
+    let src = r#"list { "a" "b" } @trivia comment { "/" "/" ident } ident {}"#;
+    assert!(accepts(src, "a b"));
+    assert!(accepts(src, "//x a //y//z b //w"));
+    assert!(!accepts(src, "a / /x b"));
+    check(
+        src,
+        "a //x b",
+        expect![[r#"
+            (list
+              "a"
+              "b")"#]],
+    );
+    // a syntactic rule within the trivia, an error that is still lowered,
+    // skips nothing
+    let src = r##"r { "a" "b" } @trivia ws { s } s { "#" "x" }"##;
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    let interp = Interpreter::new(&grammar);
+    assert!(interp.recognize(0, "a #x b"));
+    assert!(!interp.recognize(0, "a # x b"));
+}
+
+#[test]
+fn errors() {
+    let src = r#"a { "x" ("y" | b) } b { "z" ident } ident {}"#;
+    check(
+        src,
+        "x w",
+        expect![[r#"unexpected 'w' at byte 2, expected one of: "y", "z""#]],
+    );
+    check(
+        src,
+        "x z",
+        expect!["unexpected end of input at byte 3, expected one of: ident"],
+    );
+    check(
+        src,
+        "x y y",
+        expect!["unexpected 'y' at byte 4, expected one of: end of input"],
+    );
+}
+
+#[test]
+fn choices() {
+    let grammar = grammar(r#"a { ("x" | "y") ("x" | "y") }"#);
+    let cst = Interpreter::new(&grammar).parse(0, "yx").unwrap();
+    assert_eq!(cst.choices, vec![(0, 1), (1, 0)]);
+}
+
+#[test]
+fn parameterized() {
+    let src = r#"call { ident "(" args<(sep_by<ident, ","> | nil)> ")" }
+        args<inner> { inner }
+        sep_by<item, sep> { item (sep sep_by<item, sep> | nil) }
+        ident {}"#;
+    assert!(accepts(src, "f()"));
+    assert!(accepts(src, "f(a)"));
+    assert!(accepts(src, "f(a, b, c)"));
+    assert!(!accepts(src, "f(a,)"));
+    assert!(!accepts(src, "f(a b)"));
+    check(
+        src,
+        "f(a, b)",
+        expect![[r#"
+            (call
+              (ident "f")
+              "("
+              (args<(sep_by<ident, ","> | nil)>
+                (sep_by<ident, ",">
+                  (ident "a")
+                  ","
+                  (sep_by<ident, ",">
+                    (ident "b"))))
+              ")")"#]],
+    );
+}
+
+#[test]
+fn labels() {
+    let src = r#"sum { lhs:ident op:("+" | "-") rhs:(ident | sum) } ident {}"#;
+    check(
+        src,
+        "a + b - c",
+        expect![[r#"
+            (sum
+              lhs: (ident "a")
+              op: "+"
+              rhs: (sum
+                lhs: (ident "b")
+                op: "-"
+                rhs: (ident "c")))"#]],
+    );
+    let grammar = grammar(src);
+    let cst = Interpreter::new(&grammar).parse(0, "a + b").unwrap();
+    let rhs: Vec<_> = cst.field("rhs").map(|c| c.span.slice("a + b")).collect();
+    assert_eq!(rhs, ["b"]);
+}
+
+#[test]
+fn precedence() {
+    let src = r#"@prec expr { ident | left "+" "-" | right "^" | nonassoc "==" } ident {}"#;
+    assert!(accepts(src, "a + b - c"));
+    assert!(accepts(src, "a == b ^ c"));
+    assert!(!accepts(src, "a == b == c"));
+    check(
+        src,
+        "a - b - c ^ d ^ e",
+        expect![[r#"
+            (expr
+              (expr
+                (expr
+                  (expr.1
+                    (expr.2
+                      (ident "a"))))
+                "-"
+                (expr.1
+                  (expr.2
+                    (ident "b"))))
+              "-"
+              (expr.1
+                (expr.2
+                  (ident "c"))
+                "^"
+                (expr.1
+                  (expr.2
+                    (ident "d"))
+                  "^"
+                  (expr.1
+                    (expr.2
+                      (ident "e"))))))"#]],
+    );
+}
+
+#[test]
+fn literal_modifiers() {
+    let src = r#"stmt { i"select" ident b"\x3b" } ident {}"#;
+    assert!(accepts(src, "select a;"));
+    assert!(accepts(src, "SELECT a;"));
+    assert!(accepts(src, "SeLeCt a;"));
+    assert!(!accepts(src, "selec a;"));
+    assert!(!accepts(src, "select a"));
+    assert!(accepts(r#"a { i"é" }"#, "É"));
+    check(
+        src,
+        "sel a;",
+        expect![[r#"unexpected 's' at byte 0, expected one of: i"select""#]],
+    );
+    check(
+        "a { '{' }",
+        "(",
+        expect![["unexpected '(' at byte 0, expected one of: '{'"]],
+    );
+    // bytes that aren't UTF-8 are reported, and never match
+    let src = r#"a { b"\xff" }"#;
+    let cbnf = Cbnf::parse(src);
+    assert_eq!(cbnf.errors()[0].kind, ErrorKind::NonUtf8Bytes);
+    let grammar = Grammar::new(&cbnf, src);
+    assert!(!Interpreter::new(&grammar).recognize(0, "\u{ff}"));
+}
+
+#[test]
+fn self_description() {
+    let src = include_str!("../../../cbnf.cbnf");
+    let cbnf = Cbnf::parse(src);
+    assert_eq!(cbnf.errors(), []);
+    let grammar = Grammar::new(&cbnf, src);
+    let start = grammar.start().unwrap();
+    let interp = Interpreter::new(&grammar);
+    let earley = Earley::new(&grammar);
+    let peg = Peg::new(&grammar);
+    // itself, and grammars using every other feature
+    let grammars = [
+        src,
+        "",
+        "a {}",
+        "/* outer /* nested */ still */ a { nil } // done",
+        "#[start] #[prec(2 x)] a<t, u> { t:b, u | (\"x\" | ) | !'}' c<i\"y\", (d)> }",
+        r##"@lexical s { b"\x22" '\'' "\"" r#"a"# } @trivia t { &EOL "//" }"##,
+        "@prec e { atom | left \"+\" | right \"^\" } atom { \"1\" }",
+    ];
+    for input in grammars {
+        assert_eq!(Cbnf::parse(input).errors(), [], "{input}");
+        assert!(interp.recognize(start, input), "interp: {input}");
+        assert!(earley.recognize(start, input), "earley: {input}");
+        assert!(peg.recognize(start, input), "peg: {input}");
+    }
+    // comments and literals can't run past their end
+    for input in [
+        "}}} ((( garbage !!!",
+        "{",
+        "a { /* x }",
+        "a { \"x }",
+        "a { 'x }",
+    ] {
+        assert!(!interp.recognize(start, input), "interp: {input}");
+        assert!(!earley.recognize(start, input), "earley: {input}");
+        assert!(!peg.recognize(start, input), "peg: {input}");
+    }
+}
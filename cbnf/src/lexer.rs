@@ -10,6 +10,13 @@ use self::token::RawStrError;
 use super::util::{is_id_continue, is_id_start, is_whitespace};
 
 #[cfg(test)]
+#[allow(
+    clippy::needless_raw_string_hashes,
+    clippy::semicolon_if_nothing_returned,
+    clippy::needless_pass_by_value,
+    clippy::format_collect,
+    clippy::uninlined_format_args
+)]
 pub mod test;
 
 pub mod cursor;
@@ -66,6 +73,30 @@ impl Cursor<'_> {
                 Literal { kind, suffix_start }
             }
 
+            // byte string literal or identifier.
+            'b' if self.first() == '"' => {
+                self.bump();
+                let terminated = self.double_quoted_string();
+                let suffix_start = self.pos_within_token();
+                if terminated {
+                    self.eat_literal_suffix();
+                }
+                let kind = ByteStr { terminated };
+                Literal { kind, suffix_start }
+            }
+
+            // case-insensitive string literal or identifier.
+            'i' if self.first() == '"' => {
+                self.bump();
+                let terminated = self.double_quoted_string();
+                let suffix_start = self.pos_within_token();
+                if terminated {
+                    self.eat_literal_suffix();
+                }
+                let kind = InsensitiveStr { terminated };
+                Literal { kind, suffix_start }
+            }
+
             // Identifier (this should be checked after other variant that can
             // start as identifier).
             c if is_id_start(c) => self.ident_or_unknown_prefix(),
@@ -284,7 +315,7 @@ impl Cursor<'_> {
         } else {
             // No base prefix, parse number in the usual way.
             self.eat_decimal_digits();
-        };
+        }
 
         match self.first() {
             // Don't be greedy if this is actually an
@@ -48,7 +48,6 @@ pub const EOF_CHAR: char = '\0';
 
 impl Cursor<'_> {
     /// The current subslice as a string
-
     #[inline]
     #[must_use]
     pub fn as_str(&self) -> &str {
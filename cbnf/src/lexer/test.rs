@@ -205,6 +205,22 @@ fn raw_string() {
     )
 }
 
+#[test]
+fn prefixed_strings() {
+    check_lexing(
+        r#"b"\x00\xff" i"select" b i"open"#,
+        expect![[r"
+            Lexeme { kind: Literal { kind: ByteStr { terminated: true }, suffix_start: 11 }, len: 11 }
+            Lexeme { kind: Whitespace, len: 1 }
+            Lexeme { kind: Literal { kind: InsensitiveStr { terminated: true }, suffix_start: 9 }, len: 9 }
+            Lexeme { kind: Whitespace, len: 1 }
+            Lexeme { kind: Ident, len: 1 }
+            Lexeme { kind: Whitespace, len: 1 }
+            Lexeme { kind: Literal { kind: InsensitiveStr { terminated: false }, suffix_start: 6 }, len: 6 }
+        "]],
+    );
+}
+
 #[test]
 fn literal_suffixes() {
     check_lexing(
@@ -192,6 +192,10 @@ pub enum LiteralKind {
     /// `r"abc"`, `r#"abc"#`, `r####"ab"###"c"####`, `r#"a`. `None` indicates
     /// an invalid literal.
     RawStr { n_hashes: Option<u8> },
+    /// `b"abc"`, `b"\xff"`, `b"abc`
+    ByteStr { terminated: bool },
+    /// `i"abc"`, `i"abc`
+    InsensitiveStr { terminated: bool },
 }
 
 impl LiteralKind {
@@ -204,7 +208,10 @@ impl LiteralKind {
         use LiteralKind::*;
         match self {
             Int { .. } | Float { .. } => true,
-            Char { terminated } | Str { terminated } => terminated,
+            Char { terminated }
+            | Str { terminated }
+            | ByteStr { terminated }
+            | InsensitiveStr { terminated } => terminated,
             RawStr { n_hashes } => n_hashes.is_some(),
         }
     }
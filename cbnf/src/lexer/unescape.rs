@@ -9,6 +9,7 @@ use std::str::Chars;
 use Mode::*;
 
 #[cfg(test)]
+#[allow(clippy::semicolon_if_nothing_returned)]
 mod test;
 
 // NOTE:: see link below for how to use the below:
@@ -18,7 +19,7 @@ mod test;
 /// relate to malformed escape sequences, but there are a few that are about
 /// other problems.
 #[allow(clippy::doc_markdown)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum EscapeError {
     /// Expected 1 char, but 0 were found.
     ZeroChars,
@@ -304,7 +305,7 @@ fn scan_unicode(chars: &mut Chars<'_>, allow_unicode_escapes: bool) -> Result<ch
     loop {
         match chars.next() {
             None => return Err(EscapeError::UnclosedUnicodeEscape),
-            Some('_') => continue,
+            Some('_') => {}
             Some('}') => {
                 if n_digits > 6 {
                     return Err(EscapeError::OverlongUnicodeEscape);
@@ -335,7 +336,7 @@ fn scan_unicode(chars: &mut Chars<'_>, allow_unicode_escapes: bool) -> Result<ch
                 }
                 value = value * 16 + digit;
             }
-        };
+        }
     }
 }
 
@@ -3,7 +3,11 @@
 use indexmap::IndexMap;
 
 use crate::{
-    parser::{error::Error, Parser},
+    lexer::unescape::{self, Mode},
+    parser::{
+        error::{Error, ErrorKind},
+        Parser,
+    },
     span::{BSpan, TSpan},
 };
 
@@ -12,8 +16,22 @@ pub use indexmap;
 // TODO: consider using string interning (or a ton of refs) to
 // allow for partial compilation
 
+pub mod ambiguity;
+pub mod compare;
+pub mod corpus;
+pub mod coverage;
+pub mod cyk;
+pub mod diff;
+pub mod earley;
+pub mod enumerate;
+pub mod generate;
+pub mod grammar;
+pub mod interp;
 pub mod lexer;
+pub mod normal;
 pub mod parser;
+pub mod peg;
+pub mod report;
 pub mod span;
 pub mod util;
 
@@ -24,9 +42,6 @@ pub mod util;
 
 // TODO: add test coverage
 
-// TODO: add whitespace aware syntax
-// something such as a ',' denoting an immediate token
-
 // TODO: add another byte
 
 // TODO: add regex && UnicodeSet notation
@@ -74,6 +89,9 @@ pub struct Cbnf {
     pub docs: Vec<DocComment>,
     pub errors: Vec<Error>,
     pub terms: Vec<Term>,
+    pub attributes: Vec<Attribute>,
+    pub params: Vec<BSpan>,
+    pub levels: Vec<Level>,
 }
 
 impl From<Parser<'_>> for Cbnf {
@@ -87,14 +105,21 @@ impl From<Parser<'_>> for Cbnf {
                 rules.insert(value.slice(rule.name).to_owned(), rule);
             }
         }
-        Self {
+        let mut cbnf = Self {
             rules,
             extras,
             comments: value.comments,
             docs: value.docs,
             errors: value.errors,
             terms: value.terms,
-        }
+            attributes: value.attributes,
+            params: value.params,
+            levels: value.levels,
+        };
+        cbnf.check_kinds(value.cursor.src());
+        cbnf.check_calls(value.cursor.src());
+        cbnf.check_labels(value.cursor.src());
+        cbnf
     }
 }
 
@@ -115,6 +140,11 @@ impl Cbnf {
     pub fn errors(&self) -> &[Error] {
         &self.errors
     }
+    /// Whether any of the errors isn't a warning
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|e| !e.is_warning())
+    }
     #[must_use]
     pub fn parse(input: &str) -> Self {
         Self::from(Parser::new(input))
@@ -127,11 +157,236 @@ impl Cbnf {
     pub fn terms_at(&self, span: TSpan) -> &[Term] {
         &self.terms[span.range()]
     }
+    #[must_use]
+    pub fn attributes(&self, rule: &Rule) -> &[Attribute] {
+        &self.attributes[rule.attributes.range()]
+    }
+    /// The names of the parameters of `rule`, empty unless it is written
+    /// like `sep_by<item, sep> { .. }`
+    #[must_use]
+    pub fn params(&self, rule: &Rule) -> &[BSpan] {
+        &self.params[rule.params.range()]
+    }
+    /// The precedence levels of a `@prec` rule, loosest first
+    #[must_use]
+    pub fn levels(&self, rule: &Rule) -> &[Level] {
+        &self.levels[rule.levels.range()]
+    }
+    /// The terms of a `@prec` rule before its first level, which the
+    /// operators of every level are placed between
+    #[must_use]
+    pub fn operand(&self, rule: &Rule) -> TSpan {
+        let Some(list) = rule.expr else {
+            return TSpan::default();
+        };
+        let terms = list.terms;
+        let to = self
+            .items(terms.from as usize, terms.to as usize)
+            .find(|&i| matches!(self.terms[i], Term::Or(_)));
+        let to = to.and_then(|to| u32::try_from(to).ok());
+        TSpan::new(terms.from, to.unwrap_or(terms.to))
+    }
+    /// The operators of a precedence level, the index of the first term of
+    /// each
+    pub fn operators(&self, level: &Level) -> impl Iterator<Item = usize> + '_ {
+        let operators = level.operators;
+        self.items(operators.from as usize, operators.to as usize)
+    }
+    /// The attribute of `rule` named `name`, `src` being the text `self`
+    /// was parsed from
+    #[must_use]
+    pub fn attribute(&self, rule: &Rule, name: &str, src: &str) -> Option<&Attribute> {
+        self.attributes(rule)
+            .iter()
+            .find(|a| a.name.slice(src) == name)
+    }
+    /// The rule marked `@trivia`, if any
+    #[must_use]
+    pub fn trivia(&self) -> Option<(&str, &Rule)> {
+        self.rules
+            .iter()
+            .find(|(_, rule)| rule.kind == RuleKind::Trivia)
+            .map(|(name, rule)| (name.as_str(), rule))
+    }
+
+    /// Reports parameters declared twice, and rules used with the wrong
+    /// number of arguments
+    fn check_calls(&mut self, src: &str) {
+        let mut errors = Vec::new();
+        for rule in self.rules.values().chain(&self.extras) {
+            let params = self.params(rule);
+            for (i, param) in params.iter().enumerate() {
+                let name = param.slice(src);
+                if let Some(&first) = params[..i].iter().find(|p| p.slice(src) == name) {
+                    errors.push(Error {
+                        span: *param,
+                        kind: ErrorKind::DuplicateParam { first },
+                    });
+                }
+            }
+            let Some(list) = rule.expr else { continue };
+            for i in list.terms.range() {
+                let (span, name, found) = match self.terms[i] {
+                    Term::Call(call) => {
+                        (call.span, self.terms[i + 1].span(), self.args(call).count())
+                    }
+                    // the name of a call was already checked with it
+                    Term::Ident(_) if i > 0 && matches!(self.terms[i - 1], Term::Call(_)) => {
+                        continue
+                    }
+                    Term::Ident(span) => (span, span, 0),
+                    _ => continue,
+                };
+                let name = name.slice(src);
+                let (declared, expected) = match params.iter().find(|p| p.slice(src) == name) {
+                    Some(&param) => (param, 0),
+                    None => match self.rules.get(name) {
+                        Some(target) => (target.name, self.params(target).len()),
+                        None => continue,
+                    },
+                };
+                if found != expected {
+                    errors.push(Error {
+                        span,
+                        kind: ErrorKind::ArityMismatch {
+                            expected,
+                            found,
+                            rule: declared,
+                        },
+                    });
+                }
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    /// Reports labels used twice within the same alternative
+    fn check_labels(&mut self, src: &str) {
+        let mut errors = Vec::new();
+        for rule in self.rules.values().chain(&self.extras) {
+            if let Some(list) = rule.expr {
+                self.labels(list.terms().range(), &[], src, &mut errors);
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    /// The labels within the alternatives of `range`, checking each against
+    /// those of its alternative and the `outer` ones of the enclosing
+    /// alternatives
+    ///
+    /// Alternatives exclude each other, so they may use the same labels.
+    fn labels(
+        &self,
+        range: std::ops::Range<usize>,
+        outer: &[BSpan],
+        src: &str,
+        errors: &mut Vec<Error>,
+    ) -> Vec<BSpan> {
+        let mut all = Vec::new();
+        let mut seen = outer.to_vec();
+        let mut i = range.start;
+        while i < range.end {
+            match self.terms[i] {
+                Term::Or(_) => {
+                    all.extend(seen.drain(outer.len()..));
+                    i += 1;
+                }
+                Term::Label(name, _) => {
+                    let text = name.slice(src);
+                    if let Some(&first) = seen.iter().find(|l| l.slice(src) == text) {
+                        errors.push(Error {
+                            span: name,
+                            kind: ErrorKind::DuplicateLabel { first },
+                        });
+                    }
+                    seen.push(name);
+                    i += 1;
+                }
+                Term::Group(list) => {
+                    let to = list.terms().to as usize;
+                    let inner = self.labels(i + 1..to, &seen, src, errors);
+                    seen.extend(inner);
+                    i = to;
+                }
+                // arguments are labeled within the rule they are passed to
+                Term::Call(list) => i = list.terms().to as usize,
+                _ => i += 1,
+            }
+        }
+        all.extend(seen.drain(outer.len()..));
+        all
+    }
+
+    /// The arguments of a [`Term::Call`], the index of the first term of
+    /// each
+    pub(crate) fn args(&self, call: List) -> impl Iterator<Item = usize> + '_ {
+        self.items(call.terms.from as usize + 2, call.terms.to as usize)
+    }
+
+    /// The index of every term within `from..to` that isn't nested in
+    /// another, `Term::Or` markers included
+    fn items(&self, from: usize, to: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut i = from;
+        std::iter::from_fn(move || {
+            if i >= to {
+                return None;
+            }
+            let item = i;
+            i = match self.terms[i] {
+                Term::Or(_) => i + 1,
+                term => term.terms().map_or(i + 1, |t| t.to as usize),
+            };
+            Some(item)
+        })
+    }
+
+    /// Reports a second trivia rule, and lexical rules referring to
+    /// syntactic ones
+    fn check_kinds(&mut self, src: &str) {
+        let mut trivia = None::<BSpan>;
+        for rule in self.rules.values().chain(&self.extras) {
+            if rule.kind != RuleKind::Trivia {
+                continue;
+            }
+            match trivia {
+                Some(first) => self.errors.push(Error {
+                    span: rule.name,
+                    kind: ErrorKind::DuplicateTrivia { first },
+                }),
+                None => trivia = Some(rule.name),
+            }
+        }
+        for rule in self.rules.values() {
+            let Some(list) = rule.expr.filter(|_| rule.kind.is_lexical()) else {
+                continue;
+            };
+            for term in &self.terms[list.terms.range()] {
+                let Term::Ident(span) = *term else { continue };
+                let Some(target) = self.rules.get(span.slice(src)) else {
+                    continue;
+                };
+                if target.is_syntactic() {
+                    self.errors.push(Error {
+                        span,
+                        kind: ErrorKind::SyntacticInLexical { rule: target.name },
+                    });
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Rule {
     pub name: BSpan,
+    pub kind: RuleKind,
+    /// The range of [`Cbnf::attributes`] written before the rule
+    pub attributes: TSpan,
+    /// The range of [`Cbnf::params`] after the rule's name
+    pub params: TSpan,
+    /// The range of [`Cbnf::levels`] of a `@prec` rule
+    pub levels: TSpan,
     pub expr: Option<List>,
     /// The span of the entire `Rule`
     ///
@@ -139,6 +394,127 @@ pub struct Rule {
     pub span: BSpan,
 }
 
+impl Rule {
+    /// Whether whitespace and trivia are skipped between the terms of this
+    /// rule
+    ///
+    /// Rules with an empty body are tokens matched outside of the grammar,
+    /// so they aren't syntactic either.
+    #[must_use]
+    pub const fn is_syntactic(&self) -> bool {
+        match self.expr {
+            Some(list) if !list.terms.is_empty() => !self.kind.is_lexical(),
+            _ => false,
+        }
+    }
+}
+
+/// How the input between the terms of a rule is treated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleKind {
+    /// Whitespace and trivia may come before every term
+    #[default]
+    Syntactic,
+    /// `@lexical`, a token whose terms follow each other directly
+    Lexical,
+    /// `@trivia`, a lexical rule skipped before the terms of syntactic rules
+    Trivia,
+    /// `@prec`, a syntactic rule whose alternatives after the first are
+    /// [`Level`]s of binary operators
+    Precedence,
+}
+
+impl RuleKind {
+    #[must_use]
+    pub const fn is_lexical(self) -> bool {
+        matches!(self, Self::Lexical | Self::Trivia)
+    }
+}
+
+/// The attributes that mean something to cbnf, along with what they mean
+///
+/// Any other attribute is still parsed, but warned about.
+pub const ATTRIBUTES: [(&str, &str); 6] = [
+    ("start", "the rule to start parsing from"),
+    (
+        "inline",
+        "the rule is replaced by its body wherever it is used",
+    ),
+    ("skip", "the rule is left out of syntax trees"),
+    ("token", "the rule is a single token of the syntax tree"),
+    ("deprecated", "the rule should no longer be used"),
+    ("prec", "the precedence of the rule, such as `#[prec(2)]`"),
+];
+
+/// A level of binary operators within a `@prec` rule, such as
+/// `| left "+" "-"`
+///
+/// ```text
+/// @prec expr {
+///     atom
+///     | left "+" "-"
+///     | left "*" "/"
+///     | right "^"
+/// }
+/// ```
+///
+/// The levels are listed loosest first, and place their operators between
+/// the operands of the rule, see [`grammar`] for the rules they turn into.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    /// From the associativity to the last operator
+    pub span: BSpan,
+    pub assoc: Assoc,
+    /// The range of [`Cbnf::terms`] after the associativity, a single term
+    /// for every operator
+    pub operators: TSpan,
+}
+
+/// How the operators of a [`Level`] group when used in a row
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assoc {
+    /// `left`, `a - b - c` is `(a - b) - c`
+    #[default]
+    Left,
+    /// `right`, `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+    /// `nonassoc`, `a == b == c` doesn't match
+    NonAssoc,
+}
+
+impl Assoc {
+    /// The associativity named `name`, as written before the operators
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "nonassoc" => Some(Self::NonAssoc),
+            _ => None,
+        }
+    }
+}
+
+/// `#[name]` or `#[name(args)]` before a rule
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute {
+    /// From the `#` to the `]`
+    pub span: BSpan,
+    pub name: BSpan,
+    /// The tokens between the parentheses, if there are any
+    pub args: Option<BSpan>,
+}
+
+impl Attribute {
+    /// Whether this is one of the [`ATTRIBUTES`], `src` being the text it
+    /// was parsed from
+    #[must_use]
+    pub fn is_known(&self, src: &str) -> bool {
+        let name = self.name.slice(src);
+        ATTRIBUTES.iter().any(|(known, _)| *known == name)
+    }
+}
+
 /// An list is a set of Terms seperated by whitespace
 #[derive(Debug, Default, Clone, Copy)]
 pub struct List {
@@ -162,34 +538,131 @@ impl List {
     }
 }
 
+/// What the text of a [`Term::Literal`] matches
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LitKind {
+    /// `"abc"` or `r"abc"`, exactly the decoded text
+    #[default]
+    Str,
+    /// `'a'`, a single char
+    Char,
+    /// `b"\xff"`, the decoded bytes, which may not be valid UTF-8
+    Bytes,
+    /// `i"abc"`, the decoded text in any case
+    Insensitive,
+}
+
+impl LitKind {
+    /// The offset of the contents of `literal` between its quotes, those
+    /// contents, and how their escapes are decoded
+    pub(crate) fn contents(self, literal: &str) -> (usize, &str, Mode) {
+        let (from, to, mode) = match self {
+            Self::Bytes => (2, 1, Mode::ByteStr),
+            Self::Insensitive => (2, 1, Mode::Str),
+            Self::Str => literal.strip_prefix('r').map_or((1, 1, Mode::Str), |raw| {
+                let hashes = raw.len() - raw.trim_start_matches('#').len();
+                (hashes + 2, hashes + 1, Mode::RawStr)
+            }),
+            Self::Char => (1, 1, Mode::Char),
+        };
+        let inner = literal.get(from..literal.len().saturating_sub(to));
+        (from, inner.unwrap_or_default(), mode)
+    }
+
+    /// The value of `literal`, written with this kind, `None` when it has
+    /// invalid escapes
+    #[must_use]
+    pub fn decode(self, literal: &str) -> Option<Value> {
+        let (_, inner, mode) = self.contents(literal);
+        if self == Self::Char {
+            return unescape::unescape_char(inner).ok().map(Value::Char);
+        }
+        let mut text = String::with_capacity(inner.len());
+        let mut valid = true;
+        unescape::unescape_unicode(inner, mode, &mut |_, c| match c {
+            Ok(c) => text.push(c),
+            Err(e) => valid &= !e.is_fatal(),
+        });
+        valid.then(|| match self {
+            Self::Bytes => Value::Bytes(text.chars().map(unescape::byte_from_char).collect()),
+            Self::Str | Self::Char | Self::Insensitive => Value::Text(text),
+        })
+    }
+}
+
+/// What a literal matches, with its escapes decoded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    Text(String),
+    /// From a char literal such as `'a'`
+    Char(char),
+    /// From a byte literal such as `b"\xff"`
+    Bytes(Vec<u8>),
+}
+
 /// A single item within a list
 #[derive(Debug, Clone, Copy)]
 pub enum Term {
     /// ..
     Ident(BSpan),
-    /// ".."
-    Literal(BSpan),
+    /// "..", '.', b".." or i".."
+    Literal(BSpan, LitKind),
     /// Or
     Or(List),
     /// ( .. )
     Group(List),
+    /// & ..
+    ///
+    /// The list holds the single term being looked ahead at.
+    And(List),
+    /// ! ..
+    Not(List),
+    /// .. , ..
+    ///
+    /// The terms on either side follow each other without any whitespace
+    /// between them.
+    Adjacent(BSpan),
+    /// ..<.., ..>
+    ///
+    /// The list holds the name of the parameterized rule as an `Ident`,
+    /// followed by a single term for every argument.
+    Call(List),
+    /// ..: ..
+    ///
+    /// The span is the label's name, the list holds the single term it
+    /// names and spans from the name to the end of that term.
+    Label(BSpan, List),
 }
 
 impl Term {
+    /// The value of this literal, `None` for other terms and for literals
+    /// with invalid escapes
+    #[must_use]
+    pub fn value(&self, src: &str) -> Option<Value> {
+        match self {
+            Self::Literal(span, kind) => kind.decode(span.slice(src)),
+            _ => None,
+        }
+    }
+
     #[must_use]
     pub const fn span(&self) -> BSpan {
         use Term::*;
         match self {
-            Literal(span) | Ident(span) => *span,
-            Or(list) | Group(list) => list.span,
+            Literal(span, _) | Ident(span) | Adjacent(span) => *span,
+            Or(list) | Group(list) | And(list) | Not(list) | Call(list) | Label(_, list) => {
+                list.span
+            }
         }
     }
     #[must_use]
     pub const fn terms(&self) -> Option<TSpan> {
         use Term::*;
         match self {
-            Or(list) | Group(list) => Some(list.terms),
-            Literal(_) | Ident(_) => None,
+            Or(list) | Group(list) | And(list) | Not(list) | Call(list) | Label(_, list) => {
+                Some(list.terms)
+            }
+            Literal(..) | Ident(_) | Adjacent(_) => None,
         }
     }
 }
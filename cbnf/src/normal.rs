@@ -0,0 +1,553 @@
+//! normal forms of a [`Grammar`]
+//!
+//! [`Bnf`] is a grammar in plain BNF: every rule is a list of productions,
+//! each a sequence of terminals and rules. Groups with alternatives become
+//! helper rules of their own, named after the rule they are written in like
+//! `expr#1`. As with [`crate::enumerate`], rules with an empty body are
+//! opaque [`Terminal::Builtin`]s, while lookahead predicates and `,` are
+//! taken to always hold and left out.
+//!
+//! [`Bnf::cnf`] converts to Chomsky normal form, where every production is
+//! either two rules or a single terminal, and [`Bnf::gnf`] to Greibach
+//! normal form, where every production is a terminal followed by rules. In
+//! both only the start rule may have an empty production, and then no
+//! production uses the start rule. They also leave out every rule that
+//! derives no sentence or can't be reached from the start.
+//!
+//! Every rule and production keeps the span of the source it comes from, so
+//! diagnostics about a normal form can still point at the grammar.
+//!
+//! ```
+//! use cbnf::{grammar::Grammar, normal::Bnf, Cbnf};
+//!
+//! let src = r#"pair { "(" ("a" | "b") ")" }"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! let bnf = Bnf::new(&grammar, 0);
+//! assert_eq!(
+//!     bnf.to_string(),
+//!     "pair ::= \"(\" pair#1 \")\"\npair#1 ::= \"a\" | \"b\"\n",
+//! );
+//! assert_eq!(bnf.rules[1].origin.slice(src), r#"("a" | "b")"#);
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    enumerate::Terminal,
+    grammar::{Grammar, Node, NodeKind, RuleId},
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// A single symbol of a [`Production`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Symbol {
+    Terminal(Terminal),
+    /// The index of a rule within [`Bnf::rules`]
+    Rule(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Production {
+    /// Empty for a production that matches nothing
+    pub symbols: Vec<Symbol>,
+    /// The alternative this production comes from
+    pub origin: BSpan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BnfRule {
+    pub name: String,
+    /// The rule of the grammar this is, `None` for helper rules
+    pub rule: Option<RuleId>,
+    /// The name of the rule of the grammar, or the source a helper rule was
+    /// made for
+    pub origin: BSpan,
+    pub productions: Vec<Production>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bnf<'g> {
+    pub grammar: &'g Grammar,
+    pub rules: Vec<BnfRule>,
+    /// The index of the rule sentences are derived from
+    pub start: usize,
+}
+
+impl<'g> Bnf<'g> {
+    /// The rules of `grammar` in plain BNF, deriving sentences from `start`
+    ///
+    /// Parameterized rules are left out, their instances take their place.
+    #[must_use]
+    pub fn new(grammar: &'g Grammar, start: RuleId) -> Self {
+        let mut bnf = Self {
+            grammar,
+            rules: Vec::new(),
+            start: 0,
+        };
+        let mut ids = vec![None; grammar.rules.len()];
+        for (id, rule) in grammar.rules.iter().enumerate() {
+            if rule.body.is_some() && rule.params.is_empty() {
+                ids[id] = Some(bnf.rules.len());
+                bnf.push(rule.name.clone(), Some(id), rule.span, Vec::new());
+            }
+        }
+        bnf.start = ids[start].unwrap_or_else(|| {
+            // a builtin start only matches itself
+            let rule = &grammar.rules[start];
+            let origin = rule.span;
+            let productions = match rule.body {
+                None => vec![Production {
+                    symbols: vec![Symbol::Terminal(Terminal::Builtin(start))],
+                    origin,
+                }],
+                Some(_) => Vec::new(),
+            };
+            bnf.push(rule.name.clone(), Some(start), origin, productions)
+        });
+        for (id, rule) in grammar.rules.iter().enumerate() {
+            if let (Some(owner), Some(body)) = (ids[id], &rule.body) {
+                bnf.rules[owner].productions = bnf.alternatives(&ids, body, owner);
+            }
+        }
+        bnf
+    }
+
+    /// The grammar in Chomsky normal form, see the [module docs](self)
+    #[must_use]
+    pub fn cnf(&self) -> Self {
+        let mut bnf = self.clone();
+        bnf.isolate_start();
+        bnf.lift_terminals();
+        bnf.binarize();
+        bnf.remove_empty();
+        bnf.remove_units();
+        bnf.trim();
+        bnf
+    }
+
+    /// The grammar in Greibach normal form, see the [module docs](self)
+    ///
+    /// Left recursion is removed by helper rules, and the number of
+    /// productions may grow exponentially with the number of rules.
+    #[must_use]
+    pub fn gnf(&self) -> Self {
+        let mut bnf = self.cnf();
+        let len = bnf.rules.len();
+        // afterwards a rule only starts with later rules
+        for i in 0..len {
+            for j in 0..i {
+                bnf.substitute(i, |k| k == j);
+            }
+            bnf.remove_left_recursion(i);
+        }
+        // the last rule only starts with terminals, then the one before it
+        // and so on, the helpers only start with the rules before them
+        for i in (0..len).rev().chain(len..bnf.rules.len()) {
+            bnf.substitute(i, |_| true);
+        }
+        bnf.trim();
+        bnf
+    }
+
+    /// The text of `symbols`, builtins are written as `<name>`
+    #[must_use]
+    pub fn show(&self, symbols: &[Symbol]) -> String {
+        if symbols.is_empty() {
+            return "ε".into();
+        }
+        let symbols: Vec<_> = symbols
+            .iter()
+            .map(|s| match s {
+                Symbol::Terminal(Terminal::Literal(lit)) => format!("{lit:?}"),
+                Symbol::Terminal(Terminal::Insensitive(lit)) => format!("i{lit:?}"),
+                Symbol::Terminal(Terminal::Builtin(id)) => format!("<{}>", self.grammar.name(*id)),
+                Symbol::Rule(rule) => self.rules[*rule].name.clone(),
+            })
+            .collect();
+        symbols.join(" ")
+    }
+
+    fn push(
+        &mut self,
+        name: String,
+        rule: Option<RuleId>,
+        origin: BSpan,
+        productions: Vec<Production>,
+    ) -> usize {
+        self.rules.push(BnfRule {
+            name,
+            rule,
+            origin,
+            productions,
+        });
+        self.rules.len() - 1
+    }
+
+    /// Pushes a helper rule named after the rule `owner`
+    fn helper(&mut self, owner: usize, origin: BSpan, productions: Vec<Production>) -> usize {
+        let base = self.rules[owner].name.split('#').next().unwrap_or_default();
+        // at most every rule takes one of these names
+        let name = (1..=self.rules.len() + 1)
+            .map(|n| format!("{base}#{n}"))
+            .find(|name| self.rules.iter().all(|r| r.name != *name))
+            .expect("some name is free");
+        self.push(name, None, origin, productions)
+    }
+
+    /// The productions of `node`, the body of the rule `owner`
+    fn alternatives(
+        &mut self,
+        ids: &[Option<usize>],
+        node: &Node,
+        owner: usize,
+    ) -> Vec<Production> {
+        if let NodeKind::Alt(_, nodes) = &node.kind {
+            return nodes
+                .iter()
+                .flat_map(|n| self.alternatives(ids, n, owner))
+                .collect();
+        }
+        let mut symbols = Vec::new();
+        self.symbols(ids, node, owner, &mut symbols)
+            .map(|()| Production {
+                symbols,
+                origin: node.span,
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Pushes the symbols of `node`, `None` when it never matches
+    fn symbols(
+        &mut self,
+        ids: &[Option<usize>],
+        node: &Node,
+        owner: usize,
+        out: &mut Vec<Symbol>,
+    ) -> Option<()> {
+        match &node.kind {
+            NodeKind::Nil | NodeKind::And(_) | NodeKind::Not(_) | NodeKind::Adjacent => {}
+            NodeKind::Literal(lit) | NodeKind::Insensitive(lit) if lit.is_empty() => {}
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                out.push(Symbol::Terminal(
+                    Terminal::of(&node.kind).expect("a literal"),
+                ));
+            }
+            NodeKind::Rule(id) => match (ids[*id], &self.grammar.rules[*id].body) {
+                (Some(rule), _) => out.push(Symbol::Rule(rule)),
+                (None, None) => out.push(Symbol::Terminal(Terminal::Builtin(*id))),
+                (None, Some(_)) => return None,
+            },
+            NodeKind::Unknown(_) => return None,
+            NodeKind::Seq(nodes) => {
+                for node in nodes {
+                    self.symbols(ids, node, owner, out)?;
+                }
+            }
+            NodeKind::Alt(..) => {
+                let helper = self.helper(owner, node.span, Vec::new());
+                self.rules[helper].productions = self.alternatives(ids, node, owner);
+                out.push(Symbol::Rule(helper));
+            }
+        }
+        Some(())
+    }
+
+    /// Gives the start a rule of its own when a production uses it
+    fn isolate_start(&mut self) {
+        let start = Symbol::Rule(self.start);
+        let used = self
+            .rules
+            .iter()
+            .flat_map(|r| &r.productions)
+            .any(|p| p.symbols.contains(&start));
+        if used {
+            let origin = self.rules[self.start].origin;
+            let production = Production {
+                symbols: vec![start],
+                origin,
+            };
+            self.start = self.helper(self.start, origin, vec![production]);
+        }
+    }
+
+    /// Replaces the terminals of productions with more than one symbol by
+    /// rules matching just that terminal
+    fn lift_terminals(&mut self) {
+        let mut lifted = HashMap::new();
+        for r in 0..self.rules.len() {
+            for p in 0..self.rules[r].productions.len() {
+                let production = &self.rules[r].productions[p];
+                if production.symbols.len() < 2 {
+                    continue;
+                }
+                let origin = production.origin;
+                for s in 0..production.symbols.len() {
+                    let Symbol::Terminal(terminal) = &self.rules[r].productions[p].symbols[s]
+                    else {
+                        continue;
+                    };
+                    let rule = *lifted
+                        .entry(terminal.clone())
+                        .or_insert_with_key(|t| self.terminal(t.clone(), origin));
+                    self.rules[r].productions[p].symbols[s] = Symbol::Rule(rule);
+                }
+            }
+        }
+    }
+
+    /// Pushes a rule matching just `terminal`, named after it like `T"+"`
+    fn terminal(&mut self, terminal: Terminal, origin: BSpan) -> usize {
+        let name = format!("T{}", self.show(&[Symbol::Terminal(terminal.clone())]));
+        let production = Production {
+            symbols: vec![Symbol::Terminal(terminal)],
+            origin,
+        };
+        self.push(name, None, origin, vec![production])
+    }
+
+    /// Splits productions with more than two symbols into a chain of helper
+    /// rules, every link holding two symbols
+    fn binarize(&mut self) {
+        for r in 0..self.rules.len() {
+            for p in 0..self.rules[r].productions.len() {
+                let (mut rule, mut production) = (r, p);
+                loop {
+                    let link = &mut self.rules[rule].productions[production];
+                    if link.symbols.len() <= 2 {
+                        break;
+                    }
+                    let origin = link.origin;
+                    let symbols = link.symbols.split_off(1);
+                    let helper = self.helper(r, origin, vec![Production { symbols, origin }]);
+                    self.rules[rule].productions[production]
+                        .symbols
+                        .push(Symbol::Rule(helper));
+                    (rule, production) = (helper, 0);
+                }
+            }
+        }
+    }
+
+    /// Which rules derive the empty sentence
+    fn nullable(&self) -> Vec<bool> {
+        let mut nullable = vec![false; self.rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (r, rule) in self.rules.iter().enumerate() {
+                if !nullable[r]
+                    && rule.productions.iter().any(|p| {
+                        p.symbols
+                            .iter()
+                            .all(|s| matches!(s, Symbol::Rule(s) if nullable[*s]))
+                    })
+                {
+                    nullable[r] = true;
+                    changed = true;
+                }
+            }
+        }
+        nullable
+    }
+
+    /// Removes empty productions, adding every way to leave out the
+    /// nullable rules of a production instead, the start keeps matching
+    /// nothing if it did
+    fn remove_empty(&mut self) {
+        let nullable = self.nullable();
+        for (r, rule) in self.rules.iter_mut().enumerate() {
+            let mut productions = Vec::new();
+            for production in &rule.productions {
+                let mut variants = vec![Vec::new()];
+                for symbol in &production.symbols {
+                    let kept: Vec<_> = variants
+                        .iter()
+                        .map(|v: &Vec<Symbol>| {
+                            [v.as_slice(), std::slice::from_ref(symbol)].concat()
+                        })
+                        .collect();
+                    match symbol {
+                        Symbol::Rule(s) if nullable[*s] => variants.extend(kept),
+                        _ => variants = kept,
+                    }
+                }
+                productions.extend(
+                    variants
+                        .into_iter()
+                        .filter(|symbols| !symbols.is_empty())
+                        .map(|symbols| Production {
+                            symbols,
+                            origin: production.origin,
+                        }),
+                );
+            }
+            if r == self.start && nullable[r] {
+                productions.push(Production {
+                    symbols: Vec::new(),
+                    origin: rule.origin,
+                });
+            }
+            rule.productions = dedup(productions);
+        }
+    }
+
+    /// Replaces productions that are a single rule by the productions of
+    /// that rule
+    fn remove_units(&mut self) {
+        let rules = self.rules.clone();
+        for (r, rule) in self.rules.iter_mut().enumerate() {
+            let mut units = vec![r];
+            let mut productions = Vec::new();
+            let mut i = 0;
+            while let Some(&unit) = units.get(i) {
+                i += 1;
+                for production in &rules[unit].productions {
+                    match production.symbols.as_slice() {
+                        [Symbol::Rule(next)] if !units.contains(next) => units.push(*next),
+                        [Symbol::Rule(_)] => {}
+                        _ => productions.push(production.clone()),
+                    }
+                }
+            }
+            rule.productions = dedup(productions);
+        }
+    }
+
+    /// Replaces the productions of `rule` starting with a rule `first`
+    /// accepts by the productions of that rule followed by the rest
+    fn substitute(&mut self, rule: usize, first: impl Fn(usize) -> bool) {
+        let mut productions = Vec::new();
+        for production in &self.rules[rule].productions {
+            match production.symbols.first() {
+                Some(Symbol::Rule(lead)) if first(*lead) => {
+                    let rest = &production.symbols[1..];
+                    productions.extend(self.rules[*lead].productions.iter().map(|p| Production {
+                        symbols: [p.symbols.as_slice(), rest].concat(),
+                        origin: production.origin,
+                    }));
+                }
+                _ => productions.push(production.clone()),
+            }
+        }
+        self.rules[rule].productions = dedup(productions);
+    }
+
+    /// Turns `a ::= a x | y` into `a ::= y | y a#1` and `a#1 ::= x | x a#1`
+    fn remove_left_recursion(&mut self, rule: usize) {
+        let (recursive, rest): (Vec<_>, Vec<_>) = self.rules[rule]
+            .productions
+            .drain(..)
+            .partition(|p| p.symbols.first() == Some(&Symbol::Rule(rule)));
+        if recursive.is_empty() {
+            self.rules[rule].productions = rest;
+            return;
+        }
+        let origin = self.rules[rule].origin;
+        let helper = self.helper(rule, origin, Vec::new());
+        let with = |productions: Vec<Production>| {
+            let tails = productions.iter().map(|p| Production {
+                symbols: [p.symbols.as_slice(), &[Symbol::Rule(helper)]].concat(),
+                origin: p.origin,
+            });
+            let tails: Vec<_> = tails.collect();
+            productions.into_iter().chain(tails).collect()
+        };
+        let recursive = recursive
+            .into_iter()
+            .map(|p| Production {
+                symbols: p.symbols[1..].to_vec(),
+                origin: p.origin,
+            })
+            .collect();
+        self.rules[rule].productions = with(rest);
+        self.rules[helper].productions = with(recursive);
+    }
+
+    /// Removes the productions using rules that derive no sentence, then
+    /// the rules that can't be reached from the start
+    fn trim(&mut self) {
+        let mut generating = vec![false; self.rules.len()];
+        let derives = |p: &Production, generating: &[bool]| {
+            p.symbols
+                .iter()
+                .all(|s| !matches!(s, Symbol::Rule(r) if !generating[*r]))
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (r, rule) in self.rules.iter().enumerate() {
+                if !generating[r] && rule.productions.iter().any(|p| derives(p, &generating)) {
+                    generating[r] = true;
+                    changed = true;
+                }
+            }
+        }
+        for rule in &mut self.rules {
+            rule.productions.retain(|p| derives(p, &generating));
+        }
+        let mut reachable = vec![false; self.rules.len()];
+        reachable[self.start] = true;
+        let mut queue = vec![self.start];
+        while let Some(r) = queue.pop() {
+            for symbol in self.rules[r].productions.iter().flat_map(|p| &p.symbols) {
+                if let Symbol::Rule(next) = symbol {
+                    if !reachable[*next] {
+                        reachable[*next] = true;
+                        queue.push(*next);
+                    }
+                }
+            }
+        }
+        let mut ids = Vec::with_capacity(self.rules.len());
+        let mut kept = 0;
+        for &reachable in &reachable {
+            ids.push(kept);
+            kept += usize::from(reachable);
+        }
+        let mut r = 0;
+        self.rules.retain(|_| {
+            r += 1;
+            reachable[r - 1]
+        });
+        for symbol in self
+            .rules
+            .iter_mut()
+            .flat_map(|r| &mut r.productions)
+            .flat_map(|p| &mut p.symbols)
+        {
+            if let Symbol::Rule(r) = symbol {
+                *r = ids[*r];
+            }
+        }
+        self.start = ids[self.start];
+    }
+}
+
+/// Keeps the first of productions with the same symbols
+fn dedup(mut productions: Vec<Production>) -> Vec<Production> {
+    let mut seen = HashSet::new();
+    productions.retain(|p| seen.insert(p.symbols.clone()));
+    productions
+}
+
+impl fmt::Display for Bnf<'_> {
+    /// Every rule on a line of its own, as `name ::= a b | c`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rule in &self.rules {
+            write!(f, "{} ::=", rule.name)?;
+            for (i, production) in rule.productions.iter().enumerate() {
+                let bar = if i == 0 { "" } else { " |" };
+                write!(f, "{bar} {}", self.show(&production.symbols))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    enumerate::{Enumerator, Terminal},
+    grammar::test::grammar,
+    normal::{Bnf, Symbol},
+};
+
+/// Every sentence of the start of `bnf` with at most `max_len` terminals
+fn sentences(bnf: &Bnf, max_len: usize) -> BTreeSet<Vec<Terminal>> {
+    let mut sets = vec![BTreeSet::new(); bnf.rules.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (r, rule) in bnf.rules.iter().enumerate() {
+            for production in &rule.productions {
+                let mut partial = BTreeSet::from([Vec::new()]);
+                for symbol in &production.symbols {
+                    let next: Vec<Vec<Terminal>> = match symbol {
+                        Symbol::Terminal(t) => vec![vec![t.clone()]],
+                        Symbol::Rule(s) => sets[*s].iter().cloned().collect(),
+                    };
+                    partial = partial
+                        .iter()
+                        .flat_map(|p| next.iter().map(move |n| [p.as_slice(), n].concat()))
+                        .filter(|p| p.len() <= max_len)
+                        .collect();
+                }
+                for sentence in partial {
+                    changed |= sets[r].insert(sentence);
+                }
+            }
+        }
+    }
+    sets.swap_remove(bnf.start)
+}
+
+/// Checks that every normal form of `src` derives the same sentences as
+/// the grammar
+fn same_language(src: &str, max_len: usize) {
+    let grammar = grammar(src);
+    let expected: BTreeSet<_> = Enumerator::new(&grammar, 0, max_len)
+        .map(|s| s.terminals)
+        .collect();
+    let bnf = Bnf::new(&grammar, 0);
+    assert_eq!(sentences(&bnf, max_len), expected, "bnf");
+    assert_eq!(sentences(&bnf.cnf(), max_len), expected, "cnf");
+    assert_eq!(sentences(&bnf.gnf(), max_len), expected, "gnf");
+}
+
+fn is_cnf(bnf: &Bnf) -> bool {
+    bnf.rules.iter().enumerate().all(|(r, rule)| {
+        rule.productions.iter().all(|p| match p.symbols.as_slice() {
+            [] => r == bnf.start,
+            [Symbol::Terminal(_)] => true,
+            [Symbol::Rule(a), Symbol::Rule(b)] => *a != bnf.start && *b != bnf.start,
+            _ => false,
+        })
+    })
+}
+
+fn is_gnf(bnf: &Bnf) -> bool {
+    bnf.rules.iter().enumerate().all(|(r, rule)| {
+        rule.productions
+            .iter()
+            .all(|p| match p.symbols.split_first() {
+                None => r == bnf.start,
+                Some((Symbol::Terminal(_), rest)) => rest
+                    .iter()
+                    .all(|s| matches!(s, Symbol::Rule(s) if *s != bnf.start)),
+                Some(_) => false,
+            })
+    })
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, form: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let bnf = Bnf::new(&grammar, 0);
+    let bnf = match form {
+        "cnf" => bnf.cnf(),
+        "gnf" => bnf.gnf(),
+        _ => bnf,
+    };
+    expect.assert_eq(&bnf.to_string());
+}
+
+#[test]
+fn bnf() {
+    check(
+        r#"call { ident "(" (ident ("," | ";") | nil) ")" !"x" } ident {}"#,
+        "bnf",
+        expect![[r#"
+            call ::= <ident> "(" call#1 ")"
+            call#1 ::= <ident> call#2 | ε
+            call#2 ::= "," | ";"
+        "#]],
+    );
+}
+
+#[test]
+fn origins() {
+    let src = r#"a { "x" ("y" | "z" "w") } b { "v" | "u" }"#;
+    let grammar = grammar(src);
+    let bnf = Bnf::new(&grammar, 0);
+    let origins: Vec<_> = bnf
+        .rules
+        .iter()
+        .map(|r| {
+            let productions: Vec<_> = r.productions.iter().map(|p| p.origin.slice(src)).collect();
+            (r.name.as_str(), r.origin.slice(src), productions)
+        })
+        .collect();
+    assert_eq!(
+        origins,
+        [
+            ("a", "a", vec![r#"{ "x" ("y" | "z" "w") }"#]),
+            ("b", "b", vec![r#""v""#, r#""u""#]),
+            ("a#1", r#"("y" | "z" "w")"#, vec![r#""y""#, r#""z" "w""#]),
+        ]
+    );
+    let cnf = bnf.cnf();
+    // the terminal rules point at the production they were lifted from
+    let lifted = cnf.rules.iter().find(|r| r.name == r#"T"z""#).unwrap();
+    assert_eq!(lifted.origin.slice(src), r#""z" "w""#);
+}
+
+#[test]
+fn cnf() {
+    let src = r#"e { e "+" t | t } t { "x" | "(" e ")" }"#;
+    check(
+        src,
+        "cnf",
+        expect![[r#"
+            e ::= e e#2 | "x" | T"(" t#1
+            t ::= "x" | T"(" t#1
+            e#1 ::= e e#2 | "x" | T"(" t#1
+            T"+" ::= "+"
+            T"(" ::= "("
+            T")" ::= ")"
+            e#2 ::= T"+" t
+            t#1 ::= e T")"
+        "#]],
+    );
+    same_language(src, 7);
+    let grammar = grammar(src);
+    assert!(is_cnf(&Bnf::new(&grammar, 0).cnf()));
+}
+
+#[test]
+fn gnf() {
+    let src = r#"e { e "+" t | t } t { "x" | "(" e ")" }"#;
+    check(
+        src,
+        "gnf",
+        expect![[r#"
+            t ::= "x" | "(" t#1
+            e#1 ::= "x" e#2 | "(" t#1 e#2 | "x" e#3 e#2 | "(" t#1 e#3 e#2 | "x" | "(" t#1
+            T")" ::= ")"
+            e#2 ::= "+" t
+            t#1 ::= "x" T")" | "(" t#1 T")" | "x" e#3 T")" | "(" t#1 e#3 T")"
+            e#3 ::= "+" t | "+" t e#3
+        "#]],
+    );
+    let grammar = grammar(src);
+    assert!(is_gnf(&Bnf::new(&grammar, 0).gnf()));
+}
+
+#[test]
+fn nullable() {
+    let src = r#"list { item list | nil } item { "a" | "b" opt } opt { "?" | nil }"#;
+    check(
+        src,
+        "cnf",
+        expect![[r#"
+            list ::= item list | "a" | T"b" opt | "b"
+            item ::= "a" | T"b" opt | "b"
+            opt ::= "?"
+            list#1 ::= ε | item list | "a" | T"b" opt | "b"
+            T"b" ::= "b"
+        "#]],
+    );
+    same_language(src, 5);
+    let grammar = grammar(src);
+    let bnf = Bnf::new(&grammar, 0);
+    assert!(is_cnf(&bnf.cnf()));
+    assert!(is_gnf(&bnf.gnf()));
+}
+
+#[test]
+fn useless() {
+    let src = r#"a { "x" | b | "y" c } b { b "z" } c { "w" } d { "v" }"#;
+    check(
+        src,
+        "cnf",
+        expect![[r#"
+            a ::= "x" | T"y" c
+            c ::= "w"
+            T"y" ::= "y"
+        "#]],
+    );
+    same_language(src, 4);
+}
+
+#[test]
+fn indirect_left_recursion() {
+    let src = r#"a { b "x" | "y" } b { c "z" | a "w" } c { a "v" | "u" } ident {}"#;
+    same_language(src, 7);
+    let grammar = grammar(src);
+    assert!(is_gnf(&Bnf::new(&grammar, 0).gnf()));
+}
+
+#[test]
+fn builtins_and_precedence() {
+    let src = r#"@prec expr { ident | left "+" | right "^" } ident {}"#;
+    same_language(src, 5);
+    let grammar = grammar(src);
+    let bnf = Bnf::new(&grammar, 0);
+    assert!(is_cnf(&bnf.cnf()));
+    assert!(is_gnf(&bnf.gnf()));
+}
@@ -1,11 +1,11 @@
 #![allow(clippy::cast_possible_truncation)]
 
 use crate::{
-    lexer::{Base, Cursor, LexKind, LiteralKind, *},
+    lexer::{unescape, Base, Cursor, LexKind, LiteralKind, *},
     parser::error::{Error, InvalidLiteral},
     span::{BSpan, TSpan},
     util::*,
-    Comment, DocComment, List, Rule, Term,
+    Assoc, Attribute, Comment, DocComment, Level, List, LitKind, Rule, RuleKind, Term, Value,
 };
 
 use self::error::ErrorKind;
@@ -24,9 +24,12 @@ pub struct Parser<'a> {
     pub(crate) docs: Vec<DocComment>,
     pub(crate) errors: Vec<Error>,
     pub(crate) terms: Vec<Term>,
+    pub(crate) attributes: Vec<Attribute>,
+    pub(crate) params: Vec<BSpan>,
+    pub(crate) levels: Vec<Level>,
 }
 
-impl<'a> Parser<'a> {
+impl Parser<'_> {
     fn advance(&mut self) -> (Lexeme, BSpan) {
         self.curr.take().unwrap_or_else(|| {
             let token = self.cursor.advance();
@@ -63,7 +66,7 @@ impl<'a> Parser<'a> {
             if !terminated {
                 self.push_err(Error {
                     span: self.span(token),
-                    kind: ErrorKind::Unterminated,
+                    kind: ErrorKind::UnterminatedComment,
                 });
             }
             self.push_comment(doc_style, token);
@@ -73,10 +76,13 @@ impl<'a> Parser<'a> {
             LineComment { .. } | BlockComment { .. } | Whitespace
         )
     }
-    fn err_expected(&mut self, span: impl Into<AsBSpan>, expected: impl Into<Box<[LexKind]>>) {
+    fn err_expected(&mut self, found: LexKind, span: BSpan, expected: impl Into<Box<[LexKind]>>) {
         self.push_err(Error {
-            span: self.span(span),
-            kind: ErrorKind::Expected(expected.into()),
+            span,
+            kind: ErrorKind::Unexpected {
+                found,
+                expected: expected.into(),
+            },
         });
     }
     fn push_err(&mut self, err: impl Into<Error>) {
@@ -114,8 +120,10 @@ impl<'a> Parser<'a> {
     }
 }
 
-pub const LIST_EXPECTED: [LexKind; 5] = [OpenParen, Ident, Or, LITERAL, CloseBrace];
-pub const RULE_EXPECTED: [LexKind; 1] = [Ident];
+pub const LIST_EXPECTED: [LexKind; 8] =
+    [OpenParen, Ident, Or, And, Bang, Comma, LITERAL, CloseBrace];
+pub const RULE_EXPECTED: [LexKind; 3] = [Ident, At, Pound];
+pub const ARG_EXPECTED: [LexKind; 4] = [OpenParen, Ident, LITERAL, Gt];
 
 impl<'a> Parser<'a> {
     #[must_use]
@@ -127,38 +135,81 @@ impl<'a> Parser<'a> {
             docs: Vec::new(),
             errors: Vec::new(),
             terms: Vec::new(),
+            attributes: Vec::new(),
+            params: Vec::new(),
+            levels: Vec::new(),
         }
     }
 
     #[must_use]
     pub fn next_rule(&mut self) -> Option<Rule> {
-        let (name, open) = loop {
-            let span = self.until_ident()?;
+        let (kind, from, name, attributes, params, open) = loop {
+            let (kind, from, span, attributes) = self.until_ident()?;
+            let params = self.params();
             if let Some(open) = self.rule_opener(span) {
-                break (span, open);
+                break (kind, from, span, attributes, params, open);
             }
+            self.attributes.truncate(attributes.from as usize);
+            self.params.truncate(params.from as usize);
+        };
+        let (close, terms) = self.list(open, CloseBrace);
+        self.predicate_rule(terms);
+        let levels = match kind {
+            RuleKind::Precedence => self.levels(name, terms),
+            _ => TSpan::empty(self.levels.len() as u32),
         };
-        let (close, terms) = self.list(open);
         Some(Rule {
             name,
+            kind,
+            attributes,
+            params,
+            levels,
             expr: Some(List {
                 span: (open, close).into(),
                 terms,
             }),
-            span: name.to(close),
+            span: BSpan::new(from, close),
         })
     }
 
-    fn list(&mut self, open: u32) -> (u32, TSpan) {
+    /// Parses terms up to `close`, either the `}` of a rule or the `)` of
+    /// a group passed as an argument
+    fn list(&mut self, open: u32, close: LexKind) -> (u32, TSpan) {
         let first = self.terms.len() as u32;
         let mut groups = Vec::new();
         let mut ors = Vec::new();
+        let mut predicates = Vec::new();
+        // a `,` still waiting on the term after it
+        let mut comma = None;
+        // whether the last token ended a term
+        let mut after_term = false;
         let (span, eof) = loop {
             let (token, span) = self.until_non_wc();
             match token.kind {
+                CloseParen if close == CloseParen && groups.is_empty() => break (span, false),
+                // the end of the rule or call the argument is in
+                CloseBrace | Gt if close == CloseParen => {
+                    self.reverse(token);
+                    break (span, true);
+                }
                 CloseBrace => break (span, false),
                 Eof => break (span, true),
+                And | Bang => {
+                    (comma, after_term) = (None, false);
+                    predicates.push(self.terms.len() as u32);
+                    let list = List::new(span, TSpan::empty(self.terms.len() as u32));
+                    self.terms.push(match token.kind {
+                        And => Term::And(list),
+                        _ => Term::Not(list),
+                    });
+                }
+                Comma if after_term => {
+                    (comma, after_term) = (Some(span), false);
+                    self.terms.push(Term::Adjacent(span));
+                }
+                Comma => self.push_err((span, ErrorKind::DanglingComma)),
                 OpenParen => {
+                    (comma, after_term) = (None, false);
                     groups.push(self.terms.len() as u32);
                     self.terms.push(Term::Group(List::new(
                         span,
@@ -166,9 +217,16 @@ impl<'a> Parser<'a> {
                     )));
                 }
                 CloseParen if !groups.is_empty() => {
+                    self.dangling_comma(&mut comma);
+                    after_term = true;
+                    self.close_predicates(&mut predicates, &groups);
                     self.pop_group(&mut ors, &mut groups, span.to);
+                    self.close_predicates(&mut predicates, &groups);
                 }
                 Or => {
+                    self.dangling_comma(&mut comma);
+                    after_term = false;
+                    self.close_predicates(&mut predicates, &groups);
                     self.handle_or(&mut ors, &groups);
                     ors.push(self.terms.len() as u32);
                     self.terms.push(Term::Or(List::new(
@@ -176,34 +234,218 @@ impl<'a> Parser<'a> {
                         TSpan::empty(self.terms.len() as u32),
                     )));
                 }
-                Ident => self.terms.push(Term::Ident(span)),
-                Literal { kind, .. } if kind.is_string() => {
-                    if !kind.terminated() {
-                        self.push_err(Error {
-                            span: self.span(span),
-                            kind: ErrorKind::InvalidLit(InvalidLiteral::Unterminated),
-                        });
+                Ident => {
+                    (comma, after_term) = (None, true);
+                    let (next, colon) = self.until_non_wc();
+                    if next.kind == Colon {
+                        // the label waits on its term like a predicate
+                        after_term = false;
+                        let label = self.terms.len() as u32;
+                        predicates.push(label);
+                        let list = List::new(span.to(colon.to), TSpan::empty(label));
+                        self.terms.push(Term::Label(span, list));
+                    } else {
+                        self.reverse(next);
+                        self.ident(span);
+                        self.close_predicates(&mut predicates, &groups);
                     }
-                    self.terms.push(Term::Literal(span));
+                }
+                Literal { kind, .. } if kind.is_string() => {
+                    (comma, after_term) = (None, true);
+                    self.string(kind, span);
+                    self.close_predicates(&mut predicates, &groups);
                 }
                 Literal { .. } => self.push_err((InvalidLiteral::Numeric, span)),
+                CloseParen => self.push_err((span, ErrorKind::StrayCloseParen)),
+                InvalidIdent => self.push_err((span, ErrorKind::InvalidIdent)),
                 // TODO: also add a CloseParen item to the EXPECTED when there
                 // are unclosed groups
-                _ => self.err_expected(span, LIST_EXPECTED),
+                kind => self.err_expected(kind, span, LIST_EXPECTED),
             }
         };
+        self.dangling_comma(&mut comma);
+        self.close_predicates(&mut predicates, &groups);
         self.handle_or(&mut ors, &groups);
         self.handle_unclosed(groups, span);
+        // alternatives cut off by an unclosed group end with it
+        while self.handle_or(&mut ors, &[]) {}
+        self.close_predicates(&mut predicates, &[]);
         if eof {
             self.push_err(Error {
                 span: span.from(open),
-                kind: ErrorKind::UnclosedRule,
+                kind: match close {
+                    CloseBrace => ErrorKind::UnclosedRule,
+                    _ => ErrorKind::Unterminated,
+                },
             });
         }
-        assert!(ors.is_empty(), "or backlog not empty: {ors:#?}");
         (span.to, (first, self.terms.len() as u32).into())
     }
 
+    /// Pushes an ident, or a call when it is followed by `<`
+    fn ident(&mut self, span: BSpan) {
+        let (token, _) = self.until_non_wc();
+        if token.kind == Lt {
+            self.call(span);
+        } else {
+            self.reverse(token);
+            self.terms.push(Term::Ident(span));
+        }
+    }
+
+    fn string(&mut self, kind: LiteralKind, span: BSpan) {
+        if !kind.terminated() {
+            self.push_err(Error {
+                span: self.span(span),
+                kind: ErrorKind::InvalidLit(InvalidLiteral::Unterminated),
+            });
+        }
+        let lit = match kind {
+            LiteralKind::ByteStr { .. } => LitKind::Bytes,
+            LiteralKind::InsensitiveStr { .. } => LitKind::Insensitive,
+            LiteralKind::Char { .. } => LitKind::Char,
+            _ => LitKind::Str,
+        };
+        if kind.terminated() {
+            self.escapes(span, lit);
+            if let Some(Value::Bytes(bytes)) = lit.decode(self.slice(span)) {
+                if std::str::from_utf8(&bytes).is_err() {
+                    self.push_err((span, ErrorKind::NonUtf8Bytes));
+                }
+            }
+        }
+        self.terms.push(Term::Literal(span, lit));
+    }
+
+    /// Reports every invalid escape of the literal at `span`, each at the
+    /// escape itself
+    fn escapes(&mut self, span: BSpan, kind: LitKind) {
+        let (offset, inner, mode) = kind.contents(self.slice(span));
+        let from = span.from + offset as u32;
+        if kind == LitKind::Char {
+            // the error is about the whole char, or its absence
+            if let Err(error) = unescape::unescape_char(inner) {
+                let at = match inner.len() {
+                    0 => span,
+                    len => BSpan::new(from, from + len as u32),
+                };
+                self.push_err(Error {
+                    span: at,
+                    kind: ErrorKind::InvalidEscape {
+                        error,
+                        literal: span,
+                    },
+                });
+            }
+            return;
+        }
+        let mut errors = Vec::new();
+        unescape::unescape_unicode(inner, mode, &mut |range, res| {
+            if let Err(error) = res {
+                errors.push(Error {
+                    span: BSpan::new(from + range.start as u32, from + range.end as u32),
+                    kind: ErrorKind::InvalidEscape {
+                        error,
+                        literal: span,
+                    },
+                });
+            }
+        });
+        for error in errors {
+            self.push_err(error);
+        }
+    }
+
+    /// Parses the arguments of a call such as `sep_by<expr, ",">` after its
+    /// `<`, every argument being a single term
+    fn call(&mut self, name: BSpan) {
+        let call = self.terms.len();
+        self.terms
+            .push(Term::Call(List::new(name, TSpan::empty(call as u32))));
+        self.terms.push(Term::Ident(name));
+        // a `,` still waiting on the argument after it
+        let mut comma = None;
+        let mut expecting = true;
+        let to = loop {
+            let (token, span) = self.until_non_wc();
+            match token.kind {
+                Gt => {
+                    self.dangling_comma(&mut comma);
+                    break span.to;
+                }
+                Comma if !expecting => (comma, expecting) = (Some(span), true),
+                Ident if expecting => {
+                    (comma, expecting) = (None, false);
+                    self.ident(span);
+                }
+                Literal { kind, .. } if expecting && kind.is_string() => {
+                    (comma, expecting) = (None, false);
+                    self.string(kind, span);
+                }
+                OpenParen if expecting => {
+                    (comma, expecting) = (None, false);
+                    let group = self.terms.len();
+                    self.terms
+                        .push(Term::Group(List::new(span, TSpan::empty(group as u32))));
+                    let (to, terms) = self.list(span.from, CloseParen);
+                    if let Term::Group(list) = &mut self.terms[group] {
+                        list.terms.to = terms.to;
+                        list.span.to = to;
+                    }
+                }
+                Literal { kind, .. } if !kind.is_string() => {
+                    self.push_err((InvalidLiteral::Numeric, span));
+                }
+                CloseBrace | Eof => {
+                    self.err_expected(token.kind, span, [Gt]);
+                    self.reverse(token);
+                    break self.terms[self.terms.len() - 1].span().to;
+                }
+                kind if expecting => self.err_expected(kind, span, ARG_EXPECTED),
+                kind => self.err_expected(kind, span, [Comma, Gt]),
+            }
+        };
+        let len = self.terms.len() as u32;
+        if let Term::Call(list) = &mut self.terms[call] {
+            list.terms.to = len;
+            list.span.to = to;
+        }
+    }
+
+    /// Parses the parameters of a rule after its name, such as `<item, sep>`
+    fn params(&mut self) -> TSpan {
+        let first = self.params.len() as u32;
+        let (token, _) = self.until_non_wc();
+        if token.kind != Lt {
+            self.reverse(token);
+            return TSpan::empty(first);
+        }
+        let mut comma = None;
+        let mut expecting = true;
+        loop {
+            let (token, span) = self.until_non_wc();
+            match token.kind {
+                Gt => {
+                    self.dangling_comma(&mut comma);
+                    break;
+                }
+                Comma if !expecting => (comma, expecting) = (Some(span), true),
+                Ident if expecting => {
+                    (comma, expecting) = (None, false);
+                    self.params.push(span);
+                }
+                OpenBrace | CloseBrace | Eof => {
+                    self.err_expected(token.kind, span, [Gt]);
+                    self.reverse(token);
+                    break;
+                }
+                kind if expecting => self.err_expected(kind, span, [Ident, Gt]),
+                kind => self.err_expected(kind, span, [Comma, Gt]),
+            }
+        }
+        TSpan::new(first, self.params.len() as u32)
+    }
+
     fn handle_or(&mut self, ors: &mut Vec<u32>, groups: &[u32]) -> bool {
         let or = match (ors.last(), groups.last()) {
             (Some(&o), Some(&g)) if o > g => o,
@@ -221,6 +463,116 @@ impl<'a> Parser<'a> {
         true
     }
 
+    /// Ends the predicates and labels waiting on a term within the innermost
+    /// group, after that term was parsed
+    ///
+    /// A predicate or label followed by the end of its group or alternative
+    /// is empty.
+    fn close_predicates(&mut self, predicates: &mut Vec<u32>, groups: &[u32]) {
+        while let Some(&predicate) = predicates.last() {
+            if groups.last().is_some_and(|&g| predicate < g) {
+                break;
+            }
+            predicates.pop();
+            let len = self.terms.len() as u32;
+            let term = self.terms.get(predicate as usize + 1).map(Term::span);
+            let (empty, list) = match &mut self.terms[predicate as usize] {
+                Term::And(list) | Term::Not(list) => (ErrorKind::EmptyPredicate, list),
+                Term::Label(_, list) => (ErrorKind::EmptyLabel, list),
+                _ => unreachable!("non predicate found at index {predicate}"),
+            };
+            list.terms.to = len;
+            if let Some(term) = term {
+                list.span.to = term.to;
+            } else {
+                let span = list.span;
+                self.push_err((span, empty));
+            }
+        }
+    }
+
+    /// Reports a `,` with no term after it
+    fn dangling_comma(&mut self, comma: &mut Option<BSpan>) {
+        if let Some(span) = comma.take() {
+            self.push_err((span, ErrorKind::DanglingComma));
+        }
+    }
+
+    /// Reports a rule body that is nothing but predicates
+    ///
+    /// Empty predicates were already reported.
+    fn predicate_rule(&mut self, terms: TSpan) {
+        let mut i = terms.from;
+        let mut span = None::<BSpan>;
+        while i < terms.to {
+            let term = self.terms[i as usize];
+            let (Term::And(list) | Term::Not(list)) = term else {
+                return;
+            };
+            if list.terms.to == i + 1 {
+                return;
+            }
+            let to = term.span().to;
+            span = Some(span.map_or(list.span, |s| s.to(to)));
+            i = term.terms().map_or(i + 1, |t| t.to);
+        }
+        if let Some(span) = span {
+            self.push_err((span, ErrorKind::PredicateRule));
+        }
+    }
+
+    /// Reads the levels of a `@prec` rule from its alternatives after the
+    /// first, such as `| left "+" "-"`
+    fn levels(&mut self, name: BSpan, terms: TSpan) -> TSpan {
+        let first = self.levels.len() as u32;
+        let mut ors = Vec::new();
+        let mut i = terms.from;
+        while i < terms.to {
+            let term = self.terms[i as usize];
+            if let Term::Or(_) = term {
+                ors.push(i);
+                i += 1;
+            } else {
+                i = term.terms().map_or(i + 1, |t| t.to);
+            }
+        }
+        if ors.first().map_or(terms.to, |&or| or) == terms.from {
+            self.push_err((name, ErrorKind::EmptyOperand));
+        }
+        for (n, &or) in ors.iter().enumerate() {
+            let end = ors.get(n + 1).map_or(terms.to, |&next| next);
+            let level = &self.terms[or as usize + 1..end as usize];
+            let Some(&Term::Ident(span)) = level.first() else {
+                // an empty level is reported at its `|`
+                let span = level
+                    .first()
+                    .map_or_else(|| self.terms[or as usize].span(), Term::span);
+                self.push_err((span, ErrorKind::UnknownAssoc));
+                continue;
+            };
+            let Some(assoc) = Assoc::from_name(self.slice(span)) else {
+                self.push_err((span, ErrorKind::UnknownAssoc));
+                continue;
+            };
+            if or + 2 == end {
+                self.push_err((span, ErrorKind::EmptyLevel));
+                continue;
+            }
+            // the last operator may be a group, whose terms come after it
+            let to = self.terms[or as usize + 2..end as usize]
+                .iter()
+                .map(|t| t.span().to)
+                .max()
+                .unwrap_or(span.to);
+            self.levels.push(Level {
+                span: span.to(to),
+                assoc,
+                operators: TSpan::new(or + 2, end),
+            });
+        }
+        TSpan::new(first, self.levels.len() as u32)
+    }
+
     fn pop_group(&mut self, ors: &mut Vec<u32>, groups: &mut Vec<u32>, to: u32) {
         let Some(&group) = groups.last() else { return };
         let len = self.terms.len();
@@ -229,7 +581,7 @@ impl<'a> Parser<'a> {
             list.span.to = to;
         } else {
             unreachable!("group 'or' found at index {group}")
-        };
+        }
         groups.pop();
         while self.handle_or(ors, groups) {}
         if let Some(Term::Or(or)) = self.terms.get_mut(group.saturating_sub(1) as usize) {
@@ -247,7 +599,7 @@ impl<'a> Parser<'a> {
                 err_span = group.span;
             } else {
                 unreachable!("group 'or' found at index {group}")
-            };
+            }
             self.push_err(Error {
                 span: self.span(err_span),
                 kind: ErrorKind::Unterminated,
@@ -269,16 +621,142 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn until_ident(&mut self) -> Option<BSpan> {
+    /// Finds the name of the next rule, along with its kind, where its
+    /// attributes or `@kind` marker start and the attributes before it
+    fn until_ident(&mut self) -> Option<(RuleKind, u32, BSpan, TSpan)> {
+        let first = self.attributes.len() as u32;
+        let mut marked = None;
+        let mut from = None;
         loop {
             let (token, span) = self.until_non_wc();
             match token.kind {
-                Ident => break Some(span),
+                Ident => {
+                    let kind = marked.unwrap_or(RuleKind::Syntactic);
+                    let attributes = TSpan::new(first, self.attributes.len() as u32);
+                    break Some((kind, from.unwrap_or(span.from), span, attributes));
+                }
+                At => {
+                    if let Some(kind) = self.rule_kind(span) {
+                        marked = Some(kind);
+                        from = from.or(Some(span.from));
+                    }
+                }
+                Pound => {
+                    if let Some(attribute) = self.attribute(span) {
+                        self.attributes.push(attribute);
+                        from = from.or(Some(span.from));
+                    }
+                }
                 Eof => return None,
-                _ => self.err_expected(token, [Ident]),
-            };
+                OpenBrace => {
+                    (marked, from) = (None, None);
+                    self.attributes.truncate(first as usize);
+                    self.unnamed_rule(span);
+                }
+                InvalidIdent => self.push_err((span, ErrorKind::InvalidIdent)),
+                kind => self.err_expected(kind, span, RULE_EXPECTED),
+            }
         }
     }
+
+    /// Parses an attribute after a `#`, such as `#[start]` or `#[prec(2)]`
+    fn attribute(&mut self, pound: BSpan) -> Option<Attribute> {
+        let (token, span) = self.until_non_wc();
+        if token.kind != OpenBracket {
+            self.err_expected(token.kind, span, [OpenBracket]);
+            self.attribute_recover(token);
+            return None;
+        }
+        let (token, name) = self.until_non_wc();
+        if token.kind != Ident {
+            self.err_expected(token.kind, name, [Ident]);
+            self.attribute_recover(token);
+            return None;
+        }
+        let (mut token, mut span) = self.until_non_wc();
+        let mut args = None;
+        if token.kind == OpenParen {
+            args = Some(self.attribute_args(span)?);
+            (token, span) = self.until_non_wc();
+        }
+        if token.kind != CloseBracket {
+            match args {
+                Some(_) => self.err_expected(token.kind, span, [CloseBracket]),
+                None => self.err_expected(token.kind, span, [OpenParen, CloseBracket]),
+            }
+            self.attribute_recover(token);
+            return None;
+        }
+        let attribute = Attribute {
+            span: pound.to(span.to),
+            name,
+            args,
+        };
+        if !attribute.is_known(self.src()) {
+            self.push_err((name, ErrorKind::UnknownAttribute));
+        }
+        Some(attribute)
+    }
+
+    /// Skips the arguments of an attribute up to the closing paren,
+    /// returning the span from the first to the last of them
+    fn attribute_args(&mut self, open: BSpan) -> Option<BSpan> {
+        let mut args = None::<BSpan>;
+        loop {
+            let (token, span) = self.until_non_wc();
+            match token.kind {
+                CloseParen => return Some(args.unwrap_or_else(|| BSpan::empty(open.to))),
+                Eof | OpenBrace | CloseBrace | CloseBracket => {
+                    self.err_expected(token.kind, span, [CloseParen]);
+                    self.reverse(token);
+                    return None;
+                }
+                _ => args = Some(args.map_or(span, |args| args.to(span.to))),
+            }
+        }
+    }
+
+    /// Gives back a token that broke off an attribute when it may start or
+    /// end a rule, any other token is skipped
+    fn attribute_recover(&mut self, token: Lexeme) {
+        if matches!(
+            token.kind,
+            Ident | At | Pound | OpenBrace | CloseBrace | Eof
+        ) {
+            self.reverse(token);
+        }
+    }
+
+    /// Parses the kind after an `@`, such as `@lexical`
+    fn rule_kind(&mut self, at: BSpan) -> Option<RuleKind> {
+        let (token, span) = self.advance();
+        if token.kind != Ident {
+            self.push_err((at, ErrorKind::UnknownRuleKind));
+            self.reverse(token);
+            return None;
+        }
+        match self.slice(span) {
+            "lexical" => Some(RuleKind::Lexical),
+            "trivia" => Some(RuleKind::Trivia),
+            "prec" => Some(RuleKind::Precedence),
+            _ => {
+                self.push_err((at.to(span.to), ErrorKind::UnknownRuleKind));
+                None
+            }
+        }
+    }
+
+    /// Parses the body of a rule that is missing its name, then drops its
+    /// terms as no rule refers to them
+    fn unnamed_rule(&mut self, open: BSpan) {
+        let first = self.terms.len();
+        let (close, _) = self.list(open.from, CloseBrace);
+        self.terms.truncate(first);
+        self.push_err(Error {
+            span: open.to(close),
+            kind: ErrorKind::UnnamedRule,
+        });
+    }
 }
 
 const LITERAL: LexKind = LexKind::Literal {
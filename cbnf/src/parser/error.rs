@@ -1,27 +1,99 @@
-use crate::lexer;
+use crate::lexer::{self, unescape::EscapeError};
 use crate::span::BSpan;
 
 // TODO: consider having expected be a nested list of Lexemes
 
-// TODO: add more values, etc, to errors
-
-// TODO: create ErrorKind && add span to error
-
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Error {
     pub span: BSpan,
     pub kind: ErrorKind,
 }
 
-// TODO: UnnamedRule
-
+/// The kinds of errors the parser may produce
+///
+/// Every kind has a stable code, see [`ErrorKind::code`]. Codes are never
+/// reused, new kinds are given the next free code.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ErrorKind {
+    /// `E0001`, `E0002`
     InvalidLit(InvalidLiteral),
+    /// `E0003` a rule missing its closing brace
     UnclosedRule,
+    /// `E0004` a rule name not followed by an opening brace
     UnopenedRule,
+    /// `E0005` a group missing its closing paren
     Unterminated,
-    Expected(Box<[lexer::LexKind]>),
+    /// `E0006` a token that does not fit where it was found
+    Unexpected {
+        found: lexer::LexKind,
+        expected: Box<[lexer::LexKind]>,
+    },
+    /// `E0007` a block comment missing its closing `*/`
+    UnterminatedComment,
+    /// `E0008` a rule body with no name before it
+    UnnamedRule,
+    /// `E0009` a closing paren with no group to close
+    StrayCloseParen,
+    /// `E0010` an invalid escape within a literal, only a warning when the
+    /// escape is still understood
+    InvalidEscape {
+        error: EscapeError,
+        /// The span of the entire literal
+        literal: BSpan,
+    },
+    /// `E0011` an identifier containing invalid characters
+    InvalidIdent,
+    /// `E0012` a `&` or `!` with no term after it
+    EmptyPredicate,
+    /// `E0013` a rule body made up of only predicates, which never consumes
+    /// any input
+    PredicateRule,
+    /// `E0014` a `,` without a term on both sides
+    DanglingComma,
+    /// `E0015` an `@` marker that isn't `@lexical`, `@trivia` or `@prec`
+    UnknownRuleKind,
+    /// `E0016` a second `@trivia` rule
+    DuplicateTrivia {
+        /// The name of the first trivia rule
+        first: BSpan,
+    },
+    /// `E0017` a lexical rule referring to a syntactic one
+    SyntacticInLexical {
+        /// The name of the syntactic rule
+        rule: BSpan,
+    },
+    /// `E0018` an attribute that isn't one of [`crate::ATTRIBUTES`], only a
+    /// warning
+    UnknownAttribute,
+    /// `E0019` a rule used with a different number of arguments than it
+    /// has parameters
+    ArityMismatch {
+        expected: usize,
+        found: usize,
+        /// The name of the rule or parameter
+        rule: BSpan,
+    },
+    /// `E0020` a rule with two parameters of the same name
+    DuplicateParam {
+        /// The first parameter of that name
+        first: BSpan,
+    },
+    /// `E0021` a label such as `lhs:` with no term after it
+    EmptyLabel,
+    /// `E0022` two terms of the same alternative with the same label
+    DuplicateLabel {
+        /// The first label of that name
+        first: BSpan,
+    },
+    /// `E0023` a `@prec` rule with nothing before its first level
+    EmptyOperand,
+    /// `E0024` a level of a `@prec` rule that doesn't start with `left`,
+    /// `right` or `nonassoc`
+    UnknownAssoc,
+    /// `E0025` a level of a `@prec` rule without any operators
+    EmptyLevel,
+    /// `E0026` a byte literal that isn't UTF-8, which text input never holds
+    NonUtf8Bytes,
 }
 
 impl From<(BSpan, ErrorKind)> for Error {
@@ -30,15 +102,76 @@ impl From<(BSpan, ErrorKind)> for Error {
     }
 }
 
+/// A secondary span that helps explain an [`Error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Related {
+    pub span: BSpan,
+    pub message: &'static str,
+}
+
 use ErrorKind::*;
 
+impl ErrorKind {
+    /// A stable code identifying this kind of error
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            InvalidLit(InvalidLiteral::Numeric) => "E0001",
+            InvalidLit(InvalidLiteral::Unterminated) => "E0002",
+            UnclosedRule => "E0003",
+            UnopenedRule => "E0004",
+            Unterminated => "E0005",
+            Unexpected { .. } => "E0006",
+            UnterminatedComment => "E0007",
+            UnnamedRule => "E0008",
+            StrayCloseParen => "E0009",
+            InvalidEscape { .. } => "E0010",
+            InvalidIdent => "E0011",
+            EmptyPredicate => "E0012",
+            PredicateRule => "E0013",
+            DanglingComma => "E0014",
+            UnknownRuleKind => "E0015",
+            DuplicateTrivia { .. } => "E0016",
+            SyntacticInLexical { .. } => "E0017",
+            UnknownAttribute => "E0018",
+            ArityMismatch { .. } => "E0019",
+            DuplicateParam { .. } => "E0020",
+            EmptyLabel => "E0021",
+            DuplicateLabel { .. } => "E0022",
+            EmptyOperand => "E0023",
+            UnknownAssoc => "E0024",
+            EmptyLevel => "E0025",
+            NonUtf8Bytes => "E0026",
+        }
+    }
+
+    /// Whether this doesn't stop the grammar from being used
+    #[must_use]
+    pub const fn is_warning(&self) -> bool {
+        match self {
+            UnknownAttribute => true,
+            InvalidEscape { error, .. } => !error.is_fatal(),
+            _ => false,
+        }
+    }
+}
+
 impl Error {
-    /// `Some(error)` means a non congregated error
     #[must_use]
     pub const fn span(&self) -> BSpan {
         self.span
     }
 
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    #[must_use]
+    pub const fn is_warning(&self) -> bool {
+        self.kind.is_warning()
+    }
+
     #[must_use]
     pub fn message(&self) -> String {
         match &self.kind {
@@ -50,13 +183,42 @@ impl Error {
             UnclosedRule => "Unclosed rule found".into(),
             UnopenedRule => "Unopened rule found".into(),
             Unterminated => "Group not terminated".into(),
-            Expected(acc) => {
-                if acc.len() == 0 {
-                    return "Token not expected".into();
+            UnterminatedComment => "Block comment not terminated".into(),
+            UnnamedRule => "Rule has no name".into(),
+            StrayCloseParen => "Closing parenthesis has no matching group".into(),
+            InvalidEscape { error, .. } => escape_message(*error).into(),
+            InvalidIdent => "Invalid identifier".into(),
+            EmptyPredicate => "Predicate has no term to look ahead at".into(),
+            PredicateRule => "Rule body only looks ahead and never matches any input".into(),
+            DanglingComma => "Comma is missing a term on one side".into(),
+            UnknownRuleKind => "Unknown rule kind".into(),
+            DuplicateTrivia { .. } => "Only one rule may be the trivia".into(),
+            SyntacticInLexical { .. } => "Lexical rule refers to a syntactic rule".into(),
+            UnknownAttribute => "Unknown attribute".into(),
+            ArityMismatch {
+                expected, found, ..
+            } => {
+                let s = |n| if n == 1 { "" } else { "s" };
+                format!(
+                    "Expected {expected} argument{}, found {found}",
+                    s(*expected)
+                )
+            }
+            DuplicateParam { .. } => "Parameter declared twice".into(),
+            EmptyLabel => "Label has no term to name".into(),
+            DuplicateLabel { .. } => "Label used twice in the same alternative".into(),
+            EmptyOperand => "Precedence rule has no operand".into(),
+            UnknownAssoc => "Precedence level has no associativity".into(),
+            EmptyLevel => "Precedence level has no operators".into(),
+            NonUtf8Bytes => "Byte literal is not valid UTF-8".into(),
+            Unexpected { found, expected } => {
+                let mut o = format!("Unexpected {}", found.name());
+                if expected.is_empty() {
+                    return o;
                 }
-                let mut o = String::from("Token not expected, expected one of: [ ");
-                o.push_str(acc[0].name());
-                for l in &acc[1..] {
+                o.push_str(", expected one of: [ ");
+                o.push_str(expected[0].name());
+                for l in &expected[1..] {
                     o.push_str(", ");
                     o.push_str(l.name());
                 }
@@ -65,12 +227,108 @@ impl Error {
             }
         }
     }
+
+    /// A suggestion on how to fix this error
+    #[must_use]
+    pub const fn help(&self) -> Option<&'static str> {
+        Some(match self.kind {
+            InvalidLit(InvalidLiteral::Numeric) => {
+                "wrap the number in quotes to match it literally"
+            }
+            InvalidLit(InvalidLiteral::Unterminated) => "add a closing quote to the literal",
+            UnclosedRule => "add a `}` to close the rule",
+            UnopenedRule => "rules are written as `name { .. }`",
+            Unterminated => "add a `)` to close the group",
+            UnterminatedComment => "add a `*/` to close the comment",
+            UnnamedRule => "add a name before the opening brace",
+            StrayCloseParen => "remove the parenthesis or open a group before it",
+            InvalidIdent => "identifiers may only contain letters, digits and `_`",
+            EmptyPredicate => "add the term to look ahead at after the predicate",
+            PredicateRule => "add the terms to match after the predicate",
+            DanglingComma => "a comma joins the terms on either side of it",
+            UnknownRuleKind => "rules may be marked `@lexical`, `@trivia` or `@prec`",
+            DuplicateTrivia { .. } => "merge the trivia into a single rule",
+            SyntacticInLexical { .. } => "mark the referenced rule `@lexical` as well",
+            ArityMismatch { expected: 0, .. } => "only parameterized rules take arguments",
+            ArityMismatch { .. } => "pass one term for every parameter, like `name<a, b>`",
+            DuplicateParam { .. } => "rename one of the parameters",
+            EmptyLabel => "add the term to name after the label, like `lhs:expr`",
+            DuplicateLabel { .. } => "rename one of the labels",
+            EmptyOperand => "write the operand as the first alternative, like `atom | left \"+\"`",
+            UnknownAssoc => "levels start with `left`, `right` or `nonassoc`",
+            EmptyLevel => "list the operators after the associativity, like `left \"+\" \"-\"`",
+            NonUtf8Bytes => "the input is text, so only bytes that are UTF-8 can match",
+            UnknownAttribute => {
+                "known attributes are `start`, `inline`, `skip`, `token`, `deprecated` and `prec`"
+            }
+            Unexpected { .. } | InvalidEscape { .. } => return None,
+        })
+    }
+
+    /// Other spans that help explain this error
+    #[must_use]
+    pub fn related(&self) -> Vec<Related> {
+        let at = |len, message| Related {
+            span: BSpan::from_len(self.span.from, len),
+            message,
+        };
+        match self.kind {
+            UnclosedRule => vec![at(1, "rule opened here")],
+            Unterminated => vec![at(1, "group opened here")],
+            UnterminatedComment => vec![at(2, "comment opened here")],
+            InvalidEscape { literal, .. } => vec![Related {
+                span: literal,
+                message: "in this literal",
+            }],
+            InvalidLit(_)
+            | UnopenedRule
+            | Unexpected { .. }
+            | UnnamedRule
+            | StrayCloseParen
+            | InvalidIdent
+            | EmptyPredicate
+            | PredicateRule
+            | DanglingComma
+            | UnknownRuleKind
+            | UnknownAttribute
+            | EmptyLabel
+            | EmptyOperand
+            | UnknownAssoc
+            | EmptyLevel
+            | NonUtf8Bytes => Vec::new(),
+            DuplicateTrivia { first } => vec![Related {
+                span: first,
+                message: "first trivia rule declared here",
+            }],
+            SyntacticInLexical { rule } => vec![Related {
+                span: rule,
+                message: "syntactic rule declared here",
+            }],
+            ArityMismatch { rule, .. } => vec![Related {
+                span: rule,
+                message: "declared here",
+            }],
+            DuplicateParam { first } => vec![Related {
+                span: first,
+                message: "first declared here",
+            }],
+            DuplicateLabel { first } => vec![Related {
+                span: first,
+                message: "first used here",
+            }],
+        }
+    }
+
     /// `Some(error)` means a non congregated error
     pub fn congregate(&mut self, other: Self) -> Option<Self> {
-        let Expected(exp) = &mut self.kind else {
+        let Unexpected { expected: exp, .. } = &mut self.kind else {
             return Some(other);
         };
-        let Expected(other_exp) = &other.kind else {
+        let Unexpected {
+            expected: other_exp,
+            ..
+        } = &other.kind
+        else {
             return Some(other);
         };
         #[expect(clippy::suspicious_operation_groupings)]
@@ -82,6 +340,34 @@ impl Error {
     }
 }
 
+const fn escape_message(error: EscapeError) -> &'static str {
+    use EscapeError::*;
+    match error {
+        ZeroChars => "Empty character literal",
+        MoreThanOneChar => "Character literal may only contain one character",
+        LoneSlash => "Escape character without continuation",
+        InvalidEscape => "Unknown character escape",
+        BareCarriageReturn | BareCarriageReturnInRawString => "Bare carriage return in literal",
+        EscapeOnlyChar => "Character must be escaped",
+        TooShortHexEscape => "Numeric character escape is too short",
+        InvalidCharInHexEscape => "Invalid character in numeric character escape",
+        OutOfRangeHexEscape => "Out of range hex escape",
+        NoBraceInUnicodeEscape => "Incorrect unicode escape sequence",
+        InvalidCharInUnicodeEscape => "Invalid character in unicode escape",
+        EmptyUnicodeEscape => "Empty unicode escape",
+        UnclosedUnicodeEscape => "Unterminated unicode escape",
+        LeadingUnderscoreUnicodeEscape => "Invalid start of unicode escape",
+        OverlongUnicodeEscape => "Overlong unicode escape",
+        LoneSurrogateUnicodeEscape => "Invalid unicode character escape",
+        OutOfRangeUnicodeEscape => "Out of range unicode escape",
+        UnicodeEscapeInByte => "Unicode escape in byte string",
+        NonAsciiCharInByte => "Non-ASCII character in byte string",
+        NulInCStr => "Null character in C string",
+        UnskippedWhitespaceWarning => "Whitespace symbol is not skipped",
+        MultipleSkippedLinesWarning => "Multiple lines are skipped by escaped newline",
+    }
+}
+
 impl From<(InvalidLiteral, BSpan)> for Error {
     fn from((kind, span): (InvalidLiteral, BSpan)) -> Self {
         Self {
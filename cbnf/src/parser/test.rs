@@ -1,13 +1,13 @@
 use std::fmt::Display;
 
 use crate::{
-    lexer::LexKind,
+    lexer::{unescape::EscapeError, LexKind},
     parser::{
         error::{Error, ErrorKind::*, InvalidLiteral},
-        LIST_EXPECTED, RULE_EXPECTED,
+        ARG_EXPECTED, LIST_EXPECTED, RULE_EXPECTED,
     },
     span::{BSpan, TSpan},
-    Cbnf, List, Rule,
+    Assoc, Cbnf, List, LitKind, Rule, RuleKind, Term, Value,
 };
 
 use pretty_assertions::assert_eq;
@@ -29,10 +29,10 @@ fn cbnf_print(src: &str, cbnf: &Cbnf) -> String {
         .for_each(|(_, rule)| rule_print(&mut bytes, &mut terms, rule, cbnf));
     cbnf.rules()
         .iter()
-        .flat_map(|(name, r)| {
+        .filter_map(|(name, r)| {
             println!("name: {name}");
             println!("rule: {}", r.span.slice(src));
-            r.expr.clone()
+            r.expr
         })
         .flat_map(|l| {
             println!("expr: {}", l.span.slice(src));
@@ -90,19 +90,20 @@ impl TSpan {
 }
 
 macro_rules! expected {
-    ($($exp: ident, $(($a: expr, $b: expr)),*),*) => {
+    ($($exp: ident, $(($found: ident, $a: expr, $b: expr)),*),*) => {
         [$($(
-            expected!($exp, $a, $b),
+            expected!($exp, $found, $a, $b),
         )*)*]
 
     };
-    ($exp: expr, $a: expr, $b: expr) => {
-        Error { span: ($a, $b).into(), kind: Expected($exp.into()) }
+    ($exp: expr, $found: ident, $a: expr, $b: expr) => {
+        Error {
+            span: ($a, $b).into(),
+            kind: Unexpected { found: LexKind::$found, expected: $exp.into() },
+        }
     };
 }
 
-use expected;
-
 macro_rules! numeric {
     ($(($a: expr, $b: expr)),*) => {
         [$(
@@ -224,26 +225,106 @@ fn cbnf() {
     assert_eq!(
         out,
         "\
-            (28, 72)(28, 35)(36, 72)(42, 54)(55, 70)(57, 70)(73, 115)(73, 85)\
-            (86, 115)(92, 96)(97, 101)(102, 113)(103, 106)(107, 112)(109, 112)\
-            (116, 168)(116, 129)(130, 168)(136, 140)(141, 154)(155, 159)\
-            (160, 166)(162, 166)(169, 202)(169, 175)(176, 202)(183, 187)\
-            (188, 194)(195, 200)(197, 200)(203, 234)(203, 207)(208, 234)\
-            (214, 219)(220, 223)(224, 228)(229, 232)(235, 265)(235, 239)\
-            (240, 265)(246, 250)(251, 263)(252, 256)(257, 262)(259, 262)\
-            (266, 316)(266, 270)(271, 316)(277, 283)(284, 290)(286, 290)\
-            (291, 298)(293, 298)(299, 306)(301, 306)(307, 314)(309, 314)\
-            (317, 343)(317, 322)(323, 343)(329, 332)(333, 337)(338, 341)\
-            (344, 371)(344, 350)(351, 371)(357, 360)(361, 365)(366, 369)\
-            (372, 399)(372, 376)(377, 399)(383, 387)(388, 392)(393, 397)\
-            (400, 431)(400, 405)(406, 431)(412, 415)(416, 429)(432, 484)\
-            (432, 445)(446, 484)(452, 455)(456, 469)(470, 473)(474, 482)\
-            (476, 482)(486, 493)(486, 490)(491, 493)(494, 502)(494, 499)\
-            (500, 502)(503, 509)(503, 506)(507, 509)(510, 516)(510, 513)\
-            (514, 516)[0, 3][1, 3][3, 9][5, 9][7, 9][9, 14][12, 14][14, 18]\
-            [16, 18][18, 22][22, 27][23, 27][25, 27][27, 36][28, 30][30, 32]\
-            [32, 34][34, 36][36, 39][39, 42][42, 45][45, 47][47, 52][50, 52]\
-            [52, 52][52, 52][52, 52][52, 52]"
+            (28, 80)(36, 43)(44, 80)(50, 62)(63, 78)(65, 78)(81, 138)(90, 102)\
+            (103, 138)(109, 113)(114, 124)(114, 119)(120, 123)(125, 136)\
+            (126, 129)(130, 135)(132, 135)(230, 281)(239, 252)(253, 281)\
+            (259, 263)(264, 274)(275, 279)(282, 360)(291, 301)(302, 360)\
+            (308, 341)(309, 322)(323, 340)(325, 330)(326, 330)(331, 336)\
+            (332, 336)(337, 340)(342, 352)(353, 358)(355, 358)(361, 402)\
+            (370, 376)(377, 402)(383, 387)(388, 394)(395, 400)(397, 400)\
+            (403, 464)(403, 407)(408, 464)(414, 420)(421, 426)(427, 441)\
+            (428, 434)(435, 440)(437, 440)(442, 445)(446, 458)(447, 451)\
+            (452, 457)(454, 457)(459, 462)(465, 501)(465, 471)(472, 501)\
+            (478, 481)(482, 495)(483, 488)(489, 494)(491, 494)(496, 499)\
+            (502, 539)(502, 507)(508, 539)(514, 519)(520, 537)(521, 524)\
+            (525, 530)(531, 536)(533, 536)(540, 591)(540, 546)(547, 591)\
+            (553, 576)(554, 563)(564, 575)(566, 569)(570, 575)(577, 583)\
+            (584, 589)(586, 589)(592, 654)(592, 601)(602, 654)(608, 611)\
+            (612, 615)(616, 621)(622, 648)(623, 626)(627, 637)(627, 632)\
+            (633, 636)(638, 641)(642, 647)(644, 647)(649, 652)(655, 720)\
+            (655, 659)(660, 720)(666, 674)(675, 699)(676, 679)(680, 692)\
+            (681, 685)(686, 691)(688, 691)(693, 698)(695, 698)(700, 717)\
+            (702, 705)(706, 718)(707, 711)(712, 717)(714, 717)(721, 771)\
+            (721, 729)(730, 771)(736, 740)(741, 769)(742, 753)(743, 746)\
+            (747, 752)(749, 752)(754, 762)(763, 768)(765, 768)(772, 849)\
+            (772, 776)(777, 849)(783, 789)(790, 796)(792, 796)(797, 804)\
+            (799, 804)(805, 811)(807, 811)(812, 819)(814, 819)(820, 827)\
+            (822, 827)(828, 839)(830, 839)(840, 847)(842, 847)(850, 878)\
+            (850, 855)(856, 878)(862, 867)(868, 871)(872, 876)(879, 918)\
+            (879, 883)(884, 918)(890, 895)(896, 899)(900, 912)(901, 905)\
+            (906, 911)(908, 911)(913, 916)(919, 953)(919, 923)(924, 953)\
+            (930, 934)(935, 951)(936, 939)(940, 944)(945, 950)(947, 950)\
+            (954, 988)(954, 963)(964, 988)(970, 981)(971, 974)(975, 980)\
+            (977, 980)(982, 986)(989, 1023)(989, 994)(995, 1023)(1001, 1004)\
+            (1005, 1017)(1006, 1010)(1011, 1016)(1013, 1016)(1018, 1021)\
+            (1024, 1093)(1033, 1039)(1040, 1093)(1046, 1063)(1047, 1050)\
+            (1051, 1056)(1053, 1056)(1057, 1062)(1059, 1062)(1064, 1067)\
+            (1068, 1079)(1068, 1074)(1075, 1078)(1080, 1083)(1084, 1091)\
+            (1086, 1091)(1094, 1138)(1103, 1107)(1108, 1138)(1114, 1118)\
+            (1119, 1131)(1119, 1125)(1126, 1130)(1132, 1136)(1210, 1284)\
+            (1219, 1225)(1233, 1284)(1239, 1262)(1240, 1244)(1245, 1248)\
+            (1249, 1261)(1251, 1257)(1252, 1257)(1258, 1261)(1263, 1276)\
+            (1263, 1269)(1270, 1275)(1277, 1282)(1279, 1282)(1285, 1325)\
+            (1294, 1299)(1300, 1325)(1306, 1309)(1310, 1323)(1326, 1399)\
+            (1335, 1348)(1349, 1399)(1355, 1358)(1359, 1372)(1373, 1376)\
+            (1377, 1397)(1379, 1382)(1383, 1393)(1383, 1388)(1389, 1392)\
+            (1394, 1397)(1435, 1488)(1444, 1449)(1455, 1488)(1461, 1465)\
+            (1462, 1465)(1466, 1469)(1470, 1480)(1470, 1475)(1476, 1479)\
+            (1481, 1486)(1483, 1486)(1490, 1496)(1490, 1493)(1494, 1496)\
+            (1497, 1505)(1497, 1502)(1503, 1505)(1506, 1512)(1506, 1509)\
+            (1510, 1512)(1513, 1519)(1513, 1516)(1517, 1519)[0, 3][1, 3]\
+            [3, 11][4, 7][7, 11][9, 11][11, 14][14, 25][14, 22][16, 22]\
+            [17, 19][19, 21][23, 25][25, 29][27, 29][29, 41][31, 35][33, 35]\
+            [36, 40][38, 40][41, 47][42, 46][44, 46][47, 53][48, 53][51, 53]\
+            [53, 61][53, 58][55, 58][59, 61][61, 73][64, 72][66, 69][70, 72]\
+            [73, 88][74, 82][76, 80][78, 80][80, 82][82, 88][84, 88][86, 88]\
+            [88, 97][89, 97][90, 94][92, 94][95, 97][97, 112][98, 100]\
+            [100, 102][102, 104][104, 106][106, 108][108, 110][110, 112]\
+            [112, 115][115, 122][117, 121][119, 121][122, 128][123, 128]\
+            [126, 128][128, 133][128, 132][130, 132][133, 139][134, 138]\
+            [136, 138][139, 152][139, 145][141, 143][143, 145][146, 149]\
+            [150, 152][152, 157][153, 156][157, 169][157, 164][160, 164]\
+            [161, 163][164, 167][167, 169][169, 171][171, 180][174, 180]\
+            [176, 179][180, 188][180, 182][183, 186][186, 188][188, 188]\
+            [188, 188][188, 188][188, 188]\
+        "
+    );
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+}
+
+#[test]
+fn predicates() {
+    let src = r#"yeah { &a !"b" | !(c | &d) e }"#;
+    let cbnf = Cbnf::parse(src);
+    let out = cbnf_print(src, &cbnf);
+    assert_eq!(
+        out,
+        "(0, 30)(0, 4)(5, 30)(7, 9)(8, 9)(10, 14)(11, 14)(15, 25)(17, 26)\
+         (18, 26)(19, 20)(21, 25)(23, 25)(24, 25)(27, 28)\
+         [0, 12][0, 2][2, 4][4, 11][5, 11][6, 11][8, 11][9, 11]"
+    );
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+}
+#[test]
+fn nested_predicates() {
+    let src = "yeah { !&a b }";
+    let cbnf = Cbnf::parse(src);
+    let out = cbnf_print(src, &cbnf);
+    assert_eq!(
+        out,
+        "(0, 14)(0, 4)(5, 14)(7, 10)(8, 10)(9, 10)(11, 12)[0, 4][0, 3][1, 3]"
+    );
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+}
+
+#[test]
+fn adjacent() {
+    let src = r#"yeah { a , "." ,(b) }"#;
+    let cbnf = Cbnf::parse(src);
+    let out = cbnf_print(src, &cbnf);
+    assert_eq!(
+        out,
+        "(0, 21)(0, 4)(5, 21)(7, 8)(9, 10)(11, 14)(15, 16)(16, 19)(17, 18)[0, 6][4, 6]"
     );
     assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
 }
@@ -278,6 +359,27 @@ fn unclosed_rule_group() {
     assert_eq!(actual, expected);
 }
 #[test]
+fn unclosed_group_in_or() {
+    let src = "a { x | (";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((8, 9).into(), Unterminated)),
+        Error::from(((2, 9).into(), UnclosedRule)),
+    ]);
+    assert_eq!(actual, expected);
+    // the alternative ends with the group
+    let out = cbnf_print(src, &cbnf);
+    assert_eq!(
+        out,
+        "(0, 9)(0, 1)(2, 9)(4, 5)(6, 9)(8, 9)[0, 3][1, 3][2, 3]"
+    );
+
+    let src = "a { x | ( y | (z";
+    let cbnf = Cbnf::parse(src);
+    assert_eq!(cbnf.errors.len(), 3, "{:#?}", cbnf.errors);
+}
+#[test]
 fn int_or_float() {
     let src = "yeah { 12_u8 0o100 0b120i99 1f32 12.34f32 1e3 }";
     let cbnf = Cbnf::parse(src);
@@ -297,15 +399,18 @@ fn not_rule_or_ident() {
     let src = "yeah { \\ #\\@// \\ #\\\n}\n\\ #\\@// \\ #\\\n";
     let cbnf = Cbnf::parse(src);
     let actual = format!("{:#?}", cbnf.errors);
-    let expected = debug!(expected![
+    let mut expected = expected![
         LIST_EXPECTED,
-        (7, 8),
-        (9, 12),
+        (Unknown, 7, 8),
+        (Pound, 9, 12),
         RULE_EXPECTED,
-        (22, 23),
-        (24, 27)
-    ]);
-    assert_eq!(actual, expected);
+        (Unknown, 22, 23)
+    ]
+    .to_vec();
+    // a `#` outside of a rule starts an attribute, and an `@` its kind
+    expected.push(expected!([LexKind::OpenBracket], Unknown, 25, 26));
+    expected.push(Error::from(((26, 27).into(), UnknownRuleKind)));
+    assert_eq!(actual, debug!(expected));
 }
 #[test]
 fn dollar_after_rule() {
@@ -314,7 +419,7 @@ fn dollar_after_rule() {
     let actual = format!("{:#?}", cbnf.errors);
     let expected = debug!([
         Error::from(((0, 4).into(), UnopenedRule)),
-        Error::from(((5, 6).into(), Expected([LexKind::Ident].into())))
+        Error::from(((5, 6).into(), expected!(RULE_EXPECTED, Dollar, 5, 6).kind))
     ]);
     assert_eq!(actual, expected);
 }
@@ -340,3 +445,603 @@ fn unterm_string() {
     ]);
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn stray_close_paren() {
+    let src = "yeah { a ) b }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([Error::from(((9, 10).into(), StrayCloseParen))]);
+    assert_eq!(actual, expected);
+    assert_eq!(cbnf.terms().len(), 2);
+}
+
+#[test]
+fn unnamed_rule() {
+    let src = "{ a ( } yeah { b }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((4, 7).into(), Unterminated)),
+        Error::from(((0, 7).into(), UnnamedRule)),
+    ]);
+    assert_eq!(actual, expected);
+    assert_eq!(cbnf.rules().len(), 1);
+    assert!(cbnf.rules().contains_key("yeah"));
+    // the terms of the unnamed rule are dropped
+    assert_eq!(cbnf.terms().len(), 1);
+}
+
+#[test]
+fn invalid_ident() {
+    let src = "yeah { a🦀 }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([Error::from(((7, 12).into(), InvalidIdent))]);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn unterminated_comment() {
+    let src = "yeah { a } /* b";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([Error::from(((11, 15).into(), UnterminatedComment))]);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn error_codes() {
+    let errors = [
+        InvalidLit(InvalidLiteral::Numeric),
+        InvalidLit(InvalidLiteral::Unterminated),
+        UnclosedRule,
+        UnopenedRule,
+        Unterminated,
+        Unexpected {
+            found: LexKind::Dollar,
+            expected: RULE_EXPECTED.into(),
+        },
+        UnterminatedComment,
+        UnnamedRule,
+        StrayCloseParen,
+        InvalidEscape {
+            error: EscapeError::InvalidEscape,
+            literal: BSpan::default(),
+        },
+        InvalidIdent,
+        EmptyPredicate,
+        PredicateRule,
+        DanglingComma,
+        UnknownRuleKind,
+        DuplicateTrivia {
+            first: BSpan::default(),
+        },
+        SyntacticInLexical {
+            rule: BSpan::default(),
+        },
+        UnknownAttribute,
+        ArityMismatch {
+            expected: 0,
+            found: 0,
+            rule: BSpan::default(),
+        },
+        DuplicateParam {
+            first: BSpan::default(),
+        },
+        EmptyLabel,
+        DuplicateLabel {
+            first: BSpan::default(),
+        },
+        EmptyOperand,
+        UnknownAssoc,
+        EmptyLevel,
+        NonUtf8Bytes,
+    ];
+    let codes: Vec<_> = errors
+        .iter()
+        .map(crate::parser::error::ErrorKind::code)
+        .collect();
+    let expected: Vec<_> = (1..=errors.len()).map(|i| format!("E{i:04}")).collect();
+    assert_eq!(codes, expected);
+}
+
+#[test]
+fn related_spans() {
+    let src = "yeah { ( ";
+    let cbnf = Cbnf::parse(src);
+    let related: Vec<_> = cbnf.errors.iter().flat_map(Error::related).collect();
+    assert_eq!(
+        related.iter().map(|r| r.span).collect::<Vec<_>>(),
+        [BSpan::new(7, 8), BSpan::new(5, 6)]
+    );
+}
+
+#[test]
+fn empty_predicate() {
+    let src = "yeah { a & | (b !) & }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((9, 10).into(), EmptyPredicate)),
+        Error::from(((16, 17).into(), EmptyPredicate)),
+        Error::from(((19, 20).into(), EmptyPredicate)),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn predicate_rule() {
+    let src = "yeah { !a &(b c) } fine { !a b } empty { & }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((7, 16).into(), PredicateRule)),
+        Error::from(((41, 42).into(), EmptyPredicate)),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn dangling_comma() {
+    let src = "yeah { , a , | b , , c ( d , ) , }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((7, 8).into(), DanglingComma)),
+        Error::from(((11, 12).into(), DanglingComma)),
+        Error::from(((19, 20).into(), DanglingComma)),
+        Error::from(((27, 28).into(), DanglingComma)),
+        Error::from(((31, 32).into(), DanglingComma)),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn rule_kinds() {
+    let src = "@lexical num { digit digit } @trivia ws { \" \" } expr { num }";
+    let cbnf = Cbnf::parse(src);
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+    let kinds: Vec<_> = ["num", "ws", "expr"]
+        .iter()
+        .map(|name| cbnf.rules[*name].kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [RuleKind::Lexical, RuleKind::Trivia, RuleKind::Syntactic]
+    );
+    assert_eq!(cbnf.rules["num"].span, BSpan::new(0, 28));
+    assert_eq!(cbnf.trivia().map(|(name, _)| name), Some("ws"));
+}
+#[test]
+fn rule_kind_errors() {
+    let src = "@ { } @tokn a { b } @trivia c { } @trivia d { } @lexical e { a f } f { }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((0, 1).into(), UnknownRuleKind)),
+        Error::from(((2, 5).into(), UnnamedRule)),
+        Error::from(((6, 11).into(), UnknownRuleKind)),
+        Error::from((
+            (42, 43).into(),
+            DuplicateTrivia {
+                first: BSpan::new(28, 29)
+            }
+        )),
+        Error::from((
+            (61, 62).into(),
+            SyntacticInLexical {
+                rule: BSpan::new(12, 13)
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn attributes() {
+    let src = "#[start] #[prec( 2 , left )] @lexical a { b } #[ inline() ] b { }";
+    let cbnf = Cbnf::parse(src);
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+    let a = &cbnf.rules["a"];
+    assert_eq!(a.span, BSpan::new(0, 45));
+    assert_eq!(a.kind, RuleKind::Lexical);
+    let attributes: Vec<_> = cbnf
+        .attributes(a)
+        .iter()
+        .map(|a| {
+            (
+                a.span.slice(src),
+                a.name.slice(src),
+                a.args.map(|s| s.slice(src)),
+            )
+        })
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            ("#[start]", "start", None),
+            ("#[prec( 2 , left )]", "prec", Some("2 , left")),
+        ]
+    );
+    let b = &cbnf.rules["b"];
+    assert_eq!(cbnf.attributes(b)[0].args, Some(BSpan::empty(56)));
+    assert!(cbnf.attribute(b, "inline", src).is_some());
+    assert!(cbnf.attribute(b, "start", src).is_none());
+}
+#[test]
+fn attribute_errors() {
+    let src = "#[fast] a { } # b { } #[c d { } #[e(] f { } #[g] { } h { }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((2, 6).into(), UnknownAttribute)),
+        expected!([LexKind::OpenBracket], Ident, 16, 17),
+        expected!([LexKind::OpenParen, LexKind::CloseBracket], Ident, 26, 27),
+        expected!([LexKind::CloseParen], CloseBracket, 36, 37),
+        expected!(RULE_EXPECTED, CloseBracket, 36, 37),
+        Error::from(((46, 47).into(), UnknownAttribute)),
+        Error::from(((49, 52).into(), UnnamedRule)),
+    ]);
+    assert_eq!(actual, expected);
+    assert!(cbnf.has_errors());
+    assert!(cbnf.errors[0].is_warning());
+    // the attributes of an unnamed rule are dropped
+    assert!(cbnf.attributes(&cbnf.rules["h"]).is_empty());
+    assert!(cbnf.attributes(&cbnf.rules["a"]).len() == 1);
+    assert!(!Cbnf::parse("#[fast] a { }").has_errors());
+}
+#[test]
+fn calls() {
+    let src = r#"a<x, y> { x sep_by<(b | c), ","> y } sep_by<item, sep> { item sep<> }"#;
+    let cbnf = Cbnf::parse(src);
+    let params: Vec<_> = cbnf
+        .params(&cbnf.rules["a"])
+        .iter()
+        .map(|p| p.slice(src))
+        .collect();
+    assert_eq!(params, ["x", "y"]);
+    let terms: Vec<_> = cbnf
+        .terms
+        .iter()
+        .map(|t| {
+            let kind = match t {
+                Term::Call(_) => "call",
+                Term::Group(_) => "group",
+                Term::Or(_) => "or",
+                Term::Ident(_) => "ident",
+                Term::Literal(..) => "literal",
+                _ => "other",
+            };
+            (kind, t.span().slice(src), t.terms().map(|t| (t.from, t.to)))
+        })
+        .collect();
+    assert_eq!(
+        terms,
+        [
+            ("ident", "x", None),
+            ("call", r#"sep_by<(b | c), ",">"#, Some((1, 8))),
+            ("ident", "sep_by", None),
+            ("group", "(b | c)", Some((3, 7))),
+            ("ident", "b", None),
+            ("or", "| c", Some((5, 7))),
+            ("ident", "c", None),
+            ("literal", r#"",""#, None),
+            ("ident", "y", None),
+            ("ident", "item", None),
+            ("call", "sep<>", Some((10, 12))),
+            ("ident", "sep", None),
+        ]
+    );
+    // a parameter is passed without arguments, like `sep<>` or `sep`
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+}
+#[test]
+fn arity() {
+    let src = "a<x, x> { b<x> c<a, a> b c } b<y> { } c { }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from((
+            (5, 6).into(),
+            DuplicateParam {
+                first: BSpan::new(2, 3)
+            }
+        )),
+        Error::from((
+            (15, 22).into(),
+            ArityMismatch {
+                expected: 0,
+                found: 2,
+                rule: BSpan::new(38, 39)
+            }
+        )),
+        // a parameterized rule can't be passed on its own
+        Error::from((
+            (17, 18).into(),
+            ArityMismatch {
+                expected: 2,
+                found: 0,
+                rule: BSpan::new(0, 1)
+            }
+        )),
+        Error::from((
+            (20, 21).into(),
+            ArityMismatch {
+                expected: 2,
+                found: 0,
+                rule: BSpan::new(0, 1)
+            }
+        )),
+        Error::from((
+            (23, 24).into(),
+            ArityMismatch {
+                expected: 1,
+                found: 0,
+                rule: BSpan::new(29, 30)
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn call_errors() {
+    let src = "a { b<c d, , 1> e<f,> g<(h> } i<j k, > { } l<";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        expected!([LexKind::Comma, LexKind::Gt], Ident, 8, 9),
+        expected!(ARG_EXPECTED, Comma, 11, 12),
+        numeric!(13, 14),
+        Error::from(((9, 10).into(), DanglingComma)),
+        Error::from(((19, 20).into(), DanglingComma)),
+        Error::from(((24, 27).into(), Unterminated)),
+        expected!([LexKind::Comma, LexKind::Gt], Ident, 34, 35),
+        Error::from(((35, 36).into(), DanglingComma)),
+        expected!([LexKind::Gt], Eof, 45, 45),
+        Error::from(((43, 44).into(), UnopenedRule)),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn labels() {
+    let src = r#"a { lhs:b "+" rhs : !c op:(d | e) }"#;
+    let cbnf = Cbnf::parse(src);
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+    let labels: Vec<_> = cbnf
+        .terms
+        .iter()
+        .filter_map(|t| match t {
+            Term::Label(name, list) => Some((
+                name.slice(src),
+                list.span().slice(src),
+                (list.terms().from, list.terms().to),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            ("lhs", "lhs:b", (0, 2)),
+            ("rhs", "rhs : !c", (3, 6)),
+            ("op", "op:(d | e)", (6, 11)),
+        ]
+    );
+}
+#[test]
+fn label_errors() {
+    let src = "a { x:b x:c | x:d (y:e | y:f) y:g } h { i: | j: } k { l:(m: n) m: }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((40, 42).into(), EmptyLabel)),
+        Error::from(((45, 47).into(), EmptyLabel)),
+        Error::from(((63, 65).into(), EmptyLabel)),
+        Error::from((
+            (8, 9).into(),
+            DuplicateLabel {
+                first: BSpan::new(4, 5)
+            }
+        )),
+        Error::from((
+            (30, 31).into(),
+            DuplicateLabel {
+                first: BSpan::new(19, 20)
+            }
+        )),
+        Error::from((
+            (63, 64).into(),
+            DuplicateLabel {
+                first: BSpan::new(57, 58)
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn precedence() {
+    let src = r#"@prec expr { atom | left "+" "-" | right ("^" | "**") } atom { }"#;
+    let cbnf = Cbnf::parse(src);
+    assert!(cbnf.errors.is_empty(), "{:#?}", cbnf.errors);
+    let expr = &cbnf.rules["expr"];
+    assert_eq!(expr.kind, RuleKind::Precedence);
+    let operand = cbnf.operand(expr);
+    assert_eq!(cbnf.terms_at(operand)[0].span().slice(src), "atom");
+    let levels: Vec<_> = cbnf
+        .levels(expr)
+        .iter()
+        .map(|level| {
+            let operators: Vec<_> = cbnf
+                .operators(level)
+                .map(|i| cbnf.terms[i].span().slice(src))
+                .collect();
+            (level.assoc, level.span.slice(src), operators)
+        })
+        .collect();
+    assert_eq!(
+        levels,
+        [
+            (Assoc::Left, r#"left "+" "-""#, vec![r#""+""#, r#""-""#]),
+            (
+                Assoc::Right,
+                r#"right ("^" | "**")"#,
+                vec![r#"("^" | "**")"#]
+            ),
+        ]
+    );
+    assert!(cbnf.levels(&cbnf.rules["atom"]).is_empty());
+}
+#[test]
+fn precedence_errors() {
+    let src = r#"@prec a { | left "+" } @prec b { c | up "+" | "-" | | left }"#;
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((6, 7).into(), EmptyOperand)),
+        Error::from(((37, 39).into(), UnknownAssoc)),
+        Error::from(((46, 49).into(), UnknownAssoc)),
+        Error::from(((50, 51).into(), UnknownAssoc)),
+        Error::from(((54, 58).into(), EmptyLevel)),
+    ]);
+    assert_eq!(actual, expected);
+    assert_eq!(cbnf.levels(&cbnf.rules["a"]).len(), 1);
+    assert!(cbnf.levels(&cbnf.rules["b"]).is_empty());
+}
+#[test]
+fn literal_modifiers() {
+    let src = r#"a { "x" b"\x00\xff" i"select" r"y" b"\q\u{41}" i"\xff" }"#;
+    let cbnf = Cbnf::parse(src);
+    let kinds: Vec<_> = cbnf
+        .terms
+        .iter()
+        .filter_map(|t| match t {
+            Term::Literal(span, kind) => Some((span.slice(src), *kind)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (r#""x""#, LitKind::Str),
+            (r#"b"\x00\xff""#, LitKind::Bytes),
+            (r#"i"select""#, LitKind::Insensitive),
+            (r#"r"y""#, LitKind::Str),
+            (r#"b"\q\u{41}""#, LitKind::Bytes),
+            (r#"i"\xff""#, LitKind::Insensitive),
+        ]
+    );
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from(((8, 19).into(), NonUtf8Bytes)),
+        Error::from((
+            (37, 39).into(),
+            InvalidEscape {
+                error: EscapeError::InvalidEscape,
+                literal: BSpan::new(35, 46),
+            }
+        )),
+        Error::from((
+            (39, 45).into(),
+            InvalidEscape {
+                error: EscapeError::UnicodeEscapeInByte,
+                literal: BSpan::new(35, 46),
+            }
+        )),
+        Error::from((
+            (49, 53).into(),
+            InvalidEscape {
+                error: EscapeError::OutOfRangeHexEscape,
+                literal: BSpan::new(47, 54),
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+}
+#[test]
+fn escapes() {
+    let src = "a { \"\\q\" 'ab' r\"\\q\" \"x\\\n\n  y\" \"\\u{41}\\t\" }";
+    let cbnf = Cbnf::parse(src);
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from((
+            (5, 7).into(),
+            InvalidEscape {
+                error: EscapeError::InvalidEscape,
+                literal: BSpan::new(4, 8),
+            }
+        )),
+        Error::from((
+            (10, 12).into(),
+            InvalidEscape {
+                error: EscapeError::MoreThanOneChar,
+                literal: BSpan::new(9, 13),
+            }
+        )),
+        Error::from((
+            (22, 27).into(),
+            InvalidEscape {
+                error: EscapeError::MultipleSkippedLinesWarning,
+                literal: BSpan::new(20, 29),
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+    assert!(!cbnf.errors[1].is_warning());
+    assert!(cbnf.errors[2].is_warning());
+    let values: Vec<_> = cbnf.terms.iter().map(|t| t.value(src)).collect();
+    assert_eq!(
+        values,
+        [
+            None,
+            None,
+            Some(Value::Text("\\q".into())),
+            Some(Value::Text("xy".into())),
+            Some(Value::Text("A\t".into())),
+        ]
+    );
+    let bytes = r#"b { b"\x00\xff" }"#;
+    let cbnf = Cbnf::parse(bytes);
+    assert_eq!(
+        cbnf.terms[0].value(bytes),
+        Some(Value::Bytes(vec![0, 0xff]))
+    );
+}
+#[test]
+fn char_values() {
+    let src = r#"a { '{' '\'' "b" '\u{41}' '' 'ab' }"#;
+    let cbnf = Cbnf::parse(src);
+    let values: Vec<_> = cbnf
+        .terms
+        .iter()
+        .map(|t| match t {
+            Term::Literal(_, kind) => (*kind, t.value(src)),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(
+        values,
+        [
+            (LitKind::Char, Some(Value::Char('{'))),
+            (LitKind::Char, Some(Value::Char('\''))),
+            (LitKind::Str, Some(Value::Text("b".into()))),
+            (LitKind::Char, Some(Value::Char('A'))),
+            (LitKind::Char, None),
+            (LitKind::Char, None),
+        ]
+    );
+    let actual = format!("{:#?}", cbnf.errors);
+    let expected = debug!([
+        Error::from((
+            (26, 28).into(),
+            InvalidEscape {
+                error: EscapeError::ZeroChars,
+                literal: BSpan::new(26, 28),
+            }
+        )),
+        Error::from((
+            (30, 32).into(),
+            InvalidEscape {
+                error: EscapeError::MoreThanOneChar,
+                literal: BSpan::new(29, 33),
+            }
+        )),
+    ]);
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,379 @@
+//! parsing expression grammar semantics for a [`Grammar`]
+//!
+//! [`Peg`] reads `|` as ordered choice: the first alternative that matches
+//! is taken, and the ones after it are never tried. Every term matches at
+//! most one way, so the result is a single tree, and the lookahead
+//! predicates `&term` and `!term` check what follows without consuming it.
+//!
+//! Results are memoized by rule and position (packrat parsing), so parsing
+//! takes linear time. Left recursive rules such as `e { e "+" n | n }` are
+//! parsed by growing a seed: the rule first fails when it reaches itself,
+//! and is then parsed again using its previous match for as long as that
+//! makes the match longer.
+//!
+//! Rules with an empty body are matched by [`Builtins`], taking their
+//! longest match. Whitespace and trivia are skipped like in
+//! [`crate::interp`].
+//!
+//! ```
+//! use cbnf::{grammar::Grammar, peg::Peg, Cbnf};
+//!
+//! // with ordered choice `"a"` always wins over `"a" "b"`
+//! let src = r#"s { ("a" | "a" "b") "c" }"#;
+//! let grammar = Grammar::new(&Cbnf::parse(src), src);
+//! assert!(Peg::new(&grammar).recognize(0, "ac"));
+//! assert!(!Peg::new(&grammar).recognize(0, "abc"));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    grammar::{Grammar, Node, NodeKind, RuleId},
+    interp::{label, Builtins, Cst, CstKind, DefaultBuiltins, ParseError, Scan, Trivia},
+    span::BSpan,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Parses input with the rules of a [`Grammar`] as a PEG, see the
+/// [module docs](self)
+pub struct Peg<'g> {
+    grammar: &'g Grammar,
+    builtins: Box<dyn Builtins + 'g>,
+    skip_whitespace: bool,
+}
+
+impl<'g> Peg<'g> {
+    /// A parser using [`DefaultBuiltins`] that skips whitespace
+    #[must_use]
+    pub fn new(grammar: &'g Grammar) -> Self {
+        Self {
+            grammar,
+            builtins: Box::new(DefaultBuiltins),
+            skip_whitespace: true,
+        }
+    }
+
+    #[must_use]
+    pub fn builtins(mut self, builtins: impl Builtins + 'g) -> Self {
+        self.builtins = Box::new(builtins);
+        self
+    }
+
+    /// Whether whitespace is skipped before literals and at the end of input
+    #[must_use]
+    pub const fn skip_whitespace(mut self, skip: bool) -> Self {
+        self.skip_whitespace = skip;
+        self
+    }
+
+    #[must_use]
+    pub const fn grammar(&self) -> &'g Grammar {
+        self.grammar
+    }
+
+    /// Whether all of `input` matches `start`
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    #[must_use]
+    pub fn recognize(&self, start: RuleId, input: &str) -> bool {
+        self.parse(start, input).is_ok()
+    }
+
+    /// Parses all of `input` as `start`
+    ///
+    /// # Errors
+    /// When `start` doesn't match, or doesn't match all of `input`.
+    ///
+    /// # Panics
+    /// If `start` is out of bounds.
+    pub fn parse(&self, start: RuleId, input: &str) -> Result<Cst, ParseError> {
+        let mut run = Run {
+            peg: self,
+            input,
+            trivia: Trivia::new(self.grammar, &*self.builtins, self.skip_whitespace, input),
+            lexical: false,
+            memo: HashMap::new(),
+            approx: HashMap::new(),
+            heads: Vec::new(),
+            furthest: 0,
+            expected: Vec::new(),
+        };
+        if let Some(cst) = run.rule(start, 0).0 {
+            let end = run.skip(cst.span.to as usize);
+            if end == input.len() {
+                return Ok(cst);
+            }
+            run.fail(end, || "end of input".into());
+        }
+        run.expected.sort();
+        run.expected.dedup();
+        Err(ParseError {
+            pos: run.furthest,
+            found: input[run.furthest..].chars().next(),
+            expected: run.expected,
+        })
+    }
+}
+
+/// No dependency on a rule that is still being parsed
+const SETTLED: usize = usize::MAX;
+
+/// A rule being parsed at a position
+struct Head {
+    key: (RuleId, usize),
+    /// Whether the rule reached itself
+    recursive: bool,
+    /// The match used where the rule reaches itself
+    seed: Option<Cst>,
+}
+
+/// The state of a single parse
+struct Run<'a, 'g> {
+    peg: &'a Peg<'g>,
+    input: &'a str,
+    trivia: Trivia<'a>,
+    /// Whether the rule being matched is lexical, so nothing is skipped
+    lexical: bool,
+    /// Final matches of rules at a position
+    memo: HashMap<(RuleId, usize), Option<Cst>>,
+    /// Matches that depend on the seed of a head, with the depth of the
+    /// outermost such head
+    approx: HashMap<(RuleId, usize), (Option<Cst>, usize)>,
+    /// Rules being parsed, innermost last
+    heads: Vec<Head>,
+    furthest: usize,
+    expected: Vec<String>,
+}
+
+impl Run<'_, '_> {
+    fn skip(&self, pos: usize) -> usize {
+        if self.lexical {
+            return pos;
+        }
+        self.trivia.skip(pos)
+    }
+
+    /// Where a reference to `rule` starts matching, lexical rules don't
+    /// skip anything themselves
+    fn start(&self, rule: RuleId, pos: usize) -> usize {
+        let grammar = self.peg.grammar;
+        match grammar.rules[rule].body {
+            Some(_) if !grammar.is_syntactic(rule) => self.skip(pos),
+            _ => pos,
+        }
+    }
+
+    /// Records that something was expected but not found at `pos`
+    fn fail(&mut self, pos: usize, expected: impl FnOnce() -> String) {
+        if pos > self.furthest {
+            self.furthest = pos;
+            self.expected.clear();
+        }
+        if pos == self.furthest {
+            self.expected.push(expected());
+        }
+    }
+
+    /// The match of `rule` at `pos`
+    ///
+    /// Also returns the depth of the outermost head the match depends on,
+    /// or [`SETTLED`] if the match is final.
+    fn rule(&mut self, rule: RuleId, pos: usize) -> (Option<Cst>, usize) {
+        let key = (rule, pos);
+        let Some(body) = &self.peg.grammar.rules[rule].body else {
+            return (self.builtin(rule, pos), SETTLED);
+        };
+        if let Some(cst) = self.memo.get(&key) {
+            return (cst.clone(), SETTLED);
+        }
+        if let Some(depth) = self.heads.iter().position(|h| h.key == key) {
+            let head = &mut self.heads[depth];
+            head.recursive = true;
+            return (head.seed.clone(), depth);
+        }
+        if let Some((cst, low)) = self.approx.get(&key) {
+            return (cst.clone(), *low);
+        }
+        let depth = self.heads.len();
+        self.heads.push(Head {
+            key,
+            recursive: false,
+            seed: None,
+        });
+        let outer = self.lexical;
+        self.lexical = self.peg.grammar.rules[rule].kind.is_lexical();
+        let (mut cst, mut low) = self.body(rule, body, pos);
+        if self.heads[depth].recursive {
+            // grow the seed for as long as the match gets longer
+            while let Some(seed) = &cst {
+                let end = seed.span.to;
+                self.heads[depth].seed.clone_from(&cst);
+                self.approx.retain(|_, (_, l)| *l < depth);
+                let (next, l) = self.body(rule, body, pos);
+                low = low.min(l);
+                match next {
+                    Some(next) if next.span.to > end => cst = Some(next),
+                    _ => break,
+                }
+            }
+        }
+        self.lexical = outer;
+        self.heads.pop();
+        if low >= depth {
+            self.approx.retain(|_, (_, l)| *l < depth);
+            self.memo.insert(key, cst.clone());
+            return (cst, SETTLED);
+        }
+        self.approx.insert(key, (cst.clone(), low));
+        (cst, low)
+    }
+
+    fn builtin(&mut self, rule: RuleId, pos: usize) -> Option<Cst> {
+        let name = self.peg.grammar.name(rule);
+        let at = Scan {
+            input: self.input,
+            pos,
+            skipped: self.skip(pos),
+        };
+        let Some(end) = self.peg.builtins.scan(name, at).into_iter().max() else {
+            self.fail(at.skipped, || name.to_owned());
+            return None;
+        };
+        Some(Cst {
+            kind: CstKind::Builtin(rule),
+            span: span(at.skipped.min(end), end),
+            children: Vec::new(),
+            choices: Vec::new(),
+            label: None,
+        })
+    }
+
+    fn body(&mut self, rule: RuleId, body: &Node, pos: usize) -> (Option<Cst>, usize) {
+        let mut cst = Cst {
+            kind: CstKind::Rule(rule),
+            span: span(pos, pos),
+            children: Vec::new(),
+            choices: Vec::new(),
+            label: None,
+        };
+        let (end, low) = self.node(body, pos, &mut cst);
+        let Some(end) = end else {
+            return (None, low);
+        };
+        let from = cst.children.first().map_or(end, |c| c.span.from as usize);
+        cst.span = span(from.min(end), end);
+        (Some(cst), low)
+    }
+
+    /// Matches `node` at `pos`, adding its trees to `parent`
+    ///
+    /// Returns the end of the match and the depth of the outermost head it
+    /// depends on. Nothing is added to `parent` when `node` doesn't match.
+    fn node(&mut self, node: &Node, pos: usize, parent: &mut Cst) -> (Option<usize>, usize) {
+        let mark = parent.children.len();
+        let (end, low) = self.node_kind(node, pos, parent);
+        if end.is_some() {
+            label(&mut parent.children[mark..], node.label.as_deref());
+        }
+        (end, low)
+    }
+
+    fn node_kind(&mut self, node: &Node, pos: usize, parent: &mut Cst) -> (Option<usize>, usize) {
+        match &node.kind {
+            NodeKind::Nil => (Some(pos), SETTLED),
+            NodeKind::Literal(_) | NodeKind::Char(_) | NodeKind::Insensitive(_) => {
+                let at = self.skip(pos);
+                let Some(len) = node.kind.prefix(&self.input[at..]) else {
+                    self.fail(at, || node.kind.quoted().expect("a literal"));
+                    return (None, SETTLED);
+                };
+                parent.children.push(Cst {
+                    kind: CstKind::Literal,
+                    span: span(at, at + len),
+                    children: Vec::new(),
+                    choices: Vec::new(),
+                    label: None,
+                });
+                (Some(at + len), SETTLED)
+            }
+            NodeKind::Rule(rule) => {
+                let (cst, low) = self.rule(*rule, self.start(*rule, pos));
+                let end = cst.map(|cst| {
+                    let end = cst.span.to as usize;
+                    parent.children.push(cst);
+                    end
+                });
+                (end, low)
+            }
+            NodeKind::Unknown(_) => (None, SETTLED),
+            NodeKind::Adjacent => {
+                if self.skip(pos) == pos {
+                    (Some(pos), SETTLED)
+                } else {
+                    self.fail(pos, || "no whitespace".into());
+                    (None, SETTLED)
+                }
+            }
+            NodeKind::Seq(nodes) => {
+                let mark = (parent.children.len(), parent.choices.len());
+                let mut end = pos;
+                let mut low = SETTLED;
+                for node in nodes {
+                    let (next, l) = self.node(node, end, parent);
+                    low = low.min(l);
+                    let Some(next) = next else {
+                        parent.children.truncate(mark.0);
+                        parent.choices.truncate(mark.1);
+                        return (None, low);
+                    };
+                    end = next;
+                }
+                (Some(end), low)
+            }
+            NodeKind::Alt(alt, nodes) => {
+                let mut low = SETTLED;
+                for (i, node) in nodes.iter().enumerate() {
+                    parent.choices.push((*alt, i));
+                    let (end, l) = self.node(node, pos, parent);
+                    low = low.min(l);
+                    if end.is_some() {
+                        return (end, low);
+                    }
+                    parent.choices.pop();
+                }
+                (None, low)
+            }
+            NodeKind::And(node) => {
+                let (end, low) = self.node(node, pos, &mut scratch(parent));
+                (end.map(|_| pos), low)
+            }
+            NodeKind::Not(node) => {
+                // what `node` expected is no help when it must not match
+                let furthest = self.furthest;
+                let expected = std::mem::take(&mut self.expected);
+                let (end, low) = self.node(node, pos, &mut scratch(parent));
+                (self.furthest, self.expected) = (furthest, expected);
+                (end.is_none().then_some(pos), low)
+            }
+        }
+    }
+}
+
+/// A tree to throw away the matches of a predicate in
+const fn scratch(parent: &Cst) -> Cst {
+    Cst {
+        kind: parent.kind,
+        span: parent.span,
+        children: Vec::new(),
+        choices: Vec::new(),
+        label: None,
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn span(from: usize, to: usize) -> BSpan {
+    BSpan::new(from as u32, to as u32)
+}
@@ -0,0 +1,196 @@
+use expect_test::{expect, Expect};
+use pretty_assertions::assert_eq;
+
+use crate::{
+    earley::Earley,
+    grammar::{test::grammar, Grammar},
+    interp::Interpreter,
+    peg::Peg,
+    Cbnf,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, input: &str, expect: Expect) {
+    let grammar = grammar(src);
+    let actual = match Peg::new(&grammar).parse(0, input) {
+        Ok(cst) => cst.snapshot(&grammar, input),
+        Err(e) => e.to_string(),
+    };
+    expect.assert_eq(&actual);
+}
+
+fn accepts(src: &str, input: &str) -> bool {
+    let grammar = grammar(src);
+    let peg = Peg::new(&grammar);
+    peg.recognize(0, input)
+}
+
+#[test]
+fn sequence() {
+    check(
+        "pair { '(' ident ',' ident ')' } ident {}",
+        "( a , b )",
+        expect![[r#"
+            (pair
+              "("
+              (ident "a")
+              ","
+              (ident "b")
+              ")")"#]],
+    );
+}
+
+#[test]
+fn ordered_choice() {
+    let src = r#"s { ("a" | "a" "b") "c" }"#;
+    assert!(accepts(src, "ac"));
+    assert!(!accepts(src, "abc"));
+    let grammar = grammar(src);
+    assert!(Interpreter::new(&grammar).recognize(0, "abc"));
+
+    // the longer alternative has to come first
+    assert!(accepts(r#"s { ("a" "b" | "a") "c" }"#, "abc"));
+    assert!(accepts(r#"s { ("a" "b" | "a") "c" }"#, "ac"));
+}
+
+#[test]
+fn no_backtracking_into_rules() {
+    // `as` takes every `a`, leaving none for the last one
+    let src = r#"s { as "a" } as { "a" as | nil }"#;
+    assert!(!accepts(src, "a a a"));
+    let grammar = grammar(src);
+    assert!(Interpreter::new(&grammar).recognize(0, "a a a"));
+}
+
+#[test]
+fn left_recursion() {
+    check(
+        r#"e { e "+" n | e "-" n | n } n { "1" | "2" }"#,
+        "1 + 2 - 1",
+        expect![[r#"
+            (e
+              (e
+                (e
+                  (n
+                    "1"))
+                "+"
+                (n
+                  "2"))
+              "-"
+              (n
+                "1"))"#]],
+    );
+}
+
+#[test]
+fn indirect_left_recursion() {
+    let src = r#"a { b "x" | "y" } b { c } c { a | "z" }"#;
+    assert!(accepts(src, "y"));
+    assert!(accepts(src, "y x x"));
+    assert!(accepts(src, "z x"));
+    assert!(!accepts(src, "x"));
+}
+
+#[test]
+fn predicates() {
+    // an identifier that isn't the keyword `if`
+    let not = grammar(r#"s { !kw ident } kw { "if" } ident {}"#);
+    let peg = Peg::new(&not);
+    assert!(peg.recognize(0, "foo"));
+    assert!(!peg.recognize(0, "if"));
+    // the other engines check predicates too
+    let interp = Interpreter::new(&not);
+    assert!(interp.recognize(0, "foo"));
+    assert!(!interp.recognize(0, "if"));
+    let earley = Earley::new(&not);
+    assert!(earley.recognize(0, "foo"));
+    assert!(!earley.recognize(0, "if"));
+
+    // predicates match nothing
+    let and = grammar(r#"s { &kw ident } kw { "if" } ident {}"#);
+    let peg = Peg::new(&and);
+    assert!(peg.recognize(0, "iffy"));
+    assert!(!peg.recognize(0, "foo"));
+    let tree = peg.parse(0, "iffy").unwrap();
+    assert_eq!(tree.snapshot(&and, "iffy"), "(s\n  (ident \"iffy\"))");
+    assert_eq!(Earley::new(&and).parse(0, "iffy").unwrap().tree(), tree);
+    assert!(!Earley::new(&and).recognize(0, "foo"));
+}
+
+#[test]
+fn adjacent() {
+    let src = r#"path { ident , "." , ident } ident {}"#;
+    assert!(accepts(src, "a.b"));
+    assert!(accepts(src, " a.b "));
+    assert!(!accepts(src, "a .b"));
+    assert!(!accepts(src, "a. b"));
+}
+
+#[test]
+fn lexical() {
+    let src = r#"pair { num num } @lexical num { "1" "2" }"#;
+    assert!(accepts(src, "12 12"));
+    assert!(accepts(src, " 12 12 "));
+    assert!(!accepts(src, "1 2 12"));
+    assert!(!accepts(src, "12 1 2"));
+}
+
+#[test]
+fn trivia() {
+    let src = r#"list { "a" "b" } @trivia comment { "/" "/" ident } ident {}"#;
+    assert!(accepts(src, "a b"));
+    assert!(accepts(src, "//x a //y//z b //w"));
+    assert!(!accepts(src, "a / /x b"));
+    // a syntactic rule within the trivia, an error that is still lowered,
+    // skips nothing
+    let src = r##"r { "a" "b" } @trivia ws { s } s { "#" "x" }"##;
+    let grammar = Grammar::new(&Cbnf::parse(src), src);
+    let peg = Peg::new(&grammar);
+    assert!(peg.recognize(0, "a #x b"));
+    assert!(!peg.recognize(0, "a # x b"));
+}
+
+#[test]
+fn errors() {
+    check(
+        r#"a { "x" ("y" | "z") }"#,
+        "x w",
+        expect![[r#"unexpected 'w' at byte 2, expected one of: "y", "z""#]],
+    );
+    check(
+        r#"a { "x" }"#,
+        "x x",
+        expect![[r"unexpected 'x' at byte 2, expected one of: end of input"]],
+    );
+    // what a negative predicate expected isn't reported
+    let not = grammar(r#"s { !kw "(" } kw { "if" }"#);
+    let e = Peg::new(&not).parse(0, "x").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        r#"unexpected 'x' at byte 0, expected one of: "(""#
+    );
+}
+
+#[test]
+fn like_the_interpreter() {
+    // on grammars where no alternative is a prefix of a later one
+    let src =
+        r#"list { "[" (items | nil) "]" } items { item:ident ("," rest:items | nil) } ident {}"#;
+    let grammar = grammar(src);
+    let peg = Peg::new(&grammar);
+    let interp = Interpreter::new(&grammar);
+    for input in ["[]", "[a]", "[a, b, c]", "[a,]", "[a b]"] {
+        assert_eq!(peg.parse(0, input), interp.parse(0, input), "{input:?}");
+    }
+}
+
+#[test]
+fn precedence() {
+    let src = r#"@prec expr { ident | left "+" "-" | right "^" } ident {}"#;
+    let grammar = grammar(src);
+    let peg = Peg::new(&grammar);
+    let interp = Interpreter::new(&grammar);
+    for input in ["a", "a + b - c", "a ^ b ^ c + d", "a + ^ b"] {
+        assert_eq!(peg.parse(0, input), interp.parse(0, input), "{input:?}");
+    }
+}
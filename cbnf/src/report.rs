@@ -0,0 +1,435 @@
+//! compiler style diagnostic rendering
+//!
+//! Turns an [`Error`] (or any hand built [`Diagnostic`]) into a snippet of
+//! the source with carets under the offending span:
+//!
+//! ```text
+//! error[E0005]: Group not terminated
+//!  --> grammar.cbnf:1:8
+//!   |
+//! 1 | yeah { ( }
+//!   |        ^^^ Group not terminated
+//!   |        - group opened here
+//!   |
+//!   = help: add a `)` to close the group
+//! ```
+
+use std::fmt::Write;
+
+use crate::{parser::error::Error, span::BSpan};
+
+#[cfg(test)]
+mod test;
+
+/// How severe a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Level {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+            Self::Help => "help",
+        }
+    }
+    const fn color(self) -> &'static str {
+        match self {
+            Self::Error => RED,
+            Self::Warning => YELLOW,
+            Self::Note => GREEN,
+            Self::Help => CYAN,
+        }
+    }
+}
+
+/// A span within the source with an attached message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: BSpan,
+    pub message: String,
+    /// Primary labels are drawn with `^`, secondary ones with `-`
+    pub primary: bool,
+}
+
+impl Label {
+    #[must_use]
+    pub fn primary(span: BSpan, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+    #[must_use]
+    pub fn secondary(span: BSpan, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+/// A renderable message about some part of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// A stable code such as `E0001`
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    /// Trailing `= note: ..` lines
+    pub notes: Vec<String>,
+    /// Trailing `= help: ..` lines
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+    #[must_use]
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Level::Error, message)
+    }
+    #[must_use]
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Level::Warning, message)
+    }
+    #[must_use]
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+    #[must_use]
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+    #[must_use]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    #[must_use]
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+    /// The span of the first primary label
+    #[must_use]
+    pub fn span(&self) -> Option<BSpan> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+
+    /// Builds a diagnostic from a parser error
+    #[must_use]
+    pub fn from_error(error: &Error) -> Self {
+        let message = error.message();
+        let level = if error.is_warning() {
+            Level::Warning
+        } else {
+            Level::Error
+        };
+        let mut diag = Self::new(level, &message)
+            .with_code(error.code())
+            .with_label(Label::primary(error.span(), message));
+        for related in error.related() {
+            diag.labels
+                .push(Label::secondary(related.span, related.message));
+        }
+        diag.help.extend(error.help().map(String::from));
+        diag
+    }
+}
+
+/// Maps byte positions to one-based lines and columns
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// byte offset of the start of every line
+    starts: Vec<u32>,
+}
+
+impl LineIndex {
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn new(src: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i as u32 + 1))
+            .collect();
+        Self { starts }
+    }
+
+    /// The zero-based line containing `pos`
+    #[must_use]
+    pub fn line(&self, pos: u32) -> usize {
+        self.starts.partition_point(|&s| s <= pos) - 1
+    }
+
+    /// The byte span of the given zero-based line, without its line break
+    #[must_use]
+    pub fn line_span(&self, line: usize, src: &str) -> BSpan {
+        let from = self.starts[line];
+        let to = self
+            .starts
+            .get(line + 1)
+            .map_or(src.len(), |&next| next as usize - 1);
+        let text = &src[from as usize..to];
+        #[allow(clippy::cast_possible_truncation)]
+        BSpan::from_len(from, text.trim_end_matches('\r').len() as u32)
+    }
+
+    /// The one-based line and (char based) column of `pos`
+    ///
+    /// A `pos` within a char counts as the start of that char.
+    #[must_use]
+    pub fn line_col(&self, pos: u32, src: &str) -> (usize, usize) {
+        let line = self.line(pos);
+        let start = self.starts[line] as usize;
+        let mut pos = (pos as usize).min(src.len());
+        while !src.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        (line + 1, src[start..pos].chars().count() + 1)
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders [`Diagnostic`]s against a source file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { color: false }
+    }
+    /// Enables ANSI color codes in the output
+    #[must_use]
+    pub const fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(self, out: &mut String, style: &str, text: &str) {
+        if self.color {
+            out.push_str(style);
+            out.push_str(text);
+            out.push_str(RESET);
+        } else {
+            out.push_str(text);
+        }
+    }
+
+    /// Renders every error of a parsed file, one after another
+    #[must_use]
+    pub fn render_errors(self, file: &str, src: &str, errors: &[Error]) -> String {
+        let lines = LineIndex::new(src);
+        let mut out = String::new();
+        for error in errors {
+            let diag = Diagnostic::from_error(error);
+            self.write(&mut out, file, src, &lines, &diag);
+            out.push('\n');
+        }
+        out
+    }
+
+    #[must_use]
+    pub fn render(self, file: &str, src: &str, diag: &Diagnostic) -> String {
+        let mut out = String::new();
+        self.write(&mut out, file, src, &LineIndex::new(src), diag);
+        out
+    }
+
+    fn write(self, out: &mut String, file: &str, src: &str, lines: &LineIndex, diag: &Diagnostic) {
+        match &diag.code {
+            Some(code) => {
+                let level = format!("{}[{code}]", diag.level.name());
+                self.paint(out, diag.level.color(), &level);
+            }
+            None => self.paint(out, diag.level.color(), diag.level.name()),
+        }
+        out.push_str(": ");
+        self.paint(out, BOLD, &diag.message);
+        out.push('\n');
+
+        let mut labels: Vec<&Label> = diag.labels.iter().collect();
+        labels.sort_by_key(|l| (lines.line(l.span.from), !l.primary, l.span.from));
+        // enough room for the largest line number drawn
+        let width = labels
+            .iter()
+            .map(|l| lines.line(l.span.to) + 1)
+            .max()
+            .map_or(0, |n| n.to_string().len());
+        let snippet = Snippet {
+            renderer: self,
+            src,
+            lines,
+            width,
+            level: diag.level,
+        };
+
+        if let Some(span) = diag.span().or_else(|| labels.first().map(|l| l.span)) {
+            let (line, col) = lines.line_col(span.from, src);
+            out.push_str(&" ".repeat(width));
+            self.paint(out, BLUE, "-->");
+            let _ = writeln!(out, " {file}:{line}:{col}");
+            snippet.gutter(out);
+            out.push('\n');
+        }
+        let mut marks: Vec<Mark<'_>> = labels.iter().flat_map(|l| snippet.marks(l)).collect();
+        marks.sort_by_key(|m| (m.line, !m.label.primary, m.span.from));
+        let mut prev_line = None;
+        for mark in &marks {
+            if prev_line.is_some_and(|p| p + 1 < mark.line) {
+                out.push_str("...\n");
+            }
+            if prev_line != Some(mark.line) {
+                snippet.source_line(out, mark.line);
+            }
+            snippet.underline(out, mark);
+            prev_line = Some(mark.line);
+        }
+
+        if diag.notes.is_empty() && diag.help.is_empty() {
+            return;
+        }
+        if !labels.is_empty() {
+            snippet.gutter(out);
+            out.push('\n');
+        }
+        let notes = diag.notes.iter().map(|n| ("note", n));
+        for (kind, text) in notes.chain(diag.help.iter().map(|h| ("help", h))) {
+            out.push_str(&" ".repeat(width + 1));
+            self.paint(out, BLUE, "=");
+            out.push(' ');
+            self.paint(out, BOLD, kind);
+            // continuation lines line up with the start of the text
+            let indent = format!("\n{}", " ".repeat(width + kind.len() + 5));
+            let _ = writeln!(out, ": {}", text.replace('\n', &indent));
+        }
+    }
+}
+
+struct Snippet<'a> {
+    renderer: Renderer,
+    src: &'a str,
+    lines: &'a LineIndex,
+    /// width of the line number gutter
+    width: usize,
+    level: Level,
+}
+
+/// The part of a [`Label`] drawn under a single line
+struct Mark<'a> {
+    label: &'a Label,
+    line: usize,
+    span: BSpan,
+    /// Multiline labels only show their message on the last line
+    message: bool,
+}
+
+impl Snippet<'_> {
+    /// Splits a label into the marks drawn under each line
+    ///
+    /// Multiline labels underline the rest of their first line and the start
+    /// of their last one.
+    fn marks<'a>(&self, label: &'a Label) -> Vec<Mark<'a>> {
+        let first = self.lines.line(label.span.from);
+        let mut last = self.lines.line(label.span.to);
+        // a span ending right after a line break ends on the previous line
+        if last > first && self.lines.line_span(last, self.src).from == label.span.to {
+            last -= 1;
+        }
+        let first_line = self.lines.line_span(first, self.src);
+        if last == first {
+            return vec![Mark {
+                label,
+                line: first,
+                span: first_line.from(label.span.from).to(label.span.to),
+                message: true,
+            }];
+        }
+        let last_line = self.lines.line_span(last, self.src);
+        vec![
+            Mark {
+                label,
+                line: first,
+                span: first_line.from(label.span.from),
+                message: false,
+            },
+            Mark {
+                label,
+                line: last,
+                span: last_line.to(label.span.to.min(last_line.to)),
+                message: true,
+            },
+        ]
+    }
+
+    fn gutter(&self, out: &mut String) {
+        out.push_str(&" ".repeat(self.width + 1));
+        self.renderer.paint(out, BLUE, "|");
+    }
+
+    fn source_line(&self, out: &mut String, line: usize) {
+        let number = format!("{:>width$} |", line + 1, width = self.width);
+        self.renderer.paint(out, BLUE, &number);
+        let text = self.lines.line_span(line, self.src).slice(self.src);
+        if !text.is_empty() {
+            out.push(' ');
+            out.push_str(text);
+        }
+        out.push('\n');
+    }
+
+    fn underline(&self, out: &mut String, mark: &Mark<'_>) {
+        let line = self.lines.line_span(mark.line, self.src);
+        // keep tabs so that carets line up with the source line
+        let indent: String = BSpan::new(line.from, mark.span.from)
+            .slice(self.src)
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = mark.span.slice(self.src).chars().count().max(1);
+        let (symbol, style) = if mark.label.primary {
+            ("^", self.level.color())
+        } else {
+            ("-", BLUE)
+        };
+        self.gutter(out);
+        out.push(' ');
+        out.push_str(&indent);
+        self.renderer.paint(out, style, &symbol.repeat(width));
+        if mark.message && !mark.label.message.is_empty() {
+            out.push(' ');
+            self.renderer.paint(out, style, &mark.label.message);
+        }
+        out.push('\n');
+    }
+}
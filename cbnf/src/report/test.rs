@@ -0,0 +1,145 @@
+use expect_test::{expect, Expect};
+
+use crate::{
+    report::{Diagnostic, Label, LineIndex, Renderer},
+    span::BSpan,
+    Cbnf,
+};
+
+#[allow(clippy::needless_pass_by_value)]
+fn check(src: &str, expect: Expect) {
+    let cbnf = Cbnf::parse(src);
+    let actual = Renderer::new().render_errors("test.cbnf", src, cbnf.errors());
+    expect.assert_eq(&actual);
+}
+
+#[test]
+fn line_col() {
+    let src = "ab\ncd\r\n\nef";
+    let lines = LineIndex::new(src);
+    assert_eq!(lines.line_col(0, src), (1, 1));
+    assert_eq!(lines.line_col(2, src), (1, 3));
+    assert_eq!(lines.line_col(3, src), (2, 1));
+    assert_eq!(lines.line_col(7, src), (3, 1));
+    assert_eq!(lines.line_col(8, src), (4, 1));
+    assert_eq!(lines.line_col(10, src), (4, 3));
+    assert_eq!(lines.line_span(1, src).slice(src), "cd");
+    assert_eq!(lines.line_span(2, src).slice(src), "");
+    // within a multi-byte char
+    let src = "aé\nb";
+    let lines = LineIndex::new(src);
+    assert_eq!(lines.line_col(2, src), (1, 2));
+    assert_eq!(lines.line_col(3, src), (1, 3));
+}
+
+#[test]
+fn unclosed_group() {
+    check(
+        "yeah { ( }",
+        expect![[r"
+            error[E0005]: Group not terminated
+             --> test.cbnf:1:8
+              |
+            1 | yeah { ( }
+              |        ^^^ Group not terminated
+              |        - group opened here
+              |
+              = help: add a `)` to close the group
+
+        "]],
+    );
+}
+
+#[test]
+fn unclosed_rule() {
+    check(
+        "yeah {\n  a\n  b\n  c\n",
+        expect![[r"
+            error[E0003]: Unclosed rule found
+             --> test.cbnf:1:6
+              |
+            1 | yeah {
+              |      ^
+              |      - rule opened here
+            ...
+            4 |   c
+              | ^^^ Unclosed rule found
+              |
+              = help: add a `}` to close the rule
+
+        "]],
+    );
+}
+
+#[test]
+fn many_errors() {
+    check(
+        "yeah { 12 }\n\nno $",
+        expect![[r"
+            error[E0001]: Numbers not allowed
+             --> test.cbnf:1:8
+              |
+            1 | yeah { 12 }
+              |        ^^ Numbers not allowed
+              |
+              = help: wrap the number in quotes to match it literally
+
+            error[E0004]: Unopened rule found
+             --> test.cbnf:3:1
+              |
+            3 | no $
+              | ^^ Unopened rule found
+              |
+              = help: rules are written as `name { .. }`
+
+            error[E0006]: Unexpected $, expected one of: [ ident, @, # ]
+             --> test.cbnf:3:4
+              |
+            3 | no $
+              |    ^ Unexpected $, expected one of: [ ident, @, # ]
+
+        "]],
+    );
+}
+
+#[test]
+fn notes_and_color() {
+    let src = "a { b }";
+    let diag = Diagnostic::warning("unused rule")
+        .with_label(Label::primary(BSpan::new(0, 1), "never referenced"))
+        .with_note("rules are public by default");
+    let actual = Renderer::new().color(true).render("x.cbnf", src, &diag);
+    assert!(
+        actual.starts_with("\x1b[1;33mwarning\x1b[0m: "),
+        "{actual:?}"
+    );
+    assert!(actual.contains("\x1b[1;33m^\x1b[0m"), "{actual:?}");
+    let plain = Renderer::new().render("x.cbnf", src, &diag);
+    expect![[r"
+        warning: unused rule
+         --> x.cbnf:1:1
+          |
+        1 | a { b }
+          | ^ never referenced
+          |
+          = note: rules are public by default
+    "]]
+    .assert_eq(&plain);
+}
+
+#[test]
+fn warnings() {
+    check(
+        "#[fast] a { b }",
+        expect![[r"
+            warning[E0018]: Unknown attribute
+             --> test.cbnf:1:3
+              |
+            1 | #[fast] a { b }
+              |   ^^^^ Unknown attribute
+              |
+              = help: known attributes are `start`, `inline`, `skip`, `token`, `deprecated` and `prec`
+
+        "]],
+    );
+}
@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 use crate::lexer::Lexeme;
@@ -91,3 +93,21 @@ pub fn nfc_normalize(string: &str) -> String {
         _ => string.chars().nfc().collect::<String>(),
     }
 }
+
+/// `s` as a quoted JSON string
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => _ = write!(out, "\\u{:04x}", u32::from(c)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}